@@ -0,0 +1,300 @@
+//! Turning subtitle files into text: byte order marks, guessing legacy
+//! encodings, and Windows code pages.
+
+use chardetng::EncodingDetector;
+pub use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use encoding_rs::{
+    BIG5, EUC_KR, GB18030, GBK, IBM866, ISO_8859_2, ISO_8859_5, ISO_8859_7, KOI8_R, KOI8_U,
+    MACINTOSH, SHIFT_JIS, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252, WINDOWS_1253, WINDOWS_1254,
+    WINDOWS_1255, WINDOWS_1256, WINDOWS_1257, WINDOWS_1258, WINDOWS_874,
+};
+
+/// What a file's encoding was found to be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Detection {
+    pub encoding: &'static Encoding,
+    /// The file starts with a byte order mark.
+    pub bom: bool,
+    /// `false` when the encoding is a guess from byte statistics that could
+    /// well be wrong, as with short files.
+    pub confident: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decoded {
+    pub text: String,
+    pub detection: Detection,
+    /// Some bytes weren't valid in the encoding and became U+FFFD.
+    pub had_errors: bool,
+}
+
+// UTF-16 without a byte order mark: text that is mostly ASCII has a zero in
+// every other byte
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zeros = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    if odd * 10 >= pairs * 7 && even * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even * 10 >= pairs * 7 && odd * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Finds the encoding of `bytes` from its byte order mark, or failing that
+/// by guessing from its contents. Valid UTF-8 is always taken as UTF-8.
+pub fn detect(bytes: &[u8]) -> Detection {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Detection {
+            encoding,
+            bom: true,
+            confident: true,
+        };
+    }
+    if let Some(encoding) = utf16_without_bom(bytes) {
+        return Detection {
+            encoding,
+            bom: false,
+            confident: true,
+        };
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Detection {
+            encoding: UTF_8,
+            bom: false,
+            confident: true,
+        };
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let (encoding, confident) = detector.guess_assess(None, false);
+    Detection {
+        encoding,
+        bom: false,
+        confident,
+    }
+}
+
+/// Decodes `bytes` in the encoding [`detect`] finds, without the byte order
+/// mark.
+pub fn decode(bytes: &[u8]) -> Decoded {
+    decode_with(bytes, detect(bytes))
+}
+
+/// Decodes `bytes` in `encoding`, unless they start with a byte order mark,
+/// which wins.
+pub fn decode_as(bytes: &[u8], encoding: &'static Encoding) -> Decoded {
+    let detection = match Encoding::for_bom(bytes) {
+        Some((encoding, _)) => Detection {
+            encoding,
+            bom: true,
+            confident: true,
+        },
+        None => Detection {
+            encoding,
+            bom: false,
+            confident: true,
+        },
+    };
+    decode_with(bytes, detection)
+}
+
+fn decode_with(bytes: &[u8], detection: Detection) -> Decoded {
+    let (text, had_errors) =
+        detection
+            .encoding
+            .decode_without_bom_handling(match Encoding::for_bom(bytes) {
+                Some((_, length)) => &bytes[length..],
+                None => bytes,
+            });
+    Decoded {
+        text: text.into_owned(),
+        detection,
+        had_errors,
+    }
+}
+
+/// Encodes `text` in `encoding`, with a byte order mark if `bom` is set and
+/// the encoding is UTF-8 or UTF-16. Characters the encoding lacks are written
+/// as HTML numeric references, and the result says if there were any.
+pub fn encode(text: &str, encoding: &'static Encoding, bom: bool) -> (Vec<u8>, bool) {
+    let mut out = Vec::new();
+    // encoding_rs only decodes UTF-16, so it is done here
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little = encoding == UTF_16LE;
+        let units = bom.then_some(0xfeff).into_iter().chain(text.encode_utf16());
+        for unit in units {
+            out.extend(if little {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            });
+        }
+        return (out, false);
+    }
+    if bom && encoding == UTF_8 {
+        out.extend([0xef, 0xbb, 0xbf]);
+    }
+    let (bytes, _, unmappable) = encoding.encode(text);
+    out.extend_from_slice(&bytes);
+    (out, unmappable)
+}
+
+/// The encoding of a Windows code page, such as 1252 or 932.
+pub fn code_page(code_page: u16) -> Option<&'static Encoding> {
+    Some(match code_page {
+        866 => IBM866,
+        874 => WINDOWS_874,
+        932 => SHIFT_JIS,
+        936 => GBK,
+        949 => EUC_KR,
+        950 => BIG5,
+        1200 => UTF_16LE,
+        1201 => UTF_16BE,
+        1250 => WINDOWS_1250,
+        1251 => WINDOWS_1251,
+        1252 => WINDOWS_1252,
+        1253 => WINDOWS_1253,
+        1254 => WINDOWS_1254,
+        1255 => WINDOWS_1255,
+        1256 => WINDOWS_1256,
+        1257 => WINDOWS_1257,
+        1258 => WINDOWS_1258,
+        10000 => MACINTOSH,
+        20866 => KOI8_R,
+        21866 => KOI8_U,
+        28592 => ISO_8859_2,
+        28595 => ISO_8859_5,
+        28597 => ISO_8859_7,
+        54936 => GB18030,
+        65001 => UTF_8,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, little: bool) -> Vec<u8> {
+        encode(text, if little { UTF_16LE } else { UTF_16BE }, false).0
+    }
+
+    #[test]
+    fn detection() {
+        let russian = "Съешь же ещё этих мягких французских булок, да выпей чаю. ".repeat(4);
+        let (cp1251, _, _) = WINDOWS_1251.encode(&russian);
+        let japanese = "日本語の字幕ファイルです。こんにちは、世界。".repeat(4);
+        let (sjis, _, _) = SHIFT_JIS.encode(&japanese);
+        // bytes, encoding, bom, confident
+        for (bytes, encoding, bom, confident) in [
+            (b"\xef\xbb\xbfabc".to_vec(), UTF_8, true, true),
+            (b"\xff\xfea\0b\0".to_vec(), UTF_16LE, true, true),
+            (b"\xfe\xff\0a\0b".to_vec(), UTF_16BE, true, true),
+            (utf16("[Script Info]\r\n", true), UTF_16LE, false, true),
+            (utf16("[Script Info]\r\n", false), UTF_16BE, false, true),
+            // too short to tell
+            (b"a\0".to_vec(), UTF_8, false, true),
+            (
+                "plain ASCII and ünïcödé".as_bytes().to_vec(),
+                UTF_8,
+                false,
+                true,
+            ),
+            (Vec::new(), UTF_8, false, true),
+            (cp1251.into_owned(), WINDOWS_1251, false, true),
+            (sjis.into_owned(), SHIFT_JIS, false, true),
+        ] {
+            let detection = detect(&bytes);
+            assert_eq!(
+                (detection.encoding, detection.bom, detection.confident),
+                (encoding, bom, confident),
+                "{:?}",
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn short_legacy_text_is_a_guess() {
+        let detection = detect(b"caf\xe9");
+        assert_eq!(detection.encoding, WINDOWS_1252);
+        assert!(!detection.bom);
+    }
+
+    #[test]
+    fn mixed_zeros_are_not_utf16() {
+        // zeros in both halves, as in binary data
+        assert_eq!(
+            utf16_without_bom(b"\0a\0b\0c\0d\0e\0f\0g\0h\0i\0ja\0b\0"),
+            None
+        );
+        assert_eq!(utf16_without_bom(b"abcd"), None);
+    }
+
+    #[test]
+    fn decoding_drops_the_bom() {
+        let decoded = decode(b"\xef\xbb\xbfh\xc3\xa9");
+        assert_eq!(decoded.text, "hé");
+        assert!(!decoded.had_errors);
+
+        // a byte order mark wins over the encoding asked for
+        let decoded = decode_as(b"\xff\xfeh\0i\0", WINDOWS_1252);
+        assert_eq!(decoded.text, "hi");
+        assert_eq!(decoded.detection.encoding, UTF_16LE);
+
+        let decoded = decode_as(b"h\xe9", WINDOWS_1252);
+        assert_eq!(decoded.text, "hé");
+        let decoded = decode_as(b"h\xe9", UTF_8);
+        assert_eq!(decoded.text, "h\u{fffd}");
+        assert!(decoded.had_errors);
+    }
+
+    #[test]
+    fn encoding() {
+        for (text, encoding, bom, bytes, unmappable) in [
+            ("hé", UTF_8, true, &b"\xef\xbb\xbfh\xc3\xa9"[..], false),
+            ("hé", UTF_8, false, b"h\xc3\xa9", false),
+            ("hé", UTF_16LE, true, b"\xff\xfeh\0\xe9\0", false),
+            ("hé", UTF_16BE, false, b"\0h\0\xe9", false),
+            ("hé", WINDOWS_1252, true, b"h\xe9", false),
+            ("h€日", WINDOWS_1252, false, b"h\x80&#26085;", true),
+        ] {
+            assert_eq!(
+                encode(text, encoding, bom),
+                (bytes.to_vec(), unmappable),
+                "{} in {}",
+                text,
+                encoding.name()
+            );
+        }
+    }
+
+    #[test]
+    fn code_pages() {
+        for (page, encoding) in [
+            (932, Some(SHIFT_JIS)),
+            (936, Some(GBK)),
+            (1200, Some(UTF_16LE)),
+            (1252, Some(WINDOWS_1252)),
+            (65001, Some(UTF_8)),
+            (437, None),
+            (0, None),
+        ] {
+            assert_eq!(code_page(page), encoding, "{}", page);
+        }
+    }
+}
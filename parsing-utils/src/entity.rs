@@ -0,0 +1,56 @@
+//! The character references SubRip and WebVTT text use.
+
+/// Decodes the reference at the start of `input`, such as `&amp;`, `&#38;`
+/// or `&#x26;`, returning the character and how many bytes it spans.
+pub fn decode(input: &str) -> Option<(char, usize)> {
+    let (end, _) = input.char_indices().take(12).find(|(_, c)| *c == ';')?;
+    let c = match input.get(1..end)? {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "lrm" => '\u{200e}',
+        "rlm" => '\u{200f}',
+        name => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse::<u32>().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((c, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references() {
+        for (input, expected) in [
+            ("&amp;", Some(('&', 5))),
+            ("&lt;b", Some(('<', 4))),
+            ("&nbsp;", Some(('\u{a0}', 6))),
+            ("&rlm;", Some(('\u{200f}', 5))),
+            ("&#38;", Some(('&', 5))),
+            ("&#x26;", Some(('&', 6))),
+            ("&#X26;", Some(('&', 6))),
+            ("&#xD800;", None),
+            ("&bogus;", None),
+            ("& amp", None),
+            ("&aaaaaaaaaaaaaaaa;", None),
+        ] {
+            assert_eq!(decode(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn non_ascii() {
+        assert_eq!(decode("&éééééé b"), None);
+        assert_eq!(decode("&ééééééééééééé;"), None);
+        assert_eq!(decode("&é;"), None);
+    }
+}
@@ -0,0 +1,320 @@
+//! What every subtitle format has in common: a [`Cue`] model to convert
+//! through, the [`SubtitleFormat`] trait the format crates implement, and
+//! [`detect`] to tell formats apart by their content.
+
+use std::fmt;
+use std::time::Duration;
+
+/// A timed piece of text, the shape every format converts to and from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Cue {
+    #[cfg_attr(feature = "serde", serde(with = "crate::millis"))]
+    #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+    pub start: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "crate::millis"))]
+    #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+    pub end: Duration,
+    /// Numpad-style position when it isn't the default bottom center.
+    pub alignment: Option<u8>,
+    /// Lines separated by `\n`, formatted with SubRip's `<b>`, `<i>`, `<u>`,
+    /// `<s>` and `<font>` tags.
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError {
+    /// 1-based line parsing failed on, when the format knows it.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Format {
+    Ass,
+    Ssa,
+    SubRip,
+    WebVtt,
+    Ttml,
+    MicroDvd,
+    Mpl2,
+    Sbv,
+    Sami,
+    Lrc,
+}
+
+impl Format {
+    pub const ALL: [Format; 10] = [
+        Format::Ass,
+        Format::Ssa,
+        Format::SubRip,
+        Format::WebVtt,
+        Format::Ttml,
+        Format::MicroDvd,
+        Format::Mpl2,
+        Format::Sbv,
+        Format::Sami,
+        Format::Lrc,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Ass => "Advanced SubStation Alpha",
+            Format::Ssa => "SubStation Alpha",
+            Format::SubRip => "SubRip",
+            Format::WebVtt => "WebVTT",
+            Format::Ttml => "TTML",
+            Format::MicroDvd => "MicroDVD",
+            Format::Mpl2 => "MPL2",
+            Format::Sbv => "YouTube SBV",
+            Format::Sami => "SAMI",
+            Format::Lrc => "LRC",
+        }
+    }
+
+    /// File extensions, the usual one first.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Ass => &["ass"],
+            Format::Ssa => &["ssa"],
+            Format::SubRip => &["srt"],
+            Format::WebVtt => &["vtt"],
+            Format::Ttml => &["ttml", "dfxp", "xml"],
+            Format::MicroDvd => &["sub"],
+            Format::Mpl2 => &["mpl", "txt"],
+            Format::Sbv => &["sbv"],
+            Format::Sami => &["smi", "sami"],
+            Format::Lrc => &["lrc"],
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        Format::ALL
+            .into_iter()
+            .find(|f| f.extensions().contains(&extension.as_str()))
+    }
+}
+
+/// Reading and writing one format through [`Cue`]s.
+pub trait SubtitleFormat {
+    fn format(&self) -> Format;
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError>;
+
+    /// Writes the cues, dropping whatever the format can't express.
+    fn write(&self, cues: &[Cue]) -> String;
+}
+
+// the start of the input as text, whatever its byte order mark says
+fn head(input: &[u8]) -> String {
+    const LIMIT: usize = 4096;
+    let utf16 = |bytes: &[u8], little: bool| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .take(LIMIT)
+            .map(|b| {
+                if little {
+                    u16::from_le_bytes([b[0], b[1]])
+                } else {
+                    u16::from_be_bytes([b[0], b[1]])
+                }
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match input {
+        [0xef, 0xbb, 0xbf, rest @ ..] => {
+            String::from_utf8_lossy(&rest[..rest.len().min(LIMIT)]).into_owned()
+        }
+        [0xff, 0xfe, rest @ ..] => utf16(rest, true),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, false),
+        _ => String::from_utf8_lossy(&input[..input.len().min(LIMIT)]).into_owned(),
+    }
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+// `{1}{25}`, `[1][25]`: two bracketed numbers, the second possibly empty
+fn bracketed_numbers(line: &str, open: char, close: char) -> bool {
+    let mut rest = line.trim_start();
+    for n in 0..2 {
+        let Some(inner) = rest.strip_prefix(open) else {
+            return false;
+        };
+        let Some(end) = inner.find(close) else {
+            return false;
+        };
+        let number = inner[..end].trim();
+        if !(is_digits(number) || (n == 1 && number.is_empty())) {
+            return false;
+        }
+        rest = &inner[end + 1..];
+    }
+    true
+}
+
+// H:MM:SS.mmm,H:MM:SS.mmm
+fn is_sbv_timing(line: &str) -> bool {
+    let time = |t: &str| {
+        let parts: Vec<&str> = t.trim().split([':', '.']).collect();
+        parts.len() == 4 && parts.iter().all(|p| is_digits(p))
+    };
+    line.split_once(',')
+        .is_some_and(|(start, end)| time(start) && time(end))
+}
+
+// [mm:ss.xx] or an ID tag like [ar:Artist]
+fn is_lrc_line(line: &str) -> bool {
+    let Some(inner) = line.trim().strip_prefix('[') else {
+        return false;
+    };
+    let Some((key, _)) = inner.split_once(':') else {
+        return false;
+    };
+    is_digits(key)
+        || matches!(
+            key,
+            "ti" | "ar" | "al" | "au" | "by" | "re" | "ve" | "length"
+        )
+}
+
+/// Guesses the format of a subtitle file from its first few kilobytes.
+pub fn detect(input: &[u8]) -> Option<Format> {
+    let head = head(input);
+    let head = head.trim_start_matches('\u{feff}');
+    let lower = head.to_ascii_lowercase();
+    let lines: Vec<&str> = head
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .take(8)
+        .collect();
+    let first = *lines.first()?;
+
+    if first.starts_with("WEBVTT") {
+        return Some(Format::WebVtt);
+    }
+    if lower.contains("[script info]") || lower.contains("[v4+ styles]") {
+        let ssa = lower.contains("[v4 styles]")
+            || (lower.contains("scripttype: v4.00") && !lower.contains("scripttype: v4.00+"));
+        return Some(if ssa { Format::Ssa } else { Format::Ass });
+    }
+    if lower.contains("<sami") {
+        return Some(Format::Sami);
+    }
+    if lower.contains("<tt") && lower.contains("http://www.w3.org/ns/ttml") {
+        return Some(Format::Ttml);
+    }
+    if bracketed_numbers(first, '{', '}') {
+        return Some(Format::MicroDvd);
+    }
+    if bracketed_numbers(first, '[', ']') {
+        return Some(Format::Mpl2);
+    }
+    if is_digits(first) && lines.get(1).is_some_and(|l| l.contains("-->")) {
+        return Some(Format::SubRip);
+    }
+    if first.contains("-->") && first.contains(',') {
+        return Some(Format::SubRip);
+    }
+    if is_sbv_timing(first) {
+        return Some(Format::Sbv);
+    }
+    if lines.iter().take(4).any(|l| is_lrc_line(l)) {
+        return Some(Format::Lrc);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_each_format() {
+        for (input, expected) in [
+            (
+                "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\n",
+                Format::Ass,
+            ),
+            ("[Script Info]\nTitle: x\n", Format::Ass),
+            ("[Script Info]\nScriptType: v4.00\n", Format::Ssa),
+            ("[Script Info]\n\n[V4 Styles]\nFormat: Name\n", Format::Ssa),
+            ("1\n00:00:01,000 --> 00:00:02,000\nHi\n", Format::SubRip),
+            ("00:00:01,000 --> 00:00:02,000\nHi\n", Format::SubRip),
+            ("WEBVTT\n\n00:01.000 --> 00:02.000\nHi\n", Format::WebVtt),
+            (
+                "<?xml version=\"1.0\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\">",
+                Format::Ttml,
+            ),
+            ("{1}{25}Hi|there\n", Format::MicroDvd),
+            ("{0}{}Hi\n", Format::MicroDvd),
+            ("[10][25]Hi|/there\n", Format::Mpl2),
+            ("[10][]Hi\n", Format::Mpl2),
+            ("0:00:01.000,0:00:02.000\nHi\n", Format::Sbv),
+            ("<SAMI>\n<BODY>\n<SYNC Start=0>", Format::Sami),
+            ("[ar:Someone]\n[00:01.00]Hi\n", Format::Lrc),
+            ("[00:01.00]Hi\n", Format::Lrc),
+        ] {
+            assert_eq!(detect(input.as_bytes()), Some(expected), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn detect_through_byte_order_marks() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHi\n";
+        let mut utf8 = vec![0xef, 0xbb, 0xbf];
+        utf8.extend(srt.as_bytes());
+        assert_eq!(detect(&utf8), Some(Format::SubRip));
+
+        let mut le = vec![0xff, 0xfe];
+        let mut be = vec![0xfe, 0xff];
+        for unit in "WEBVTT\n".encode_utf16() {
+            le.extend(unit.to_le_bytes());
+            be.extend(unit.to_be_bytes());
+        }
+        assert_eq!(detect(&le), Some(Format::WebVtt));
+        assert_eq!(detect(&be), Some(Format::WebVtt));
+    }
+
+    #[test]
+    fn ambiguous_input() {
+        for input in [
+            "",
+            "\n\n",
+            "Just some text\nin a file\n",
+            // bracketed text that isn't two times
+            "[note] [10]\n",
+            "[10] Hi\n",
+            "{10} Hi\n",
+            "1\nnot a timing\n",
+            "<html><body></body></html>",
+            "<tt>no namespace</tt>",
+            "[xx:yy]\n",
+        ] {
+            assert_eq!(detect(input.as_bytes()), None, "{:?}", input);
+        }
+        // MPL2's [start][end] wins over LRC's [mm:ss], which needs a colon
+        assert_eq!(detect(b"[1][2]a\n[00:01.00]b\n"), Some(Format::Mpl2));
+        assert_eq!(detect(b"[00:01.00]b\n[1][2]a\n"), Some(Format::Lrc));
+        // a comma in the timing line tells SubRip from WebVTT without a header
+        assert_eq!(detect(b"00:00:01.000 --> 00:00:02.000\nHi\n"), None);
+    }
+}
@@ -9,6 +9,13 @@ use nom::{
     IResult,
 };
 
+pub mod encoding;
+pub mod entity;
+pub mod format;
+#[cfg(feature = "serde")]
+pub mod millis;
+pub mod timing;
+
 pub fn decimal_or_float(input: &str) -> IResult<&str, f64> {
     alt((double, map(parsei64, |v: i64| v as f64)))(input)
 }
@@ -18,18 +25,18 @@ pub fn from_hex(input: &str) -> Result<u8, std::num::ParseIntError> {
 }
 
 pub fn is_hex_digit(c: char) -> bool {
-    c.is_digit(16)
+    c.is_ascii_hexdigit()
 }
 
 pub fn hex_primary(input: &str) -> IResult<&str, u8> {
     map_res(take_while_m_n(2, 2, is_hex_digit), from_hex)(input)
 }
 
-pub fn ws<'a, F: 'a, O, E: ParseError<&'a str>>(
+pub fn ws<'a, F, O, E: ParseError<&'a str>>(
     inner: F,
 ) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
 where
-    F: Fn(&'a str) -> IResult<&'a str, O, E>,
+    F: 'a + Fn(&'a str) -> IResult<&'a str, O, E>,
 {
     delimited(multispace0, inner, multispace0)
 }
@@ -0,0 +1,96 @@
+//! Durations as whole milliseconds, the representation the crates' `serde`
+//! features use for times. Use with `#[serde(with = "parsing_utils::millis")]`,
+//! or [`option`] for an `Option<Duration>`.
+
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    Ok(Duration::from_millis(u64::deserialize(deserializer)?))
+}
+
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(d) => serializer.serialize_some(&(d.as_millis() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Times {
+        #[serde(with = "super")]
+        start: Duration,
+        #[serde(with = "super::option")]
+        end: Option<Duration>,
+    }
+
+    #[test]
+    fn whole_milliseconds() {
+        for (times, json) in [
+            (
+                Times {
+                    start: Duration::from_millis(1500),
+                    end: Some(Duration::from_secs(3600)),
+                },
+                r#"{"start":1500,"end":3600000}"#,
+            ),
+            (
+                Times {
+                    start: Duration::ZERO,
+                    end: None,
+                },
+                r#"{"start":0,"end":null}"#,
+            ),
+        ] {
+            assert_eq!(serde_json::to_string(&times).unwrap(), json);
+            assert_eq!(serde_json::from_str::<Times>(json).unwrap(), times);
+        }
+    }
+
+    #[test]
+    fn sub_millisecond_parts_are_dropped() {
+        let times = Times {
+            start: Duration::from_micros(1999),
+            end: Some(Duration::from_nanos(1)),
+        };
+        assert_eq!(
+            serde_json::to_string(&times).unwrap(),
+            r#"{"start":1,"end":0}"#
+        );
+    }
+
+    #[test]
+    fn invalid_times() {
+        for json in [
+            r#"{"start":-1,"end":null}"#,
+            r#"{"start":1.5,"end":null}"#,
+            r#"{"start":"1","end":null}"#,
+            r#"{"start":1,"end":-1}"#,
+        ] {
+            assert!(serde_json::from_str::<Times>(json).is_err(), "{}", json);
+        }
+    }
+}
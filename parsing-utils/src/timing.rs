@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+/// A half-open span of time, `start..end`, used to restrict timing
+/// operations to part of a script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl TimeRange {
+    pub fn new(start: Duration, end: Duration) -> TimeRange {
+        TimeRange { start, end }
+    }
+
+    pub fn contains(&self, time: Duration) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+/// Moves `time` by `offset_ms` milliseconds, clamping at zero instead of
+/// underflowing and at `u64::MAX` milliseconds instead of overflowing.
+pub fn shift_duration(time: Duration, offset_ms: i64) -> Duration {
+    let ms = time.as_millis() as i128 + offset_ms as i128;
+    Duration::from_millis(ms.clamp(0, u64::MAX as i128) as u64)
+}
+
+/// Multiplies `time` by `factor`, rounding to the nearest millisecond.
+/// Negative or non-finite results clamp to zero.
+pub fn scale_duration(time: Duration, factor: f64) -> Duration {
+    let ms = (time.as_millis() as f64 * factor).round();
+    if ms.is_finite() && ms > 0.0 {
+        Duration::from_millis(ms as u64)
+    } else {
+        Duration::ZERO
+    }
+}
+
+/// The factor to scale times by when retiming from one framerate to another,
+/// e.g. `framerate_ratio(25.0, 23.976)` undoes a PAL speedup.
+pub fn framerate_ratio(from: f64, to: f64) -> f64 {
+    from / to
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn ranges_are_half_open() {
+        let range = TimeRange::new(ms(1000), ms(2000));
+        for (time, inside) in [(999, false), (1000, true), (1999, true), (2000, false)] {
+            assert_eq!(range.contains(ms(time)), inside, "{}", time);
+        }
+    }
+
+    #[test]
+    fn shifting() {
+        for (time, offset, expected) in [
+            (1000, 500, 1500),
+            (1000, -500, 500),
+            (1000, -1000, 0),
+            (1000, -5000, 0),
+            (0, i64::MIN, 0),
+            (u64::MAX, 1, u64::MAX),
+            (u64::MAX, i64::MIN, u64::MAX - i64::MAX as u64 - 1),
+        ] {
+            assert_eq!(
+                shift_duration(ms(time), offset),
+                ms(expected),
+                "{} {}",
+                time,
+                offset
+            );
+        }
+    }
+
+    #[test]
+    fn scaling() {
+        for (time, factor, expected) in [
+            (1000, 2.0, 2000),
+            (1001, 0.5, 501),
+            (1000, 25.0 / 23.976, 1043),
+            (1000, 0.0, 0),
+            (1000, -1.0, 0),
+            (1000, f64::NAN, 0),
+            (1000, f64::INFINITY, 0),
+        ] {
+            assert_eq!(
+                scale_duration(ms(time), factor),
+                ms(expected),
+                "{} {}",
+                time,
+                factor
+            );
+        }
+        assert_eq!(framerate_ratio(25.0, 23.976), 25.0 / 23.976);
+    }
+}
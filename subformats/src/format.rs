@@ -0,0 +1,107 @@
+//! The formats of this crate through the workspace's common
+//! [`SubtitleFormat`] trait.
+
+use crate::{lrc, microdvd, mpl2, sami, sbv};
+use parsing_utils::format::{Cue, Format, FormatError, SubtitleFormat};
+use subrip::format::{cues, entries};
+use subrip::writer::LineEnding;
+
+/// Frames are converted with `options.fps`, unless the file declares its own
+/// framerate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MicroDvd {
+    pub options: microdvd::WriteOptions,
+}
+
+impl SubtitleFormat for MicroDvd {
+    fn format(&self) -> Format {
+        Format::MicroDvd
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(cues(&microdvd::parse(input, self.options.fps)?))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        microdvd::to_string(&entries(cues), &self.options)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mpl2 {
+    pub line_ending: LineEnding,
+}
+
+impl SubtitleFormat for Mpl2 {
+    fn format(&self) -> Format {
+        Format::Mpl2
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(cues(&mpl2::parse(input)?))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        mpl2::to_string(&entries(cues), self.line_ending)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sbv {
+    pub line_ending: LineEnding,
+}
+
+impl SubtitleFormat for Sbv {
+    fn format(&self) -> Format {
+        Format::Sbv
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(cues(&sbv::parse(input)?))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        sbv::to_string(&entries(cues), self.line_ending)
+    }
+}
+
+/// Reads the language with class `class`, the first declared if `None`.
+#[derive(Clone, Debug, Default)]
+pub struct Sami {
+    pub class: Option<String>,
+    pub options: sami::WriteOptions,
+}
+
+impl SubtitleFormat for Sami {
+    fn format(&self) -> Format {
+        Format::Sami
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(cues(&sami::parse(input, self.class.as_deref())))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        sami::to_string(&entries(cues), &self.options)
+    }
+}
+
+/// Lyric lines as cues; ID tags and word timings are dropped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lrc {
+    pub line_ending: LineEnding,
+}
+
+impl SubtitleFormat for Lrc {
+    fn format(&self) -> Format {
+        Format::Lrc
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(cues(&lrc::to_entries(&lrc::parse(input)?)))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        lrc::to_string(&lrc::from_entries(&entries(cues)), self.line_ending)
+    }
+}
@@ -0,0 +1,113 @@
+//! Line-based subtitle formats that read into [`subrip::Entry`].
+//!
+//! Each module parses its format into SubRip entries, with formatting as
+//! SubRip tags, and writes entries back out, keeping whatever formatting the
+//! format can express.
+
+use std::time::{Duration, TryFromFloatSecsError};
+use subrip::text::{Span, TextStyle};
+
+pub mod format;
+pub mod lrc;
+pub mod microdvd;
+pub mod mpl2;
+pub mod sami;
+pub mod sbv;
+
+pub use subrip::reader::ParseError;
+
+/// Whether `fps` is a framerate frames can be timed at: finite and at
+/// least one frame a second.
+pub fn valid_fps(fps: f64) -> bool {
+    fps.is_finite() && fps >= 1.0
+}
+
+/// The time a frame starts at, or an error when it can't be represented,
+/// as with a framerate of 0.
+pub fn frame_time(frame: u64, fps: f64) -> Result<Duration, TryFromFloatSecsError> {
+    Duration::try_from_secs_f64(frame as f64 / fps)
+}
+
+/// The frame shown at `time`, rounded to the nearest.
+pub fn time_frame(time: Duration, fps: f64) -> u64 {
+    (time.as_secs_f64() * fps).round() as u64
+}
+
+pub(crate) fn lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    input
+        .split('\n')
+        .map(|l| l.trim_end_matches('\r'))
+        .enumerate()
+        .map(|(n, l)| (n + 1, l))
+        .filter(|(_, l)| !l.trim().is_empty())
+}
+
+// numbers entries from 1 and gives those without an end time the start of
+// the next one, or `fallback` after their own start
+pub(crate) fn finish(entries: &mut [subrip::Entry], open_ended: &[bool], fallback: Duration) {
+    for n in 0..entries.len() {
+        entries[n].index = n as u32 + 1;
+        if open_ended[n] {
+            entries[n].end = match entries.get(n + 1) {
+                Some(next) if next.start > entries[n].start => next.start,
+                _ => entries[n].start + fallback,
+            };
+        }
+    }
+}
+
+/// The formatting every visible character of `spans` has in common.
+pub(crate) fn common_style<'a>(spans: impl IntoIterator<Item = &'a Span>) -> TextStyle {
+    let mut common: Option<TextStyle> = None;
+    for span in spans {
+        if span.text.trim().is_empty() {
+            continue;
+        }
+        let style = &span.style;
+        common = Some(match common {
+            None => style.clone(),
+            Some(mut c) => {
+                c.bold &= style.bold;
+                c.italic &= style.italic;
+                c.underline &= style.underline;
+                c.strikeout &= style.strikeout;
+                if c.font.color != style.font.color {
+                    c.font.color = None;
+                }
+                if c.font.face != style.font.face {
+                    c.font.face = None;
+                }
+                if c.font.size != style.font.size {
+                    c.font.size = None;
+                }
+                c
+            }
+        });
+    }
+    common.unwrap_or_default()
+}
+
+/// An entry's text split into lines, each as the spans on it.
+pub(crate) fn span_lines(entry: &subrip::Entry) -> Vec<Vec<Span>> {
+    let mut lines: Vec<Vec<Span>> = vec![Vec::new()];
+    for span in entry.parsed_text() {
+        let text = span.text.replace("\r\n", "\n");
+        for (n, part) in text.split('\n').enumerate() {
+            if n > 0 {
+                lines.push(Vec::new());
+            }
+            if !part.is_empty() {
+                lines.last_mut().unwrap().push(Span {
+                    text: part.to_owned(),
+                    style: span.style.clone(),
+                });
+            }
+        }
+    }
+    lines
+}
+
+pub(crate) fn plain(lines: &[Vec<Span>], n: usize) -> String {
+    lines[n].iter().map(|s| s.text.as_str()).collect()
+}
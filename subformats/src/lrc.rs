@@ -0,0 +1,434 @@
+//! LRC lyrics: `[mm:ss.xx]Line` with `[ar:Artist]`-style ID tags, and the
+//! enhanced `<mm:ss.xx>` word timings, which become ASS `\k` karaoke.
+
+use crate::ParseError;
+use nom::{
+    bytes::complete::{is_not, take_while1},
+    character::complete::{char, digit1, one_of, u64 as decimal},
+    combinator::{opt, recognize},
+    error::ErrorKind,
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+use std::fmt::Write as _;
+use std::time::Duration;
+use subrip::text::{Span, TextStyle};
+use subrip::writer::LineEnding;
+use substation::srt::FromSrtOptions;
+use substation::{Script, Timestamp};
+
+// how long the last line stays up
+const LAST_LINE: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Line {
+    pub time: Duration,
+    /// The text without word timings.
+    pub text: String,
+    /// Each timed word or syllable with when it starts; empty unless the
+    /// line uses `<mm:ss.xx>` timings. An empty last word marks where the one
+    /// before it ends.
+    pub words: Vec<(Duration, String)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lrc {
+    /// ID tags such as `ti`, `ar`, `al` and `by`, in file order. `offset`
+    /// is applied to the times and not kept.
+    pub tags: Vec<(String, String)>,
+    /// Sorted by time, a line with several timestamps appearing once for each.
+    pub lines: Vec<Line>,
+}
+
+impl Lrc {
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// minutes:seconds(.fraction)?
+pub fn timestamp(input: &str) -> IResult<&str, Duration> {
+    let (input, (mins, secs, fraction)) = tuple((
+        terminated(decimal, char(':')),
+        decimal,
+        opt(preceded(one_of(".:"), recognize(digit1))),
+    ))(input)?;
+    let fraction = fraction
+        .map(|f: &str| format!("0.{}", f).parse::<f64>().unwrap_or(0.0))
+        .unwrap_or(0.0);
+    let millis = mins
+        .checked_mul(60_000)
+        .zip(secs.checked_mul(1_000))
+        .and_then(|(m, s)| m.checked_add(s))
+        .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, ErrorKind::TooLarge)))?;
+    Ok((
+        input,
+        Duration::from_millis(millis) + Duration::from_secs_f64(fraction),
+    ))
+}
+
+// [key:value]
+fn id_tag(input: &str) -> IResult<&str, (&str, &str)> {
+    let (input, (key, value)) = delimited(
+        char('['),
+        separated_pair(
+            take_while1(|c: char| c.is_ascii_alphabetic()),
+            char(':'),
+            opt(is_not("]")),
+        ),
+        char(']'),
+    )(input)?;
+    Ok((input, (key, value.unwrap_or(""))))
+}
+
+fn shift(time: Duration, offset: i64) -> Duration {
+    // a positive offset makes the lyrics come sooner
+    parsing_utils::timing::shift_duration(time, -offset)
+}
+
+// text with <mm:ss.xx> word timings, and whatever comes before the first
+fn words(text: &str) -> (String, String, Vec<(Duration, String)>) {
+    let mut plain = String::new();
+    let mut lead = String::new();
+    let mut words: Vec<(Duration, String)> = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Ok((after, time)) = terminated(preceded(char('<'), timestamp), char('>'))(rest) {
+                words.push((time, String::new()));
+                rest = after;
+                continue;
+            }
+        }
+        plain.push(c);
+        match words.last_mut() {
+            Some((_, word)) => word.push(c),
+            None => lead.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    (plain.trim().to_owned(), lead, words)
+}
+
+/// Parses an LRC file. Lines that are neither timed nor an ID tag are an
+/// error, except blank ones.
+pub fn parse(input: &str) -> Result<Lrc, ParseError> {
+    let mut lrc = Lrc::default();
+    let mut offset = 0;
+    let mut timed: Vec<(Vec<Duration>, &str)> = Vec::new();
+
+    for (number, line) in crate::lines(input) {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Ok((after, time)) = delimited(char('['), timestamp, char(']'))(rest) {
+            times.push(time);
+            rest = after;
+        }
+        if !times.is_empty() {
+            timed.push((times, rest));
+            continue;
+        }
+        match id_tag(rest) {
+            Ok((_, (key, value))) if key.eq_ignore_ascii_case("offset") => {
+                offset = value.trim().trim_start_matches('+').parse().unwrap_or(0);
+            }
+            Ok((_, (key, value))) => lrc.tags.push((key.to_owned(), value.trim().to_owned())),
+            Err(_) => {
+                return Err(ParseError {
+                    line: number,
+                    message: format!("expected a [mm:ss.xx] time or an ID tag, found {:?}", line),
+                })
+            }
+        }
+    }
+
+    for (times, text) in timed {
+        let (text, lead, mut words) = words(text);
+        if !words.is_empty() && !lead.trim().is_empty() {
+            // sung from the start of the line
+            words.insert(0, (times[0], lead));
+        }
+        for &time in &times {
+            // word times are absolute, so they move with each copy of the line
+            let moved = |t: Duration| shift(time + t.saturating_sub(times[0]), offset);
+            lrc.lines.push(Line {
+                time: shift(time, offset),
+                text: text.clone(),
+                words: words.iter().map(|(t, w)| (moved(*t), w.clone())).collect(),
+            });
+        }
+    }
+    lrc.lines.sort_by_key(|l| l.time);
+
+    Ok(lrc)
+}
+
+fn format_timestamp(time: Duration) -> String {
+    let centis = (time.as_millis() + 5) / 10;
+    format!(
+        "{:02}:{:02}.{:02}",
+        centis / 6_000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+pub fn to_string(lrc: &Lrc, line_ending: LineEnding) -> String {
+    let nl = line_ending.as_str();
+    let mut out = String::new();
+    for (key, value) in &lrc.tags {
+        let _ = write!(out, "[{}:{}]{}", key, value, nl);
+    }
+    for line in &lrc.lines {
+        let _ = write!(out, "[{}]", format_timestamp(line.time));
+        if line.words.is_empty() {
+            out.push_str(&line.text);
+        } else {
+            for (time, word) in &line.words {
+                let _ = write!(out, "<{}>{}", format_timestamp(*time), word);
+            }
+        }
+        out.push_str(nl);
+    }
+    out
+}
+
+// when a line stops showing: the next line's time, or a while after it
+fn line_end(lrc: &Lrc, n: usize) -> Duration {
+    let line = &lrc.lines[n];
+    let last_word = line.words.last().map(|(t, _)| *t).unwrap_or(line.time);
+    match lrc.lines.get(n + 1) {
+        Some(next) if next.time > line.time => next.time,
+        _ => last_word.max(line.time) + LAST_LINE,
+    }
+}
+
+/// One cue per lyric line, lasting until the next. Empty lines, which clear
+/// the screen in players, are dropped.
+pub fn to_entries(lrc: &Lrc) -> Vec<subrip::Entry> {
+    let mut entries = Vec::new();
+    for (n, line) in lrc.lines.iter().enumerate() {
+        if line.text.trim().is_empty() {
+            continue;
+        }
+        entries.push(subrip::Entry {
+            index: entries.len() as u32 + 1,
+            start: line.time,
+            end: line_end(lrc, n),
+            text: subrip::text::write(&[Span {
+                text: line.text.clone(),
+                style: TextStyle::default(),
+            }]),
+            ..subrip::Entry::default()
+        });
+    }
+    entries
+}
+
+/// Lyric lines from cues, with an empty line clearing the screen wherever a
+/// cue isn't followed right away by the next.
+pub fn from_entries(entries: &[subrip::Entry]) -> Lrc {
+    let mut lrc = Lrc::default();
+    for (n, entry) in entries.iter().enumerate() {
+        let text: String = entry
+            .parsed_text()
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        lrc.lines.push(Line {
+            time: entry.start,
+            text: text.lines().collect::<Vec<_>>().join(" "),
+            words: Vec::new(),
+        });
+        if entries.get(n + 1).is_none_or(|next| next.start > entry.end) {
+            lrc.lines.push(Line {
+                time: entry.end,
+                ..Line::default()
+            });
+        }
+    }
+    lrc
+}
+
+fn centis(from: Duration, to: Duration) -> i64 {
+    Timestamp::from_duration(to).centis() - Timestamp::from_duration(from).centis()
+}
+
+/// ASS text for a line, each timed word or syllable getting a `\k` for how
+/// long it lasts. A line without word timings is one syllable running until
+/// `end`.
+pub fn karaoke_text(line: &Line, end: Duration) -> String {
+    if line.words.is_empty() {
+        return format!(
+            "{{\\k{}}}{}",
+            centis(line.time, end).max(0),
+            substation::escape_text(&line.text)
+        );
+    }
+
+    let mut text = String::new();
+    if line.words[0].0 > line.time {
+        // silence before the first syllable
+        let _ = write!(text, "{{\\k{}}}", centis(line.time, line.words[0].0));
+    }
+    for (n, (time, word)) in line.words.iter().enumerate() {
+        let next = line.words.get(n + 1).map(|(t, _)| *t).unwrap_or(end);
+        if word.is_empty() && n + 1 == line.words.len() {
+            break;
+        }
+        let _ = write!(
+            text,
+            "{{\\k{}}}{}",
+            centis(*time, next).max(0),
+            substation::escape_text(word)
+        );
+    }
+    text.trim_end().to_owned()
+}
+
+/// A karaoke script with a `\k`-timed Dialogue event per lyric line, using
+/// the style and resolution of `options`. The title defaults to the `ti`
+/// tag.
+pub fn to_ass(lrc: &Lrc, options: &FromSrtOptions) -> Script {
+    let mut options = options.clone();
+    if options.title.is_none() {
+        options.title = lrc.tag("ti").map(|t| t.to_owned());
+    }
+    let mut script = substation::srt::from_srt(&[], &options);
+
+    for (n, line) in lrc.lines.iter().enumerate() {
+        if line.text.trim().is_empty() {
+            continue;
+        }
+        let end = line_end(lrc, n);
+        script.events.push(substation::Entry {
+            kind: Some("Dialogue".to_owned()),
+            layer: Some(0),
+            start: Some(Timestamp::from_duration(line.time).as_duration()),
+            end: Some(Timestamp::from_duration(end).as_duration()),
+            style: Some(options.style.name.clone()),
+            name: None,
+            margin_l: Some(0),
+            margin_r: Some(0),
+            margin_v: Some(0),
+            effect: None,
+            read_order: None,
+            text: karaoke_text(line, end),
+        });
+    }
+
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn timestamps() {
+        for (input, expected) in [
+            ("00:01.5", Some(1500)),
+            ("01:02.03", Some(62_030)),
+            ("1:02:345", Some(62_345)),
+            ("10:00", Some(600_000)),
+            ("999999999999999999:00.00", None),
+            ("0:18446744073709551615", None),
+        ] {
+            assert_eq!(
+                timestamp(input).ok().map(|(_, t)| t.as_millis() as u64),
+                expected,
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parse_lines_and_tags() {
+        let lrc = parse(
+            "[ti:Song]\n[ar: Someone ]\n[offset:+500]\n\
+             [00:03.00][00:01.00]Chorus\n[00:02.00]<00:02.00>Two <00:02.50>words<00:03.00>\n",
+        )
+        .unwrap();
+        assert_eq!(lrc.tag("TI"), Some("Song"));
+        assert_eq!(lrc.tag("ar"), Some("Someone"));
+        assert_eq!(lrc.tag("offset"), None);
+        assert_eq!(
+            lrc.lines
+                .iter()
+                .map(|l| (l.time, l.text.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (ms(500), "Chorus"),
+                (ms(1500), "Two words"),
+                (ms(2500), "Chorus")
+            ]
+        );
+        assert_eq!(
+            lrc.lines[1].words,
+            [
+                (ms(1500), "Two ".to_owned()),
+                (ms(2000), "words".to_owned()),
+                (ms(2500), String::new())
+            ]
+        );
+
+        for (input, line) in [
+            ("[00:01.00]a\nnot a line\n", 2),
+            ("[999999999999999999:00.00]x\n", 1),
+        ] {
+            assert_eq!(parse(input).unwrap_err().line, line, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "[ti:Song]\n[00:01.00]One\n[00:02.00]<00:02.00>Two <00:02.50>words<00:03.00>\n";
+        let lrc = parse(input).unwrap();
+        assert_eq!(to_string(&lrc, LineEnding::Lf), input);
+
+        let entries = to_entries(&lrc);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| (e.start, e.end, e.text.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (ms(1000), ms(2000), "One"),
+                (ms(2000), ms(8000), "Two words")
+            ]
+        );
+        assert_eq!(from_entries(&entries).lines.len(), 3);
+    }
+
+    #[test]
+    fn karaoke() {
+        let line = Line {
+            time: ms(1000),
+            text: "Hello {world}".to_owned(),
+            words: Vec::new(),
+        };
+        assert_eq!(karaoke_text(&line, ms(2500)), "{\\k150}Hello \\{world\\}");
+
+        let line = Line {
+            time: ms(1000),
+            text: "a\\N b".to_owned(),
+            words: vec![
+                (ms(1200), "a\\N ".to_owned()),
+                (ms(1500), "{b}".to_owned()),
+                (ms(2000), String::new()),
+            ],
+        };
+        assert_eq!(
+            karaoke_text(&line, ms(3000)),
+            "{\\k20}{\\k30}a\\\u{200d}N {\\k50}\\{b\\}"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! MicroDVD: `{start}{end}Text|Line two`, timed in frames.
+//!
+//! Control codes at the start of a line style it, lowercase ones that line
+//! and uppercase ones the whole subtitle: `{y:b,i,u,s}` for bold, italic,
+//! underline and strikeout, `{c:$BBGGRR}` for the color, `{f:name}` for the
+//! font and `{s:size}` for its size. Others, like `{P:x,y}` and `{H:charset}`,
+//! are dropped.
+
+use crate::{
+    common_style, finish, frame_time, lines, plain, span_lines, time_frame, valid_fps, ParseError,
+};
+use nom::{
+    bytes::complete::is_not,
+    character::complete::{char, one_of, space0, u64 as decimal},
+    combinator::opt,
+    sequence::{delimited, separated_pair, tuple},
+    IResult,
+};
+use std::fmt::Write as _;
+use std::time::Duration;
+use subrip::text::{Span, TextStyle};
+use subrip::writer::LineEnding;
+use subrip::Entry;
+
+// how long a subtitle without an end frame stays up when nothing follows it
+const OPEN_END: Duration = Duration::from_secs(3);
+
+// {start}{end}, the end may be left empty
+fn frames(input: &str) -> IResult<&str, (u64, Option<u64>)> {
+    tuple((
+        delimited(char('{'), delimited(space0, decimal, space0), char('}')),
+        delimited(
+            char('{'),
+            delimited(space0, opt(decimal), space0),
+            char('}'),
+        ),
+    ))(input)
+}
+
+// {y:i}, {C:$0000FF}, ...
+fn code(input: &str) -> IResult<&str, (char, &str)> {
+    delimited(
+        char('{'),
+        separated_pair(one_of("yYcCfFsSpPhH"), char(':'), is_not("}")),
+        char('}'),
+    )(input)
+}
+
+fn apply(style: &mut TextStyle, kind: char, value: &str) {
+    match kind.to_ascii_lowercase() {
+        'y' => {
+            for flag in value.split(',') {
+                match flag.trim().to_ascii_lowercase().as_str() {
+                    "b" => style.bold = true,
+                    "i" => style.italic = true,
+                    "u" => style.underline = true,
+                    "s" => style.strikeout = true,
+                    _ => (),
+                }
+            }
+        }
+        'c' => {
+            let hex = value.trim().trim_start_matches('$');
+            if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                style.font.color = Some(format!("#{}{}{}", &hex[4..6], &hex[2..4], &hex[0..2]));
+            }
+        }
+        'f' => style.font.face = Some(value.trim().to_owned()),
+        's' => style.font.size = Some(value.trim().to_owned()),
+        _ => (),
+    }
+}
+
+/// Converts MicroDVD text, `|` separating lines, to SubRip text.
+pub fn text(input: &str) -> String {
+    let mut global = TextStyle::default();
+    let mut lines: Vec<(TextStyle, String)> = Vec::new();
+
+    for line in input.split('|') {
+        let mut style = TextStyle::default();
+        let mut text = String::new();
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if c == '{' {
+                if let Ok((after, (kind, value))) = code(rest) {
+                    if kind.is_ascii_uppercase() {
+                        apply(&mut global, kind, value);
+                    } else {
+                        apply(&mut style, kind, value);
+                    }
+                    rest = after;
+                    continue;
+                }
+            }
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        lines.push((style, text));
+    }
+
+    let mut spans: Vec<Span> = Vec::new();
+    for (n, (style, text)) in lines.into_iter().enumerate() {
+        if n > 0 {
+            // keeps subtitle-wide tags open across the line break
+            spans.push(Span {
+                text: "\n".to_owned(),
+                style: global.clone(),
+            });
+        }
+        let mut line_style = global.clone();
+        line_style.bold |= style.bold;
+        line_style.italic |= style.italic;
+        line_style.underline |= style.underline;
+        line_style.strikeout |= style.strikeout;
+        line_style.font.color = style.font.color.or(line_style.font.color);
+        line_style.font.face = style.font.face.or(line_style.font.face);
+        line_style.font.size = style.font.size.or(line_style.font.size);
+        spans.push(Span {
+            text,
+            style: line_style,
+        });
+    }
+    subrip::text::write(&spans)
+}
+
+fn line(input: &str) -> Option<(u64, Option<u64>, &str)> {
+    let (text, (start, end)) = frames(input.trim_start()).ok()?;
+    Some((start, end, text))
+}
+
+// the number on a `{1}{1}23.976` first line, whatever it is
+fn header(input: &str) -> Option<f64> {
+    let (_, first) = lines(input).next()?;
+    match line(first)? {
+        (start, Some(end), text) if start == end && start <= 1 => text.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// The framerate of a `{1}{1}23.976` first line, the convention for storing
+/// it in the file. Framerates below one frame a second aren't read as one.
+pub fn header_fps(input: &str) -> Option<f64> {
+    header(input).filter(|fps| valid_fps(*fps))
+}
+
+/// Parses a MicroDVD file at `fps` frames a second, unless a header line
+/// gives the framerate. Subtitles without an end frame last until the next
+/// one starts. Fails if the framerate is below one frame a second or not
+/// finite.
+pub fn parse(input: &str, fps: f64) -> Result<Vec<Entry>, ParseError> {
+    let header = header(input);
+    let fps = header.unwrap_or(fps);
+    if !valid_fps(fps) {
+        return Err(ParseError {
+            line: lines(input)
+                .next()
+                .filter(|_| header.is_some())
+                .map_or(1, |(n, _)| n),
+            message: format!("can't time frames at {} fps", fps),
+        });
+    }
+
+    let mut entries = Vec::new();
+    let mut open_ended = Vec::new();
+    for (n, (number, l)) in lines(input).enumerate() {
+        if n == 0 && header.is_some() {
+            continue;
+        }
+        let Some((start, end, text)) = line(l) else {
+            return Err(ParseError {
+                line: number,
+                message: format!("expected {{start}}{{end}} frames, found {:?}", l),
+            });
+        };
+        let time = |frame| {
+            frame_time(frame, fps).map_err(|_| ParseError {
+                line: number,
+                message: format!("frame {} is out of range", frame),
+            })
+        };
+        open_ended.push(end.is_none());
+        entries.push(Entry {
+            start: time(start)?,
+            end: time(end.unwrap_or(start))?,
+            text: self::text(text),
+            ..Entry::default()
+        });
+    }
+
+    finish(&mut entries, &open_ended, OPEN_END);
+    Ok(entries)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WriteOptions {
+    pub fps: f64,
+    /// Writes the framerate as a `{1}{1}fps` first line.
+    pub header: bool,
+    pub line_ending: LineEnding,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            fps: 23.976,
+            header: true,
+            line_ending: LineEnding::CrLf,
+        }
+    }
+}
+
+fn codes(style: &TextStyle, except: &TextStyle, upper: bool) -> String {
+    let mut out = String::new();
+    let case = |c: char| if upper { c.to_ascii_uppercase() } else { c };
+
+    let flags: Vec<&str> = [
+        (style.italic && !except.italic, "i"),
+        (style.bold && !except.bold, "b"),
+        (style.underline && !except.underline, "u"),
+        (style.strikeout && !except.strikeout, "s"),
+    ]
+    .into_iter()
+    .filter_map(|(set, flag)| set.then_some(flag))
+    .collect();
+    if !flags.is_empty() {
+        let _ = write!(out, "{{{}:{}}}", case('y'), flags.join(","));
+    }
+    if style.font.color != except.font.color {
+        if let Some((r, g, b)) = style.font.rgb() {
+            let _ = write!(out, "{{{}:${:02X}{:02X}{:02X}}}", case('c'), b, g, r);
+        }
+    }
+    if let Some(face) = style
+        .font
+        .face
+        .as_ref()
+        .filter(|_| style.font.face != except.font.face)
+    {
+        let _ = write!(out, "{{{}:{}}}", case('f'), face);
+    }
+    if let Some(size) = style
+        .font
+        .size
+        .as_ref()
+        .filter(|_| style.font.size != except.font.size)
+    {
+        let _ = write!(out, "{{{}:{}}}", case('s'), size);
+    }
+    out
+}
+
+/// SubRip text as MicroDVD text. Formatting shared by a whole line or the
+/// whole subtitle becomes control codes; anything applied to part of a line
+/// is lost.
+pub fn write_text(entry: &Entry) -> String {
+    let lines = span_lines(entry);
+    let global = common_style(lines.iter().flatten());
+
+    let mut out = codes(&global, &TextStyle::default(), true);
+    for n in 0..lines.len() {
+        if n > 0 {
+            out.push('|');
+        }
+        out.push_str(&codes(&common_style(&lines[n]), &global, false));
+        out.push_str(&plain(&lines, n));
+    }
+    out
+}
+
+pub fn to_string(entries: &[Entry], options: &WriteOptions) -> String {
+    let nl = options.line_ending.as_str();
+    let mut out = String::new();
+    if options.header {
+        let _ = write!(out, "{{1}}{{1}}{}{}", options.fps, nl);
+    }
+    for entry in entries {
+        let _ = write!(
+            out,
+            "{{{}}}{{{}}}{}{}",
+            time_frame(entry.start, options.fps),
+            time_frame(entry.end, options.fps),
+            write_text(entry),
+            nl
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_framerate() {
+        let entries = parse("{1}{1}25\n{25}{50}One|Two\n{75}{}Three\n", 10.0).unwrap();
+        let times: Vec<(u64, u64)> = entries
+            .iter()
+            .map(|e| (e.start.as_millis() as u64, e.end.as_millis() as u64))
+            .collect();
+        assert_eq!(times, [(1000, 2000), (3000, 6000)]);
+        assert_eq!(entries[0].text, "One\nTwo");
+    }
+
+    #[test]
+    fn invalid_framerates() {
+        for input in [
+            "{1}{1}0\n{1}{2}a\n",
+            "{1}{1}1e-300\n{1}{2}a\n",
+            "{1}{1}NaN\n{1}{2}a\n",
+        ] {
+            assert_eq!(header_fps(input), None, "{:?}", input);
+            assert_eq!(parse(input, 25.0).unwrap_err().line, 1, "{:?}", input);
+        }
+        for fps in [0.0, -1.0, 1e-300, f64::NAN, f64::INFINITY] {
+            assert!(parse("{1}{2}a\n", fps).is_err(), "{}", fps);
+        }
+        assert!(frame_time(1, 0.0).is_err());
+    }
+
+    #[test]
+    fn frame_out_of_range() {
+        let err = parse("{18446744073709551615}{0}a\n", 1.0).unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+}
@@ -0,0 +1,164 @@
+//! MPL2: `[start][end]Text|Line two`, timed in deciseconds, with a `/` in
+//! front of a line making it italic.
+
+use crate::{common_style, finish, lines, plain, span_lines, ParseError};
+use nom::{
+    character::complete::{char, space0, u64 as decimal},
+    combinator::opt,
+    sequence::{delimited, tuple},
+    IResult,
+};
+use std::fmt::Write as _;
+use std::time::Duration;
+use subrip::text::{Span, TextStyle};
+use subrip::writer::LineEnding;
+use subrip::Entry;
+
+const OPEN_END: Duration = Duration::from_secs(3);
+
+// [start][end], the end may be left empty
+fn times(input: &str) -> IResult<&str, (u64, Option<u64>)> {
+    tuple((
+        delimited(char('['), delimited(space0, decimal, space0), char(']')),
+        delimited(
+            char('['),
+            delimited(space0, opt(decimal), space0),
+            char(']'),
+        ),
+    ))(input)
+}
+
+// None when the time doesn't fit in milliseconds
+fn deciseconds(value: u64) -> Option<Duration> {
+    value.checked_mul(100).map(Duration::from_millis)
+}
+
+/// Converts MPL2 text, `|` separating lines, to SubRip text.
+pub fn text(input: &str) -> String {
+    let mut spans: Vec<Span> = Vec::new();
+    for (n, line) in input.split('|').enumerate() {
+        if n > 0 {
+            spans.push(Span {
+                text: "\n".to_owned(),
+                style: TextStyle::default(),
+            });
+        }
+        let (italic, line) = match line.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        spans.push(Span {
+            text: line.to_owned(),
+            style: TextStyle {
+                italic,
+                ..TextStyle::default()
+            },
+        });
+    }
+    subrip::text::write(&spans)
+}
+
+/// Parses an MPL2 file. Subtitles without an end time last until the next
+/// one starts.
+pub fn parse(input: &str) -> Result<Vec<Entry>, ParseError> {
+    let mut entries = Vec::new();
+    let mut open_ended = Vec::new();
+    for (number, l) in lines(input) {
+        let Ok((text, (start, end))) = times(l.trim_start()) else {
+            return Err(ParseError {
+                line: number,
+                message: format!("expected [start][end] times, found {:?}", l),
+            });
+        };
+        open_ended.push(end.is_none());
+        let (Some(start), Some(end)) = (deciseconds(start), deciseconds(end.unwrap_or(start)))
+        else {
+            return Err(ParseError {
+                line: number,
+                message: format!("time out of range in {:?}", l),
+            });
+        };
+        entries.push(Entry {
+            start,
+            end,
+            text: self::text(text),
+            ..Entry::default()
+        });
+    }
+
+    finish(&mut entries, &open_ended, OPEN_END);
+    Ok(entries)
+}
+
+/// SubRip text as MPL2 text. Lines that are italic throughout get a `/`,
+/// other formatting is lost.
+pub fn write_text(entry: &Entry) -> String {
+    let lines = span_lines(entry);
+    let mut out = String::new();
+    for n in 0..lines.len() {
+        if n > 0 {
+            out.push('|');
+        }
+        let text = plain(&lines, n);
+        if common_style(&lines[n]).italic && !text.trim().is_empty() {
+            out.push('/');
+        }
+        out.push_str(&text);
+    }
+    out
+}
+
+fn to_deciseconds(time: Duration) -> u128 {
+    (time.as_millis() + 50) / 100
+}
+
+pub fn to_string(entries: &[Entry], line_ending: LineEnding) -> String {
+    let mut out = String::new();
+    for entry in entries {
+        let _ = write!(
+            out,
+            "[{}][{}]{}{}",
+            to_deciseconds(entry.start),
+            to_deciseconds(entry.end),
+            write_text(entry),
+            line_ending.as_str()
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_times_and_text() {
+        let entries = parse("[10][25]One|/Two\n[30][]Three\n[60][70]Four\n").unwrap();
+        let times: Vec<(u64, u64)> = entries
+            .iter()
+            .map(|e| (e.start.as_millis() as u64, e.end.as_millis() as u64))
+            .collect();
+        assert_eq!(times, [(1000, 2500), (3000, 6000), (6000, 7000)]);
+        assert_eq!(entries[0].text, "One\n<i>Two</i>");
+        assert_eq!(entries[2].index, 3);
+    }
+
+    #[test]
+    fn invalid_lines() {
+        for (input, line) in [
+            ("[1][2]a\nb\n", 2),
+            ("[1][2]a\n\n[x][2]b\n", 3),
+            ("[200000000000000000][200000000000000001]x\n", 1),
+            ("[1][184467440737095517]x\n", 1),
+        ] {
+            assert_eq!(parse(input).unwrap_err().line, line, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "[10][25]One|/Two\n[30][60]Three\n";
+        let entries = parse(input).unwrap();
+        assert_eq!(to_string(&entries, LineEnding::Lf), input);
+    }
+}
@@ -0,0 +1,386 @@
+//! SAMI (`.smi`): HTML-like `<SYNC Start=ms><P Class=ENCC>Text` blocks, each
+//! lasting until the next `<SYNC>`. A file can hold several languages, one
+//! `<P>` class each, declared in its `<STYLE>` block.
+//!
+//! Tags in the text (`<b>`, `<i>`, `<u>`, `<font>`) are what SubRip uses, so
+//! they are kept; `<br>` becomes a line break.
+
+use crate::finish;
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag_no_case, take_while1},
+    character::complete::{char, multispace0},
+    combinator::opt,
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+use std::fmt::Write as _;
+use std::time::Duration;
+use subrip::writer::LineEnding;
+use subrip::Entry;
+
+const OPEN_END: Duration = Duration::from_secs(3);
+
+/// A language declared as a `.CLASS { Name: …; lang: …; }` style rule.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Language {
+    pub class: String,
+    pub name: Option<String>,
+    pub lang: Option<String>,
+}
+
+// index of `needle` in `haystack` ignoring ASCII case
+fn find_no_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// The languages declared in the `<STYLE>` block, in order.
+pub fn languages(input: &str) -> Vec<Language> {
+    let Some(start) = find_no_case(input, "<style") else {
+        return Vec::new();
+    };
+    let css = &input[start..];
+    let css = &css[..find_no_case(css, "</style").unwrap_or(css.len())];
+
+    let mut languages = Vec::new();
+    let mut rest = css;
+    while let Some(n) = rest.find('.') {
+        rest = &rest[n + 1..];
+        let Some(open) = rest.find('{') else { break };
+        let class = rest[..open].trim();
+        let Some(close) = rest.find('}') else { break };
+        if class.is_empty() || class.contains(char::is_whitespace) || close < open {
+            continue;
+        }
+        let mut language = Language {
+            class: class.to_owned(),
+            ..Language::default()
+        };
+        for rule in rest[open + 1..close].split(';') {
+            if let Some((key, value)) = rule.split_once(':') {
+                let value = Some(value.trim().to_owned()).filter(|v| !v.is_empty());
+                match key.trim().to_ascii_lowercase().as_str() {
+                    "name" => language.name = value,
+                    "lang" => language.lang = value,
+                    _ => (),
+                }
+            }
+        }
+        languages.push(language);
+        rest = &rest[close + 1..];
+    }
+    languages
+}
+
+fn attribute_value(input: &str) -> IResult<&str, &str> {
+    preceded(
+        tuple((multispace0, char('='), multispace0)),
+        alt((
+            delimited(char('"'), is_not("\""), char('"')),
+            delimited(char('\''), is_not("'"), char('\'')),
+            take_while1(|c: char| !c.is_whitespace() && c != '>'),
+        )),
+    )(input)
+}
+
+// the value of `name` in the attributes of a tag
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    while let Some(n) = find_no_case(rest, name) {
+        let before = rest[..n].chars().last();
+        let after = &rest[n + name.len()..];
+        if before.is_none_or(char::is_whitespace) {
+            if let Ok((_, value)) = attribute_value(after) {
+                return Some(value);
+            }
+        }
+        rest = after;
+    }
+    None
+}
+
+// <SYNC Start=1000 ...>
+fn sync(input: &str) -> IResult<&str, Option<u64>> {
+    let (input, attributes) = delimited(
+        tag_no_case("<sync"),
+        opt(preceded(multispace0, take_while1(|c| c != '>'))),
+        char('>'),
+    )(input)?;
+    let start = attributes
+        .and_then(|a| attribute(a, "start"))
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    Ok((input, start))
+}
+
+// the text of each `<P>` in a sync block, by class
+fn paragraphs(block: &str) -> Vec<(Option<String>, String)> {
+    let mut out: Vec<(Option<String>, String)> = Vec::new();
+    let mut rest = block;
+    let mut current: Option<(Option<String>, String)> = None;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest.find('>').map(|n| n + 1).unwrap_or(rest.len());
+            let tag = &rest[1..end].trim_end_matches('>');
+            let name: String = tag
+                .trim_start_matches('/')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            match name.as_str() {
+                "p" if !tag.starts_with('/') => {
+                    out.extend(current.take());
+                    let class = attribute(&tag[1..], "class").map(|c| c.to_owned());
+                    current = Some((class, String::new()));
+                }
+                "p" => out.extend(current.take()),
+                "br" => current
+                    .get_or_insert_with(|| (None, String::new()))
+                    .1
+                    .push('\n'),
+                "b" | "i" | "u" | "s" | "font" => current
+                    .get_or_insert_with(|| (None, String::new()))
+                    .1
+                    .push_str(&rest[..end]),
+                // </BODY>, </SAMI>, comments, …
+                _ => (),
+            }
+            rest = &rest[end..];
+            continue;
+        }
+        let text = current.get_or_insert_with(|| (None, String::new()));
+        // source line breaks are only whitespace
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !text.1.ends_with([' ', '\n']) && !text.1.is_empty() {
+                text.1.push(' ');
+            }
+        } else {
+            text.1.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out.extend(current);
+
+    for (_, text) in out.iter_mut() {
+        *text = text
+            .split('\n')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_owned();
+    }
+    out
+}
+
+fn is_blank(text: &str) -> bool {
+    subrip::text::parse(text)
+        .iter()
+        .all(|s| s.text.trim().is_empty())
+}
+
+/// Reads the captions of one language, the first declared if `class` is
+/// `None`. A `<SYNC>` with only `&nbsp;` in it clears the screen.
+pub fn parse(input: &str, class: Option<&str>) -> Vec<Entry> {
+    let class = class
+        .map(|c| c.to_owned())
+        .or_else(|| languages(input).first().map(|l| l.class.clone()));
+    let Some(body) = find_no_case(input, "<sync") else {
+        return Vec::new();
+    };
+
+    let mut syncs: Vec<(Duration, String)> = Vec::new();
+    let mut rest = &input[body..];
+    while let Ok((after, start)) = sync(rest) {
+        let next = find_no_case(after, "<sync").unwrap_or(after.len());
+        let block = &after[..next];
+        rest = &after[next..];
+        let Some(start) = start else { continue };
+
+        let text = paragraphs(block)
+            .into_iter()
+            .find(|(c, _)| match (c, &class) {
+                (Some(c), Some(class)) => c.eq_ignore_ascii_case(class),
+                _ => true,
+            })
+            .map(|(_, text)| text)
+            .unwrap_or_default();
+        syncs.push((Duration::from_millis(start), text));
+        if rest.is_empty() {
+            break;
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut open_ended = Vec::new();
+    for (n, (start, text)) in syncs.iter().enumerate() {
+        if is_blank(text) {
+            continue;
+        }
+        let end = syncs.get(n + 1).map(|(next, _)| *next);
+        open_ended.push(end.is_none());
+        entries.push(Entry {
+            start: *start,
+            end: end.unwrap_or(*start),
+            text: text.clone(),
+            ..Entry::default()
+        });
+    }
+    finish(&mut entries, &open_ended, OPEN_END);
+    entries
+}
+
+#[derive(Clone, Debug)]
+pub struct WriteOptions {
+    pub title: Option<String>,
+    pub language: Language,
+    pub line_ending: LineEnding,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            title: None,
+            language: Language {
+                class: "ENCC".to_owned(),
+                name: Some("English".to_owned()),
+                lang: Some("en-US".to_owned()),
+            },
+            line_ending: LineEnding::CrLf,
+        }
+    }
+}
+
+/// Writes entries as SAMI in a single language, with a clearing `&nbsp;`
+/// sync after any caption not directly followed by another.
+pub fn to_string(entries: &[Entry], options: &WriteOptions) -> String {
+    let nl = options.line_ending.as_str();
+    let language = &options.language;
+    let mut out = String::new();
+
+    let _ = write!(out, "<SAMI>{nl}<HEAD>{nl}");
+    if let Some(title) = &options.title {
+        let _ = write!(out, "<TITLE>{}</TITLE>{nl}", title);
+    }
+    let _ = write!(out, "<STYLE TYPE=\"text/css\">{nl}<!--{nl}");
+    let _ = write!(
+        out,
+        "P {{ margin-left: 8pt; margin-right: 8pt; margin-bottom: 2pt; margin-top: 2pt; \
+         text-align: center; font-size: 20pt; font-family: Arial; \
+         font-weight: normal; color: white; }}{nl}"
+    );
+    let mut rule = String::new();
+    if let Some(name) = &language.name {
+        let _ = write!(rule, " Name: {};", name);
+    }
+    if let Some(lang) = &language.lang {
+        let _ = write!(rule, " lang: {};", lang);
+    }
+    let _ = write!(
+        out,
+        ".{} {{{} SAMIType: CC; }}{nl}-->{nl}</STYLE>{nl}</HEAD>{nl}<BODY>{nl}",
+        language.class, rule
+    );
+
+    for (n, entry) in entries.iter().enumerate() {
+        let text = entry.text.replace("\r\n", "\n").replace('\n', "<br>");
+        let _ = write!(
+            out,
+            "<SYNC Start={}><P Class={}>{}{nl}",
+            entry.start.as_millis(),
+            language.class,
+            text
+        );
+        let next = entries.get(n + 1).map(|e| e.start);
+        if next.is_none_or(|next| next > entry.end) {
+            let _ = write!(
+                out,
+                "<SYNC Start={}><P Class={}>&nbsp;{nl}",
+                entry.end.as_millis(),
+                language.class
+            );
+        }
+    }
+    let _ = write!(out, "</BODY>{nl}</SAMI>{nl}");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_LANGUAGES: &str = "<SAMI>\n<HEAD>\n<STYLE TYPE=\"text/css\">\n<!--\n\
+        P { font-size: 20pt; }\n\
+        .ENCC { Name: English; lang: en-US; SAMIType: CC; }\n\
+        .FRCC { Name: French; lang: fr-FR; }\n\
+        -->\n</STYLE>\n</HEAD>\n<BODY>\n\
+        <SYNC Start=1000><P Class=ENCC>Hello<br>  there\n<P Class=FRCC>Bonjour\n\
+        <SYNC Start=2500><P Class=ENCC>&nbsp;<P Class=FRCC>&nbsp;\n\
+        <sync start=\"3000\"><p class=encc><i>Bye</i>\n\
+        </BODY>\n</SAMI>\n";
+
+    fn times(entries: &[Entry]) -> Vec<(u64, u64)> {
+        entries
+            .iter()
+            .map(|e| (e.start.as_millis() as u64, e.end.as_millis() as u64))
+            .collect()
+    }
+
+    #[test]
+    fn declared_languages() {
+        let declared = languages(TWO_LANGUAGES);
+        assert_eq!(
+            declared
+                .iter()
+                .map(|l| (l.class.as_str(), l.name.as_deref(), l.lang.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                ("ENCC", Some("English"), Some("en-US")),
+                ("FRCC", Some("French"), Some("fr-FR")),
+            ]
+        );
+        assert!(languages("<SAMI><BODY></BODY></SAMI>").is_empty());
+    }
+
+    #[test]
+    fn parse_each_language() {
+        let english = parse(TWO_LANGUAGES, None);
+        assert_eq!(times(&english), [(1000, 2500), (3000, 6000)]);
+        assert_eq!(english[0].text, "Hello\nthere");
+        assert_eq!(english[1].text, "<i>Bye</i>");
+        assert_eq!(english[1].index, 2);
+
+        let french = parse(TWO_LANGUAGES, Some("frcc"));
+        assert_eq!(times(&french), [(1000, 2500)]);
+        assert_eq!(french[0].text, "Bonjour");
+
+        assert!(parse("<SAMI></SAMI>", None).is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let entries = parse(TWO_LANGUAGES, None);
+        let options = WriteOptions {
+            title: Some("Title".to_owned()),
+            line_ending: LineEnding::Lf,
+            ..WriteOptions::default()
+        };
+        let written = to_string(&entries, &options);
+        assert!(written.contains("<TITLE>Title</TITLE>\n"), "{}", written);
+        assert!(written.contains(".ENCC { Name: English; lang: en-US; SAMIType: CC; }"));
+        assert!(written.contains("<SYNC Start=1000><P Class=ENCC>Hello<br>there\n"));
+        assert!(written.contains("<SYNC Start=2500><P Class=ENCC>&nbsp;\n"));
+        assert!(!written.contains('\r'));
+
+        let back = parse(&written, None);
+        assert_eq!(times(&back), times(&entries));
+        assert_eq!(
+            back.iter().map(|e| &e.text).collect::<Vec<_>>(),
+            entries.iter().map(|e| &e.text).collect::<Vec<_>>()
+        );
+    }
+}
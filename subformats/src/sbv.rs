@@ -0,0 +1,191 @@
+//! YouTube SBV: a `H:MM:SS.mmm,H:MM:SS.mmm` line followed by the text, with
+//! blank lines between captions. The text has no formatting.
+
+use crate::ParseError;
+use nom::{
+    character::complete::{char, space0, u64 as decimal},
+    combinator::eof,
+    error::ErrorKind,
+    sequence::{delimited, terminated, tuple},
+    IResult,
+};
+use std::fmt::Write as _;
+use std::time::Duration;
+use subrip::text::{Span, TextStyle};
+use subrip::writer::LineEnding;
+use subrip::Entry;
+
+// hours:minutes:seconds.milliseconds
+pub fn timestamp(input: &str) -> IResult<&str, Duration> {
+    let (input, (hours, mins, secs, millis)) = tuple((
+        terminated(decimal, char(':')),
+        terminated(decimal, char(':')),
+        terminated(decimal, char('.')),
+        decimal,
+    ))(input)?;
+    let total = hours
+        .checked_mul(3_600_000)
+        .zip(mins.checked_mul(60_000))
+        .zip(secs.checked_mul(1_000))
+        .and_then(|((h, m), s)| h.checked_add(m)?.checked_add(s)?.checked_add(millis))
+        .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, ErrorKind::TooLarge)))?;
+    Ok((input, Duration::from_millis(total)))
+}
+
+fn timing(input: &str) -> IResult<&str, (Duration, Duration)> {
+    let (input, (start, _, end, _)) = tuple((
+        timestamp,
+        delimited(space0, char(','), space0),
+        timestamp,
+        terminated(space0, eof),
+    ))(input)?;
+    Ok((input, (start, end)))
+}
+
+/// Parses an SBV file. Text is escaped so nothing in it reads as a SubRip
+/// tag.
+pub fn parse(input: &str) -> Result<Vec<Entry>, ParseError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut text: Vec<&str> = Vec::new();
+    let mut previous_blank = true;
+
+    let flush = |entries: &mut Vec<Entry>, text: &mut Vec<&str>| {
+        if let Some(entry) = entries.last_mut() {
+            entry.text = subrip::text::write(&[Span {
+                text: text.join("\n"),
+                style: TextStyle::default(),
+            }]);
+        }
+        text.clear();
+    };
+
+    for (n, line) in input
+        .split('\n')
+        .map(|l| l.trim_end_matches('\r'))
+        .enumerate()
+    {
+        if line.trim().is_empty() {
+            previous_blank = true;
+            continue;
+        }
+        if previous_blank {
+            if let Ok((_, (start, end))) = timing(line.trim()) {
+                flush(&mut entries, &mut text);
+                entries.push(Entry {
+                    index: entries.len() as u32 + 1,
+                    start,
+                    end,
+                    ..Entry::default()
+                });
+                previous_blank = false;
+                continue;
+            }
+        }
+        if entries.is_empty() {
+            return Err(ParseError {
+                line: n + 1,
+                message: format!("expected a start,end timing line, found {:?}", line),
+            });
+        }
+        if previous_blank && !text.is_empty() {
+            // a blank line inside a caption, keep it as a line break
+            text.push("");
+        }
+        text.push(line);
+        previous_blank = false;
+    }
+    flush(&mut entries, &mut text);
+
+    Ok(entries)
+}
+
+pub fn format_timestamp(time: Duration) -> String {
+    let ms = time.as_millis();
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    )
+}
+
+/// Writes entries as SBV, dropping their formatting.
+pub fn to_string(entries: &[Entry], line_ending: LineEnding) -> String {
+    let nl = line_ending.as_str();
+    let mut out = String::new();
+    for (n, entry) in entries.iter().enumerate() {
+        if n > 0 {
+            out.push_str(nl);
+        }
+        let text: String = entry
+            .parsed_text()
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        let _ = write!(
+            out,
+            "{},{}{}",
+            format_timestamp(entry.start),
+            format_timestamp(entry.end),
+            nl
+        );
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            out.push_str(line);
+            out.push_str(nl);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(entries: &[Entry]) -> Vec<(u64, u64)> {
+        entries
+            .iter()
+            .map(|e| (e.start.as_millis() as u64, e.end.as_millis() as u64))
+            .collect()
+    }
+
+    #[test]
+    fn parse_captions() {
+        let input = "\u{feff}0:00:01.000,0:00:02.500\r\nOne\r\nTwo\r\n\r\n\
+                     0:00:03.000, 0:00:04.000\nThree\n\nstill three\n\n\
+                     1:02:03.004,1:02:05.000\n<i>a</i>\n";
+        let entries = parse(input).unwrap();
+        assert_eq!(
+            times(&entries),
+            [(1000, 2500), (3000, 4000), (3_723_004, 3_725_000)]
+        );
+        assert_eq!(entries[0].text, "One\nTwo");
+        assert_eq!(entries[1].text, "Three\n\nstill three");
+        assert_eq!(entries[1].index, 2);
+        assert_eq!(entries[2].parsed_text()[0].text, "<i>a</i>");
+    }
+
+    #[test]
+    fn invalid_input() {
+        for (input, line) in [
+            ("One\n", 1),
+            ("\n\n0:00:01,0:00:02\nOne\n", 3),
+            ("9999999999999999:00:00.000,0:00:01.000\nx\n", 1),
+            ("0:00:00.000,0:00:18446744073709551.616\nx\n", 1),
+        ] {
+            assert_eq!(parse(input).unwrap_err().line, line, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "0:00:01.000,0:00:02.500\nOne\nTwo\n\n1:02:03.004,1:02:05.000\nThree\n";
+        let entries = parse(input).unwrap();
+        assert_eq!(to_string(&entries, LineEnding::Lf), input);
+        assert_eq!(
+            to_string(&entries, LineEnding::CrLf),
+            input.replace('\n', "\r\n")
+        );
+    }
+}
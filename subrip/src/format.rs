@@ -0,0 +1,75 @@
+//! SubRip through the workspace's common [`SubtitleFormat`] trait.
+
+use crate::reader::{parse_lenient, ParseError};
+use crate::writer::{to_string, WriteOptions};
+use crate::Entry;
+use parsing_utils::format::{Cue, Format, FormatError, SubtitleFormat};
+
+impl From<&Entry> for Cue {
+    fn from(entry: &Entry) -> Cue {
+        Cue {
+            start: entry.start,
+            end: entry.end,
+            alignment: entry.alignment,
+            text: entry.text.clone(),
+        }
+    }
+}
+
+impl From<&Cue> for Entry {
+    fn from(cue: &Cue) -> Entry {
+        Entry {
+            start: cue.start,
+            end: cue.end,
+            alignment: cue.alignment,
+            text: cue.text.clone(),
+            ..Entry::default()
+        }
+    }
+}
+
+impl From<ParseError> for FormatError {
+    fn from(e: ParseError) -> FormatError {
+        FormatError {
+            line: Some(e.line),
+            message: e.message,
+        }
+    }
+}
+
+/// Cues from entries, for formats that read into SubRip entries.
+pub fn cues(entries: &[Entry]) -> Vec<Cue> {
+    entries.iter().map(Cue::from).collect()
+}
+
+/// Entries numbered from 1, for formats that write from SubRip entries.
+pub fn entries(cues: &[Cue]) -> Vec<Entry> {
+    cues.iter()
+        .enumerate()
+        .map(|(n, cue)| Entry {
+            index: n as u32 + 1,
+            ..Entry::from(cue)
+        })
+        .collect()
+}
+
+/// Reads leniently, the way players do; use [`crate::reader`] directly to
+/// see what was worked around.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SubRip {
+    pub options: WriteOptions,
+}
+
+impl SubtitleFormat for SubRip {
+    fn format(&self) -> Format {
+        Format::SubRip
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(cues(&parse_lenient(input).0))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        to_string(&entries(cues), &self.options)
+    }
+}
@@ -1,47 +1,132 @@
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{char, line_ending, not_line_ending, one_of, u64 as decimal},
-    combinator::{eof, verify},
+    bytes::complete::{tag, tag_no_case},
+    character::complete::{
+        char, i64 as signed, line_ending, not_line_ending, one_of, space0, space1, u64 as decimal,
+    },
+    combinator::{eof, map, opt, verify},
+    error::ErrorKind,
     multi::{many0, many1},
-    sequence::{separated_pair, terminated, tuple},
+    sequence::{pair, preceded, terminated, tuple},
     IResult,
 };
 use parsing_utils::*;
 use std::time::Duration;
 
-#[derive(Debug, Clone)]
+pub mod format;
+pub mod reader;
+pub mod text;
+pub mod timestamp;
+pub mod timing;
+pub mod writer;
+
+pub use timestamp::Timestamp;
+
+/// A numbered cue. With the `serde` feature, `start` and `end` are written
+/// as whole milliseconds.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Entry {
     pub index: u32,
+    #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+    #[cfg_attr(feature = "schema", schemars(with = "u64"))]
     pub start: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+    #[cfg_attr(feature = "schema", schemars(with = "u64"))]
     pub end: Duration,
+    /// Numpad-style position from an `{\anN}` tag, taken out of the text.
+    pub alignment: Option<u8>,
+    pub coordinates: Option<Coordinates>,
     pub text: String,
 }
 
+/// The `X1:… X2:… Y1:… Y2:…` box some files put after the timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Coordinates {
+    pub x1: i64,
+    pub x2: i64,
+    pub y1: i64,
+    pub y2: i64,
+}
+
+/// The JSON schema of a list of entries, as written with the `serde`
+/// feature.
+#[cfg(feature = "schema")]
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(Vec<Entry>)
+}
+
+impl Entry {
+    pub fn parsed_text(&self) -> Vec<text::Span> {
+        text::parse(&self.text)
+    }
+}
+
 //  hours:minutes:seconds,milliseconds
-fn duration(input: &str) -> IResult<&str, Duration> {
+pub fn timestamp(input: &str) -> IResult<&str, Timestamp> {
     let (input, (hours, mins, secs, millis)) = tuple((
         terminated(decimal, char(':')),
         terminated(decimal, char(':')),
         terminated(decimal, one_of(",")),
         decimal,
     ))(input)?;
-    Ok((
-        input,
-        Duration::from_millis(hours * 3_600_000 + mins * 60_000 + secs * 1_000 + millis),
-    ))
+    let too_large = || nom::Err::Error(nom::error::Error::new(input, ErrorKind::TooLarge));
+    let field = |value: u64| i64::try_from(value).map_err(|_| too_large());
+    let timestamp =
+        Timestamp::checked_from_hms(field(hours)?, field(mins)?, field(secs)?, field(millis)?)
+            .ok_or_else(too_large)?;
+    Ok((input, timestamp))
+}
+
+fn duration(input: &str) -> IResult<&str, Duration> {
+    map(timestamp, Timestamp::as_duration)(input)
+}
+
+// X1:left X2:right Y1:top Y2:bottom
+fn coordinates(input: &str) -> IResult<&str, Coordinates> {
+    let (input, (x1, x2, y1, y2)) = tuple((
+        preceded(pair(space1, tag_no_case("X1:")), signed),
+        preceded(pair(space1, tag_no_case("X2:")), signed),
+        preceded(pair(space1, tag_no_case("Y1:")), signed),
+        preceded(pair(space1, tag_no_case("Y2:")), signed),
+    ))(input)?;
+    Ok((input, Coordinates { x1, x2, y1, y2 }))
+}
+
+// removes every `{\anN}` from the text, keeping the first position
+fn take_alignment(text: &str) -> (Option<u8>, String) {
+    let mut alignment = None;
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(n) = rest.find("{\\an") {
+        let tag = &rest.as_bytes()[n..];
+        if tag.len() >= 6 && (b'1'..=b'9').contains(&tag[4]) && tag[5] == b'}' {
+            alignment.get_or_insert(tag[4] - b'0');
+            out.push_str(&rest[..n]);
+            rest = &rest[n + 6..];
+        } else {
+            out.push_str(&rest[..n + 1]);
+            rest = &rest[n + 1..];
+        }
+    }
+    out.push_str(rest);
+    (alignment, out)
 }
 
 pub fn entry(input: &str) -> IResult<&str, Entry> {
     let (input, index) = terminated(decimal, line_ending)(input.trim_start())?;
-    let (input, (start, end)) = terminated(
-        separated_pair(duration, ws(tag("-->")), duration),
-        line_ending,
+    let (input, (start, _, end, coordinates)) = terminated(
+        tuple((duration, ws(tag("-->")), duration, opt(coordinates))),
+        pair(space0, line_ending),
     )(input)?;
     let (input, line) = many1(terminated(
         verify(not_line_ending, |s: &str| !s.is_empty()),
         alt((eof, line_ending)),
     ))(input)?;
+    let (alignment, text) = take_alignment(&line.join("\n"));
 
     Ok((
         input,
@@ -49,11 +134,95 @@ pub fn entry(input: &str) -> IResult<&str, Entry> {
             index: index as u32,
             start,
             end,
-            text: line.join("\n"),
+            alignment,
+            coordinates,
+            text,
         },
     ))
 }
 
+/// Parses cues until the first one that doesn't match, see
+/// [`reader::parse_strict`] for a version that reports where it stopped.
 pub fn entries(input: &str) -> IResult<&str, Vec<Entry>> {
     many0(entry)(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment_tags() {
+        for (text, alignment, rest) in [
+            ("{\\an8}Top", Some(8), "Top"),
+            ("Top{\\an7}", Some(7), "Top"),
+            ("{\\an1}a\n{\\an9}b", Some(1), "a\nb"),
+            ("{\\an0}a", None, "{\\an0}a"),
+            ("{\\an10}a", None, "{\\an10}a"),
+            ("{\\an8 }a", None, "{\\an8 }a"),
+            ("{\\an", None, "{\\an"),
+            ("{\\b1}a{\\an", None, "{\\b1}a{\\an"),
+            ("plain", None, "plain"),
+        ] {
+            assert_eq!(
+                take_alignment(text),
+                (alignment, rest.to_owned()),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn coordinate_parsing() {
+        assert_eq!(
+            coordinates(" X1:10 X2:-20 y1:30 Y2:40 rest"),
+            Ok((
+                " rest",
+                Coordinates {
+                    x1: 10,
+                    x2: -20,
+                    y1: 30,
+                    y2: 40
+                }
+            ))
+        );
+        for input in [
+            " X1:10 X2:20 Y1:30",
+            "X1:10 X2:20 Y1:30 Y2:40",
+            " X1:a X2:2 Y1:3 Y2:4",
+        ] {
+            assert!(coordinates(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn positions_parse_and_write() {
+        let input = "7\n00:00:01,000 --> 00:00:02,000 X1:1 X2:2 Y1:3 Y2:4\n{\\an8}Top\nline\n";
+        let (_, positioned) = entry(input).unwrap();
+        assert_eq!(positioned.index, 7);
+        assert_eq!(positioned.alignment, Some(8));
+        assert_eq!(
+            positioned.coordinates,
+            Some(Coordinates {
+                x1: 1,
+                x2: 2,
+                y1: 3,
+                y2: 4
+            })
+        );
+        assert_eq!(positioned.text, "Top\nline");
+        assert_eq!(positioned.to_string(), input);
+
+        let (_, plain) = entry("1\n00:00:01,000 --> 00:00:02,000\nBottom\n").unwrap();
+        assert_eq!((plain.alignment, plain.coordinates), (None, None));
+        let only_alignment = Entry {
+            text: String::new(),
+            ..positioned.clone()
+        };
+        assert_eq!(
+            only_alignment.to_string(),
+            "7\n00:00:01,000 --> 00:00:02,000 X1:1 X2:2 Y1:3 Y2:4\n{\\an8}\n"
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! Whole-file parsing with error reporting.
+//!
+//! [`parse_strict`] accepts the same grammar as [`crate::entry`] but fails
+//! with a line number instead of stopping at the first bad cue the way
+//! [`crate::entries`] does. [`parse_lenient`] recovers from the mistakes real
+//! files are full of and reports each one it worked around.
+
+use crate::{entry, Coordinates, Entry};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line parsing failed on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    ByteOrderMark,
+    MissingIndex,
+    /// `.` or `:` used instead of `,` before the milliseconds.
+    WrongSeparator,
+    /// Fewer than three millisecond digits, read as a number of milliseconds
+    /// the way players do.
+    ShortMilliseconds,
+    /// A negative time, clamped to zero.
+    NegativeTime,
+    EmptyText,
+    /// Blank lines inside a cue's text, kept in the text.
+    BlankLinesInText,
+    /// A timing line that couldn't be read; the cue was dropped.
+    InvalidTiming(String),
+    /// Text outside of any cue, ignored.
+    StrayText(String),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::ByteOrderMark => write!(f, "byte order mark"),
+            Issue::MissingIndex => write!(f, "missing cue number"),
+            Issue::WrongSeparator => write!(f, "milliseconds not separated by a comma"),
+            Issue::ShortMilliseconds => write!(f, "fewer than three millisecond digits"),
+            Issue::NegativeTime => write!(f, "negative time"),
+            Issue::EmptyText => write!(f, "cue without text"),
+            Issue::BlankLinesInText => write!(f, "blank lines inside a cue"),
+            Issue::InvalidTiming(line) => write!(f, "invalid timing line {:?}", line),
+            Issue::StrayText(line) => write!(f, "text outside a cue {:?}", line),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 1-based line the issue was found on.
+    pub line: usize,
+    pub issue: Issue,
+}
+
+fn line_of(input: &str, rest: &str) -> usize {
+    input[..input.len() - rest.len()].matches('\n').count() + 1
+}
+
+/// Parses a whole file, failing on the first cue that isn't well-formed.
+/// A leading byte order mark is allowed.
+pub fn parse_strict(input: &str) -> Result<Vec<Entry>, ParseError> {
+    let body = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut rest = body;
+    let mut entries = Vec::new();
+
+    while !rest.trim().is_empty() {
+        match entry(rest) {
+            Ok((remaining, e)) => {
+                entries.push(e);
+                rest = remaining;
+            }
+            Err(err) => {
+                let cue = line_of(body, rest.trim_start());
+                return Err(match err {
+                    nom::Err::Error(e) | nom::Err::Failure(e) => ParseError {
+                        line: line_of(body, e.input),
+                        message: format!(
+                            "invalid cue starting on line {} near {:?}",
+                            cue,
+                            e.input.lines().next().unwrap_or("")
+                        ),
+                    },
+                    nom::Err::Incomplete(_) => ParseError {
+                        line: cue,
+                        message: "incomplete cue".to_owned(),
+                    },
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+struct Timing {
+    start: Duration,
+    end: Duration,
+    coordinates: Option<Coordinates>,
+    issues: Vec<Issue>,
+}
+
+// [-]H:MM:SS,mmm with any separator before the milliseconds, returning the
+// time in milliseconds and what was off about it
+fn timestamp(input: &str, issues: &mut Vec<Issue>) -> Option<(i64, usize)> {
+    let (negative, digits_start) = match input.strip_prefix('-') {
+        Some(_) => (true, 1),
+        None => (false, 0),
+    };
+    let mut fields: Vec<(i64, usize)> = Vec::new();
+    let mut separators = Vec::new();
+    let mut pos = digits_start;
+    let bytes = input.as_bytes();
+
+    loop {
+        let negative_field = bytes.get(pos) == Some(&b'-');
+        let from = pos + negative_field as usize;
+        let len = bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if len == 0 {
+            return None;
+        }
+        let value: i64 = input[from..from + len].parse().ok()?;
+        fields.push((if negative_field { -value } else { value }, len));
+        pos = from + len;
+        if fields.len() == 4 {
+            break;
+        }
+        match bytes.get(pos) {
+            Some(&c @ (b':' | b',' | b'.')) if fields.len() < 4 => {
+                separators.push(c);
+                pos += 1;
+            }
+            _ => break,
+        }
+    }
+
+    // MM:SS,mmm without hours
+    if fields.len() == 3 && separators[1] != b':' {
+        fields.insert(0, (0, 2));
+        separators.insert(0, b':');
+    }
+    if fields.len() != 4 || separators[0] != b':' || separators[1] != b':' {
+        return None;
+    }
+    if separators[2] != b',' {
+        issues.push(Issue::WrongSeparator);
+    }
+    if fields[3].1 < 3 {
+        issues.push(Issue::ShortMilliseconds);
+    }
+
+    let ms = fields[0]
+        .0
+        .checked_mul(60)?
+        .checked_add(fields[1].0)?
+        .checked_mul(60)?
+        .checked_add(fields[2].0)?
+        .checked_mul(1000)?
+        .checked_add(fields[3].0)?;
+    let ms = if negative { -ms } else { ms };
+    if ms < 0 || fields.iter().any(|f| f.0 < 0) {
+        issues.push(Issue::NegativeTime);
+    }
+    Some((ms, pos))
+}
+
+fn timing(line: &str) -> Option<Timing> {
+    let (left, right) = line.split_once("-->")?;
+    let mut issues = Vec::new();
+    let left = left.trim();
+    let (start, used) = timestamp(left, &mut issues)?;
+    if used != left.len() {
+        return None;
+    }
+    let right = right.trim_start();
+    let (end, used) = timestamp(right, &mut issues)?;
+    let rest = &right[used..];
+    let coordinates = if rest.trim().is_empty() {
+        None
+    } else {
+        match crate::coordinates(rest) {
+            Ok((tail, c)) if tail.trim().is_empty() => Some(c),
+            _ => return None,
+        }
+    };
+    issues.dedup();
+
+    let clamp = |ms: i64| Duration::from_millis(ms.max(0) as u64);
+    Some(Timing {
+        start: clamp(start),
+        end: clamp(end),
+        coordinates,
+        issues,
+    })
+}
+
+/// Parses whatever can be recovered from a file: cues without indices or
+/// text, `.` separators, short or negative times, extra blank lines and
+/// byte order marks. Cues end where the next timing line begins, so blank
+/// lines inside text don't cut it short.
+pub fn parse_lenient(input: &str) -> (Vec<Entry>, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let input = match input.strip_prefix('\u{feff}') {
+        Some(rest) => {
+            diagnostics.push(Diagnostic {
+                line: 1,
+                issue: Issue::ByteOrderMark,
+            });
+            rest
+        }
+        None => input,
+    };
+    let lines: Vec<&str> = input
+        .split('\n')
+        .map(|l| l.trim_end_matches('\r'))
+        .collect();
+
+    let is_timing = |n: usize| lines.get(n).is_some_and(|l| l.contains("-->"));
+    let is_index = |n: usize| {
+        let l = lines[n].trim();
+        !l.is_empty() && l.bytes().all(|b| b.is_ascii_digit()) && is_timing(n + 1)
+    };
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut n = 0;
+    while n < lines.len() {
+        if !is_timing(n) {
+            if !lines[n].trim().is_empty() && !is_index(n) {
+                diagnostics.push(Diagnostic {
+                    line: n + 1,
+                    issue: Issue::StrayText(lines[n].to_owned()),
+                });
+            }
+            n += 1;
+            continue;
+        }
+
+        let timing_line = n;
+        let index = match n.checked_sub(1).filter(|&i| is_index(i)) {
+            Some(i) => lines[i].trim().parse::<u32>().ok(),
+            None => None,
+        };
+        n += 1;
+        let text_start = n;
+        while n < lines.len() && !is_timing(n) && !is_index(n) {
+            n += 1;
+        }
+        let mut text_lines = &lines[text_start..n];
+        while let Some((last, init)) = text_lines.split_last() {
+            if !last.trim().is_empty() {
+                break;
+            }
+            text_lines = init;
+        }
+
+        let Some(timing) = timing(lines[timing_line]) else {
+            diagnostics.push(Diagnostic {
+                line: timing_line + 1,
+                issue: Issue::InvalidTiming(lines[timing_line].to_owned()),
+            });
+            continue;
+        };
+
+        let mut issue = |line: usize, issue: Issue| diagnostics.push(Diagnostic { line, issue });
+        if index.is_none() {
+            issue(timing_line + 1, Issue::MissingIndex);
+        }
+        for i in timing.issues {
+            issue(timing_line + 1, i);
+        }
+        if text_lines.is_empty() {
+            issue(timing_line + 1, Issue::EmptyText);
+        } else if text_lines.iter().any(|l| l.trim().is_empty()) {
+            issue(timing_line + 2, Issue::BlankLinesInText);
+        }
+
+        let (alignment, text) = crate::take_alignment(&text_lines.join("\n"));
+        entries.push(Entry {
+            index: index.unwrap_or_else(|| entries.last().map_or(1, |e| e.index + 1)),
+            start: timing.start,
+            end: timing.end,
+            alignment,
+            coordinates: timing.coordinates,
+            text,
+        });
+    }
+
+    (entries, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn lenient_issues() {
+        for (input, issues, times) in [
+            (
+                "1\n00:00:01.000 --> 00:00:02,000\na\n",
+                vec![(2, Issue::WrongSeparator)],
+                vec![(1000, 2000)],
+            ),
+            (
+                "1\n00:00:01,000 --> 00:00:02:000\na\n",
+                vec![(2, Issue::WrongSeparator)],
+                vec![(1000, 2000)],
+            ),
+            (
+                "00:00:01,000 --> 00:00:02,000\na\n",
+                vec![(1, Issue::MissingIndex)],
+                vec![(1000, 2000)],
+            ),
+            (
+                "1\n00:00:01,000 --> 00:00:02,000\n\n2\n00:00:03,000 --> 00:00:04,000\nb\n",
+                vec![(2, Issue::EmptyText)],
+                vec![(1000, 2000), (3000, 4000)],
+            ),
+            (
+                "1\n00:00:01,000 --> 00:00:02,000\na\n\n\nb\n\n\n\n",
+                vec![(3, Issue::BlankLinesInText)],
+                vec![(1000, 2000)],
+            ),
+            (
+                "\u{feff}1\n00:00:01,000 --> 00:00:02,000\na\n",
+                vec![(1, Issue::ByteOrderMark)],
+                vec![(1000, 2000)],
+            ),
+            (
+                "1\n-00:00:01,000 --> 00:00:02,000\na\n",
+                vec![(2, Issue::NegativeTime)],
+                vec![(0, 2000)],
+            ),
+            (
+                "1\n00:00:-01,000 --> 00:00:02,000\na\n",
+                vec![(2, Issue::NegativeTime)],
+                vec![(0, 2000)],
+            ),
+            (
+                "1\n00:00:01,5 --> 00:00:02,50\na\n",
+                vec![(2, Issue::ShortMilliseconds)],
+                vec![(1005, 2050)],
+            ),
+            (
+                "1\n00:00:01,000 -> 00:00:02,000\na\n",
+                vec![
+                    (1, Issue::StrayText("1".to_owned())),
+                    (
+                        2,
+                        Issue::StrayText("00:00:01,000 -> 00:00:02,000".to_owned()),
+                    ),
+                    (3, Issue::StrayText("a".to_owned())),
+                ],
+                vec![],
+            ),
+            (
+                "1\n00:00:01,000 --> soon\na\n\n2\n00:00:03,000 --> 00:00:04,000\nb\n",
+                vec![(2, Issue::InvalidTiming("00:00:01,000 --> soon".to_owned()))],
+                vec![(3000, 4000)],
+            ),
+            (
+                "1\n999999999999999999:00:00,000 --> 00:00:01,000\na\n",
+                vec![(
+                    2,
+                    Issue::InvalidTiming(
+                        "999999999999999999:00:00,000 --> 00:00:01,000".to_owned(),
+                    ),
+                )],
+                vec![],
+            ),
+        ] {
+            let (entries, diagnostics) = parse_lenient(input);
+            let found: Vec<(usize, Issue)> =
+                diagnostics.into_iter().map(|d| (d.line, d.issue)).collect();
+            assert_eq!(found, issues, "{:?}", input);
+            let found: Vec<(u64, u64)> = entries
+                .iter()
+                .map(|e| (e.start.as_millis() as u64, e.end.as_millis() as u64))
+                .collect();
+            assert_eq!(found, times, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn lenient_text_and_indices() {
+        let (entries, diagnostics) = parse_lenient(
+            "3\r\n00:00:01,000 --> 00:00:02,000\r\none\r\n\r\n\r\ntwo\r\n\
+             00:00:03,000 --> 00:00:04,000 X1:1 X2:2 Y1:3 Y2:4\r\n{\\an8}three\r\n",
+        );
+        assert_eq!(entries[0].index, 3);
+        assert_eq!(entries[0].text, "one\n\n\ntwo");
+        assert_eq!(entries[1].index, 4);
+        assert_eq!((entries[1].start, entries[1].end), (ms(3000), ms(4000)));
+        assert_eq!(entries[1].alignment, Some(8));
+        assert_eq!(entries[1].text, "three");
+        assert!(entries[1].coordinates.is_some());
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.line, &d.issue))
+                .collect::<Vec<_>>(),
+            [(3, &Issue::BlankLinesInText), (7, &Issue::MissingIndex)]
+        );
+    }
+
+    #[test]
+    fn strict_error_lines() {
+        for (input, line) in [
+            ("1\n00:00:01.000 --> 00:00:02,000\na\n", 2),
+            ("x\n00:00:01,000 --> 00:00:02,000\na\n", 1),
+            (
+                "1\n00:00:01,000 --> 00:00:02,000\na\n\n2\n00:00:03,000 -> 00:00:04,000\nb\n",
+                6,
+            ),
+            (
+                "\u{feff}1\n00:00:01,000 --> 00:00:02,000\na\n\n\n\n2\n00:00:03,000 --> x\nb\n",
+                8,
+            ),
+        ] {
+            assert_eq!(parse_strict(input).unwrap_err().line, line, "{:?}", input);
+        }
+
+        let entries = parse_strict(
+            "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\na\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nb\r\n",
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].text, "b");
+    }
+}
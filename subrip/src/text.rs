@@ -0,0 +1,345 @@
+//! The HTML-like formatting tags found in SubRip text.
+//!
+//! Parsing is lenient the way players are: unclosed tags run to the end of
+//! the cue, misnested tags close whatever they name, `{b}`-style braces are
+//! accepted in place of angle brackets and unknown tags are kept as text.
+
+use parsing_utils::entity;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikeout: bool,
+    pub font: Font,
+}
+
+/// The attributes of the innermost `<font>` tag, as written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Font {
+    pub color: Option<String>,
+    pub face: Option<String>,
+    pub size: Option<String>,
+}
+
+impl Font {
+    fn is_empty(&self) -> bool {
+        self.color.is_none() && self.face.is_none() && self.size.is_none()
+    }
+
+    /// The font color as RGB, from `#RRGGBB`, `#RGB`, bare hex or one of the
+    /// basic HTML color names.
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        parse_color(self.color.as_deref()?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+pub fn parse_color(color: &str) -> Option<(u8, u8, u8)> {
+    let color = color.trim();
+    let named = match color.to_ascii_lowercase().as_str() {
+        "white" => Some((255, 255, 255)),
+        "black" => Some((0, 0, 0)),
+        "red" => Some((255, 0, 0)),
+        "lime" => Some((0, 255, 0)),
+        "green" => Some((0, 128, 0)),
+        "blue" => Some((0, 0, 255)),
+        "yellow" => Some((255, 255, 0)),
+        "cyan" | "aqua" => Some((0, 255, 255)),
+        "magenta" | "fuchsia" => Some((255, 0, 255)),
+        "silver" => Some((192, 192, 192)),
+        "gray" | "grey" => Some((128, 128, 128)),
+        "maroon" => Some((128, 0, 0)),
+        "olive" => Some((128, 128, 0)),
+        "navy" => Some((0, 0, 128)),
+        "purple" => Some((128, 0, 128)),
+        "teal" => Some((0, 128, 128)),
+        "orange" => Some((255, 165, 0)),
+        _ => None,
+    };
+    if named.is_some() {
+        return named;
+    }
+
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+    match hex.len() {
+        6 => Some((
+            u8::from_str_radix(&hex[0..2], 16).ok()?,
+            u8::from_str_radix(&hex[2..4], 16).ok()?,
+            u8::from_str_radix(&hex[4..6], 16).ok()?,
+        )),
+        3 => Some((digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Tag {
+    Bold,
+    Italic,
+    Underline,
+    StrikeOut,
+    Font(Font),
+}
+
+impl Tag {
+    fn name(&self) -> &'static str {
+        match self {
+            Tag::Bold => "b",
+            Tag::Italic => "i",
+            Tag::Underline => "u",
+            Tag::StrikeOut => "s",
+            Tag::Font(_) => "font",
+        }
+    }
+}
+
+// `<b>`, `</i>`, `{u}`, `<font color="#fff" face='Arial' size=12>`; returns
+// the tag, whether it closes and how many bytes it spans
+fn tag(input: &str) -> Option<(Tag, bool, usize)> {
+    let close = match input.as_bytes().first()? {
+        b'<' => '>',
+        b'{' => '}',
+        _ => return None,
+    };
+    let len = input.find(close)? + 1;
+    let inner = input[1..len - 1].trim();
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, inner),
+    };
+    let name_len = inner
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(inner.len());
+    let (name, attrs) = inner.split_at(name_len);
+
+    let tag = match name.to_ascii_lowercase().as_str() {
+        "b" => Tag::Bold,
+        "i" => Tag::Italic,
+        "u" => Tag::Underline,
+        "s" => Tag::StrikeOut,
+        // braces are only a shorthand for the simple tags
+        "font" if close == '>' => Tag::Font(font_attributes(attrs)),
+        _ => return None,
+    };
+    if !closing && !attrs.trim().is_empty() && !matches!(tag, Tag::Font(_)) {
+        return None;
+    }
+    Some((tag, closing, len))
+}
+
+fn font_attributes(mut attrs: &str) -> Font {
+    let mut font = Font::default();
+    loop {
+        attrs = attrs.trim_start();
+        let Some(eq) = attrs.find('=') else {
+            break;
+        };
+        let key = attrs[..eq].trim().to_ascii_lowercase();
+        let rest = attrs[eq + 1..].trim_start();
+        let (value, remaining) = match rest.chars().next() {
+            Some(q @ ('"' | '\'')) => match rest[1..].find(q) {
+                Some(end) => (&rest[1..end + 1], &rest[end + 2..]),
+                None => (&rest[1..], ""),
+            },
+            _ => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        match key.as_str() {
+            "color" => font.color = Some(value.to_owned()),
+            "face" => font.face = Some(value.to_owned()),
+            "size" => font.size = Some(value.to_owned()),
+            _ => (),
+        }
+        attrs = remaining;
+    }
+    font
+}
+
+fn style_of(stack: &[Tag]) -> TextStyle {
+    let mut style = TextStyle::default();
+    for tag in stack {
+        match tag {
+            Tag::Bold => style.bold = true,
+            Tag::Italic => style.italic = true,
+            Tag::Underline => style.underline = true,
+            Tag::StrikeOut => style.strikeout = true,
+            Tag::Font(font) => {
+                let inner = font.clone();
+                style.font = Font {
+                    color: inner.color.or(style.font.color),
+                    face: inner.face.or(style.font.face),
+                    size: inner.size.or(style.font.size),
+                };
+            }
+        }
+    }
+    style
+}
+
+fn flush(text: &mut String, stack: &[Tag], spans: &mut Vec<Span>) {
+    if text.is_empty() {
+        return;
+    }
+    let style = style_of(stack);
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.clone(),
+            style,
+        }),
+    }
+    text.clear();
+}
+
+/// Splits SubRip text into runs of identically styled text.
+pub fn parse(input: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut stack: Vec<Tag> = Vec::new();
+    let mut text = String::new();
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' || c == '{' {
+            if let Some((tag, closing, len)) = tag(rest) {
+                flush(&mut text, &stack, &mut spans);
+                if closing {
+                    // close the innermost tag of that name, ignoring strays
+                    if let Some(n) = stack.iter().rposition(|t| t.name() == tag.name()) {
+                        stack.remove(n);
+                    }
+                } else {
+                    stack.push(tag);
+                }
+                rest = &rest[len..];
+                continue;
+            }
+        } else if c == '&' {
+            if let Some((c, len)) = entity::decode(rest) {
+                text.push(c);
+                rest = &rest[len..];
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    flush(&mut text, &stack, &mut spans);
+
+    spans
+}
+
+fn wanted(style: &TextStyle) -> Vec<Tag> {
+    let mut tags = Vec::new();
+    if style.bold {
+        tags.push(Tag::Bold);
+    }
+    if style.italic {
+        tags.push(Tag::Italic);
+    }
+    if style.underline {
+        tags.push(Tag::Underline);
+    }
+    if style.strikeout {
+        tags.push(Tag::StrikeOut);
+    }
+    if !style.font.is_empty() {
+        tags.push(Tag::Font(style.font.clone()));
+    }
+    tags
+}
+
+// only escape what would otherwise read as a tag or an entity, so unknown
+// tags and stray ampersands come back out as they went in
+fn escape(text: &str, out: &mut String) {
+    for (n, c) in text.char_indices() {
+        match c {
+            '<' if tag(&text[n..]).is_some() => out.push_str("&lt;"),
+            '{' if tag(&text[n..]).is_some() => out.push_str("&#123;"),
+            '&' if entity::decode(&text[n..]).is_some() => out.push_str("&amp;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Writes styled runs back as SubRip text, keeping tags properly nested and
+/// only reopening what changes between runs.
+pub fn write(spans: &[Span]) -> String {
+    let mut out = String::new();
+    let mut open: Vec<Tag> = Vec::new();
+
+    for span in spans {
+        let wanted = wanted(&span.style);
+        let keep = open.iter().take_while(|t| wanted.contains(t)).count();
+        while open.len() > keep {
+            let tag = open.pop().unwrap();
+            out.push_str("</");
+            out.push_str(tag.name());
+            out.push('>');
+        }
+        let missing: Vec<Tag> = wanted.into_iter().filter(|t| !open.contains(t)).collect();
+        for tag in missing {
+            match &tag {
+                Tag::Font(font) => {
+                    out.push_str("<font");
+                    for (key, value) in [
+                        ("color", &font.color),
+                        ("face", &font.face),
+                        ("size", &font.size),
+                    ] {
+                        if let Some(value) = value {
+                            out.push_str(&format!(" {}=\"{}\"", key, value));
+                        }
+                    }
+                    out.push('>');
+                }
+                tag => {
+                    out.push('<');
+                    out.push_str(tag.name());
+                    out.push('>');
+                }
+            }
+            open.push(tag);
+        }
+        escape(&span.text, &mut out);
+    }
+    while let Some(tag) = open.pop() {
+        out.push_str("</");
+        out.push_str(tag.name());
+        out.push('>');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(spans: &[Span]) -> String {
+        spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(plain(&parse("a &amp; b &#38; &#x26;")), "a & b & &");
+        assert_eq!(plain(&parse("&unknown; &")), "&unknown; &");
+    }
+
+    #[test]
+    fn non_ascii_after_ampersand() {
+        assert_eq!(plain(&parse("a &éééééé b")), "a &éééééé b");
+        assert_eq!(plain(&parse("&é;")), "&é;");
+        let spans = parse("a &éééééé b");
+        assert_eq!(plain(&parse(&write(&spans))), "a &éééééé b");
+    }
+}
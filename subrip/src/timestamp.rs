@@ -0,0 +1,185 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A point in a SubRip file, in the milliseconds the format stores.
+///
+/// Arithmetic may go negative; formatting clamps to zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub const ZERO: Timestamp = Timestamp(0);
+
+    pub fn from_millis(millis: i64) -> Timestamp {
+        Timestamp(millis)
+    }
+
+    pub fn from_hms(hours: i64, minutes: i64, seconds: i64, millis: i64) -> Timestamp {
+        Timestamp(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis)
+    }
+
+    /// [`Timestamp::from_hms`], or `None` if the time doesn't fit.
+    pub fn checked_from_hms(
+        hours: i64,
+        minutes: i64,
+        seconds: i64,
+        millis: i64,
+    ) -> Option<Timestamp> {
+        hours
+            .checked_mul(60)?
+            .checked_add(minutes)?
+            .checked_mul(60)?
+            .checked_add(seconds)?
+            .checked_mul(1000)?
+            .checked_add(millis)
+            .map(Timestamp)
+    }
+
+    pub fn millis(self) -> i64 {
+        self.0
+    }
+
+    /// The timestamp as a `Duration`, clamped to zero.
+    pub fn as_duration(self) -> Duration {
+        Duration::from_millis(self.0.max(0) as u64)
+    }
+
+    pub fn scale(self, factor: f64) -> Timestamp {
+        Timestamp((self.0 as f64 * factor).round() as i64)
+    }
+}
+
+/// Truncates anything below a millisecond.
+impl From<Duration> for Timestamp {
+    fn from(duration: Duration) -> Timestamp {
+        Timestamp(duration.as_millis() as i64)
+    }
+}
+
+impl From<Timestamp> for Duration {
+    fn from(timestamp: Timestamp) -> Duration {
+        timestamp.as_duration()
+    }
+}
+
+impl Add for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, other: Timestamp) -> Timestamp {
+        Timestamp(self.0 + other.0)
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, other: Timestamp) -> Timestamp {
+        Timestamp(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Timestamp {
+    fn add_assign(&mut self, other: Timestamp) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Timestamp {
+    fn sub_assign(&mut self, other: Timestamp) {
+        self.0 -= other.0;
+    }
+}
+
+// HH:MM:SS,mmm, with more hour digits if needed
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.0.max(0);
+        write!(
+            f,
+            "{:02}:{:02}:{:02},{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1_000 % 60,
+            ms % 1_000
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = nom::Err<nom::error::Error<String>>;
+
+    fn from_str(s: &str) -> Result<Timestamp, Self::Err> {
+        nom::combinator::all_consuming(crate::timestamp)(s.trim())
+            .map(|(_, t)| t)
+            .map_err(|e| e.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for (text, millis, shown) in [
+            ("00:00:00,000", 0, "00:00:00,000"),
+            ("01:02:03,456", 3_723_456, "01:02:03,456"),
+            (" 1:02:03,456 ", 3_723_456, "01:02:03,456"),
+            ("00:00:01,5", 1005, "00:00:01,005"),
+            ("123:00:00,000", 442_800_000, "123:00:00,000"),
+            ("00:00:59,999", 59_999, "00:00:59,999"),
+        ] {
+            let timestamp: Timestamp = text.parse().unwrap();
+            assert_eq!(timestamp.millis(), millis, "{:?}", text);
+            assert_eq!(timestamp.to_string(), shown, "{:?}", text);
+            assert_eq!(shown.parse::<Timestamp>(), Ok(timestamp), "{:?}", shown);
+        }
+        for text in [
+            "",
+            "00:00",
+            "00:00:01.000",
+            "00:00:01,000x",
+            "-00:00:01,000",
+            "9999999999999999:00:00,000",
+            "00:00:00,99999999999999999999",
+        ] {
+            assert!(text.parse::<Timestamp>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(
+            Timestamp::from(Duration::from_micros(1_000_999)).millis(),
+            1000
+        );
+        assert_eq!(
+            Duration::from(Timestamp::from_millis(1500)),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(Timestamp::from_millis(3).scale(0.5).millis(), 2);
+        assert_eq!(Timestamp::from_millis(1000).scale(1.0004).millis(), 1000);
+    }
+
+    #[test]
+    fn negatives() {
+        let early = Timestamp::from_millis(500) - Timestamp::from_millis(1500);
+        assert_eq!(early.millis(), -1000);
+        assert_eq!(early.as_duration(), Duration::ZERO);
+        assert_eq!(early.to_string(), "00:00:00,000");
+        assert!(early < Timestamp::ZERO);
+
+        let mut t = early;
+        t += Timestamp::from_hms(0, 0, 2, 0);
+        assert_eq!(t, Timestamp::from_millis(1000));
+        t -= Timestamp::from_millis(250);
+        assert_eq!(
+            t + Timestamp::from_millis(250),
+            Timestamp::from_millis(1000)
+        );
+        assert_eq!(Timestamp::from_hms(0, -1, 0, 0).millis(), -60_000);
+        assert_eq!(Timestamp::checked_from_hms(0, 0, i64::MAX, 0), None);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::Entry;
+use parsing_utils::timing::{scale_duration, shift_duration, TimeRange};
+
+fn selected(entry: &Entry, range: Option<TimeRange>) -> bool {
+    range.is_none_or(|r| r.contains(entry.start))
+}
+
+/// Moves entries starting inside `range` (or all of them) by `offset_ms`
+/// milliseconds. Times that would become negative are clamped to zero.
+pub fn shift(entries: &mut [Entry], offset_ms: i64, range: Option<TimeRange>) {
+    for entry in entries.iter_mut().filter(|e| selected(e, range)) {
+        entry.start = shift_duration(entry.start, offset_ms);
+        entry.end = shift_duration(entry.end, offset_ms);
+    }
+}
+
+/// Multiplies the times of entries starting inside `range` (or all of them)
+/// by `factor`.
+pub fn scale(entries: &mut [Entry], factor: f64, range: Option<TimeRange>) {
+    for entry in entries.iter_mut().filter(|e| selected(e, range)) {
+        entry.start = scale_duration(entry.start, factor);
+        entry.end = scale_duration(entry.end, factor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entries() -> Vec<Entry> {
+        [(0, 1000), (1000, 2000), (2000, 3000)]
+            .into_iter()
+            .map(|(start, end)| Entry {
+                start: Duration::from_millis(start),
+                end: Duration::from_millis(end),
+                text: "{\\an8}<i>a</i>".to_owned(),
+                ..Entry::default()
+            })
+            .collect()
+    }
+
+    fn times(entries: &[Entry]) -> Vec<(u64, u64)> {
+        entries
+            .iter()
+            .map(|e| (e.start.as_millis() as u64, e.end.as_millis() as u64))
+            .collect()
+    }
+
+    #[test]
+    fn shift_and_scale() {
+        let range = Some(TimeRange::new(
+            Duration::from_millis(1000),
+            Duration::from_millis(2000),
+        ));
+        for (offset, range, expected) in [
+            (500, None, [(500, 1500), (1500, 2500), (2500, 3500)]),
+            (-1500, None, [(0, 0), (0, 500), (500, 1500)]),
+            (500, range, [(0, 1000), (1500, 2500), (2000, 3000)]),
+        ] {
+            let mut shifted = entries();
+            shift(&mut shifted, offset, range);
+            assert_eq!(times(&shifted), expected, "{} {:?}", offset, range);
+            assert!(shifted.iter().all(|e| e.text == "{\\an8}<i>a</i>"));
+        }
+
+        for (factor, range, expected) in [
+            (2.0, None, [(0, 2000), (2000, 4000), (4000, 6000)]),
+            (0.5, range, [(0, 1000), (500, 1000), (2000, 3000)]),
+        ] {
+            let mut scaled = entries();
+            scale(&mut scaled, factor, range);
+            assert_eq!(times(&scaled), expected, "{} {:?}", factor, range);
+        }
+    }
+}
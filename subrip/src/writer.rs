@@ -0,0 +1,203 @@
+use crate::Entry;
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    CrLf,
+    Lf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Lf => "\n",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteOptions {
+    /// Number entries from 1 in the order they are written instead of using
+    /// `Entry::index`.
+    pub renumber: bool,
+    pub line_ending: LineEnding,
+}
+
+struct Cue<'a> {
+    entry: &'a Entry,
+    index: u32,
+    line_ending: LineEnding,
+}
+
+impl fmt::Display for Cue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nl = self.line_ending.as_str();
+        write!(
+            f,
+            "{}{}{} --> {}",
+            self.index,
+            nl,
+            crate::Timestamp::from(self.entry.start),
+            crate::Timestamp::from(self.entry.end),
+        )?;
+        if let Some(c) = self.entry.coordinates {
+            write!(f, " X1:{} X2:{} Y1:{} Y2:{}", c.x1, c.x2, c.y1, c.y2)?;
+        }
+        f.write_str(nl)?;
+        let mut prefix = self
+            .entry
+            .alignment
+            .map(|a| format!("{{\\an{}}}", a))
+            .unwrap_or_default();
+        // a blank line would end the cue early, so those are dropped
+        for line in self.entry.text.lines().filter(|l| !l.is_empty()) {
+            write!(f, "{}{}{}", prefix, line, nl)?;
+            prefix.clear();
+        }
+        if !prefix.is_empty() {
+            write!(f, "{}{}", prefix, nl)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a single cue with `\n` line endings, without the blank line that
+/// separates it from the next one.
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Cue {
+            entry: self,
+            index: self.index,
+            line_ending: LineEnding::Lf,
+        }
+        .fmt(f)
+    }
+}
+
+// a cue with no lines of text, which readers can't tell from the gap
+// between cues
+fn is_empty(entry: &Entry) -> bool {
+    entry.alignment.is_none() && entry.text.lines().all(|l| l.is_empty())
+}
+
+/// Writes entries as a SubRip file. Entries without any text are left out,
+/// since the file couldn't be read back with them in it.
+pub fn write_entries<W: Write>(
+    mut writer: W,
+    entries: &[Entry],
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let nl = options.line_ending.as_str();
+    let written = entries.iter().filter(|e| !is_empty(e));
+    for (n, entry) in written.enumerate() {
+        if n > 0 {
+            writer.write_all(nl.as_bytes())?;
+        }
+        let index = if options.renumber {
+            n as u32 + 1
+        } else {
+            entry.index
+        };
+        write!(
+            writer,
+            "{}",
+            Cue {
+                entry,
+                index,
+                line_ending: options.line_ending,
+            }
+        )?;
+    }
+    Ok(())
+}
+
+pub fn to_string(entries: &[Entry], options: &WriteOptions) -> String {
+    let mut out = Vec::new();
+    write_entries(&mut out, entries, options).expect("writing to a Vec can't fail");
+    String::from_utf8(out).expect("entries are valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::parse_strict;
+    use std::time::Duration;
+
+    fn entry(index: u32, text: &str) -> Entry {
+        Entry {
+            index,
+            start: Duration::from_secs(index as u64),
+            end: Duration::from_secs(index as u64 + 1),
+            text: text.to_owned(),
+            ..Entry::default()
+        }
+    }
+
+    #[test]
+    fn line_endings() {
+        let entries = [entry(1, "a\nb"), entry(2, "c")];
+        let lf = "1\n00:00:01,000 --> 00:00:02,000\na\nb\n\n2\n00:00:02,000 --> 00:00:03,000\nc\n";
+        let options = WriteOptions {
+            line_ending: LineEnding::Lf,
+            ..WriteOptions::default()
+        };
+        assert_eq!(to_string(&entries, &options), lf);
+        assert_eq!(
+            to_string(&entries, &WriteOptions::default()),
+            lf.replace('\n', "\r\n")
+        );
+        assert_eq!(
+            entries[0].to_string(),
+            "1\n00:00:01,000 --> 00:00:02,000\na\nb\n"
+        );
+    }
+
+    #[test]
+    fn renumbering() {
+        let entries = [entry(7, "a"), entry(3, "b"), entry(3, "c")];
+        let indices = |options: &WriteOptions| {
+            parse_strict(&to_string(&entries, options))
+                .unwrap()
+                .iter()
+                .map(|e| e.index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(indices(&WriteOptions::default()), [7, 3, 3]);
+        let renumber = WriteOptions {
+            renumber: true,
+            ..WriteOptions::default()
+        };
+        assert_eq!(indices(&renumber), [1, 2, 3]);
+    }
+
+    #[test]
+    fn empty_entries_are_skipped() {
+        let entries = [
+            entry(1, "a"),
+            entry(2, ""),
+            entry(3, "\n\n"),
+            Entry {
+                alignment: Some(8),
+                ..entry(4, "")
+            },
+            entry(5, "b\n\nc"),
+        ];
+        let options = WriteOptions {
+            renumber: true,
+            line_ending: LineEnding::Lf,
+        };
+        let written = to_string(&entries, &options);
+        let back = parse_strict(&written).unwrap();
+        assert_eq!(
+            back.iter()
+                .map(|e| (e.index, e.start.as_secs(), e.text.as_str()))
+                .collect::<Vec<_>>(),
+            [(1, 1, "a"), (2, 4, ""), (3, 5, "b\nc")]
+        );
+        assert_eq!(back[1].alignment, Some(8));
+        assert_eq!(to_string(&entries[1..3], &options), "");
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{io, parse_format, InputArgs, Result};
+use clap::Args;
+use parsing_utils::encoding::UTF_8;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use subtitles::Format;
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Output file, `-` for standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format, taken from the output file's extension if not given.
+    #[arg(long, value_parser = parse_format)]
+    to: Option<Format>,
+
+    /// Output encoding, UTF-8 if not given.
+    #[arg(long)]
+    output_encoding: Option<String>,
+
+    /// Start the output with a byte order mark.
+    #[arg(long)]
+    bom: bool,
+}
+
+pub fn run(options: &Options) -> Result<ExitCode> {
+    let to = match options.to {
+        Some(to) => to,
+        None => options
+            .output
+            .as_deref()
+            .and_then(|p| p.extension())
+            .and_then(|e| Format::from_extension(&e.to_string_lossy()))
+            .ok_or("no output format, use --to")?,
+    };
+    if to == Format::Ssa {
+        return Err("writing SSA isn't supported, use --to ass".into());
+    }
+    let input = io::read(&options.input)?;
+
+    let is_ass = |f: Format| matches!(f, Format::Ass | Format::Ssa);
+    let text = if is_ass(input.format) && is_ass(to) {
+        // keep everything SubRip can't express
+        substation::writer::to_string(&io::script(&input)?)
+    } else {
+        let cues = subtitles::format(input.format).parse(&input.text)?;
+        subtitles::format(to).write(&cues)
+    };
+
+    let encoding = io::output_encoding(options.output_encoding.as_deref(), UTF_8)?;
+    io::write(options.output.as_deref(), &text, encoding, options.bom)?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,62 @@
+use crate::{io, InputArgs, Result};
+use clap::Args;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use substation::attachments::{fonts, graphics};
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Directory to write the files to.
+    #[arg(short, long, default_value = ".")]
+    dir: PathBuf,
+
+    /// Only list the attachments.
+    #[arg(long)]
+    list: bool,
+}
+
+#[derive(Serialize)]
+struct Extracted {
+    name: String,
+    kind: &'static str,
+    size: usize,
+}
+
+pub fn run(options: &Options, json: bool) -> Result<ExitCode> {
+    let input = io::read(&options.input)?;
+    let attachments = fonts(&input.text)
+        .into_iter()
+        .map(|a| ("font", a))
+        .chain(graphics(&input.text).into_iter().map(|a| ("graphic", a)));
+
+    let mut extracted = Vec::new();
+    for (kind, attachment) in attachments {
+        if !options.list {
+            // only the file name, so a crafted name can't write elsewhere
+            let name = Path::new(&attachment.name)
+                .file_name()
+                .ok_or_else(|| format!("invalid attachment name {:?}", attachment.name))?;
+            let path = options.dir.join(name);
+            fs::write(&path, &attachment.data).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        extracted.push(Extracted {
+            name: attachment.name,
+            kind,
+            size: attachment.data.len(),
+        });
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&extracted)?);
+    } else {
+        for e in &extracted {
+            println!("{:<8} {:>10}  {}", e.kind, e.size, e.name);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,122 @@
+use crate::{io, InputArgs, Result};
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use substation::fonts::{attached, match_fonts, scan_dir, usage, FontUsage};
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Directory of font files to look for the fonts in, along with those
+    /// attached to the script. Can be given more than once.
+    #[arg(long)]
+    dir: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Font {
+    family: String,
+    weight: u16,
+    italic: bool,
+    characters: String,
+    /// The file it matched, when there were any to look in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    faux_bold: bool,
+    faux_italic: bool,
+    missing_glyphs: String,
+}
+
+fn font(usage: &FontUsage) -> Font {
+    Font {
+        family: usage.family.clone(),
+        weight: usage.weight,
+        italic: usage.italic,
+        characters: usage.characters.iter().collect(),
+        file: None,
+        faux_bold: false,
+        faux_italic: false,
+        missing_glyphs: String::new(),
+    }
+}
+
+fn describe(font: &Font) -> String {
+    let mut style = Vec::new();
+    match font.weight {
+        400 => (),
+        700 => style.push("bold".to_owned()),
+        weight => style.push(format!("weight {}", weight)),
+    }
+    if font.italic {
+        style.push("italic".to_owned());
+    }
+    if style.is_empty() {
+        font.family.clone()
+    } else {
+        format!("{} ({})", font.family, style.join(", "))
+    }
+}
+
+/// Exits with 1 when fonts were looked for and some are missing or lack
+/// glyphs.
+pub fn run(options: &Options, json: bool) -> Result<ExitCode> {
+    let input = io::read(&options.input)?;
+    let script = io::script(&input)?;
+    let usage = usage(&script);
+
+    let mut files = attached(&script);
+    for dir in &options.dir {
+        files.extend(scan_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?);
+    }
+    let searched = !options.dir.is_empty() || !files.is_empty();
+
+    let fonts: Vec<Font> = if searched {
+        match_fonts(&usage, &files)
+            .into_iter()
+            .map(|m| Font {
+                file: m.file.map(|f| f.path.display().to_string()),
+                faux_bold: m.faux_bold,
+                faux_italic: m.faux_italic,
+                missing_glyphs: m.missing_glyphs.iter().collect(),
+                ..font(m.usage)
+            })
+            .collect()
+    } else {
+        usage.iter().map(font).collect()
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&fonts)?);
+    } else {
+        for font in &fonts {
+            let mut notes = Vec::new();
+            match &font.file {
+                Some(file) => notes.push(file.clone()),
+                None if searched => notes.push("missing".to_owned()),
+                None => notes.push(format!("{} characters", font.characters.chars().count())),
+            }
+            if font.faux_bold {
+                notes.push("faux bold".to_owned());
+            }
+            if font.faux_italic {
+                notes.push("faux italic".to_owned());
+            }
+            if !font.missing_glyphs.is_empty() {
+                notes.push(format!("no glyphs for {:?}", font.missing_glyphs));
+            }
+            println!("{}: {}", describe(font), notes.join(", "));
+        }
+    }
+
+    let incomplete = fonts
+        .iter()
+        .any(|f| f.file.is_none() || !f.missing_glyphs.is_empty());
+    Ok(if searched && incomplete {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
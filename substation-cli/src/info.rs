@@ -0,0 +1,114 @@
+use crate::{io, InputArgs, Result};
+use serde::Serialize;
+use std::process::ExitCode;
+use std::time::Duration;
+use substation::Script;
+use subtitles::Format;
+
+#[derive(Serialize)]
+struct Info {
+    format: &'static str,
+    encoding: &'static str,
+    events: usize,
+    comments: usize,
+    styles: Vec<String>,
+    fonts: Vec<String>,
+    attachments: Vec<String>,
+    resolution: Option<(f64, f64)>,
+    start_ms: Option<u64>,
+    end_ms: Option<u64>,
+}
+
+/// The families text is drawn in.
+fn fonts(script: &Script) -> Vec<String> {
+    let mut fonts: Vec<String> = Vec::new();
+    for font in substation::fonts::usage(script) {
+        if !fonts.iter().any(|f| f.eq_ignore_ascii_case(&font.family)) {
+            fonts.push(font.family);
+        }
+    }
+    fonts
+}
+
+fn span(times: impl Iterator<Item = (Duration, Duration)>) -> (Option<u64>, Option<u64>) {
+    let (mut first, mut last) = (None::<Duration>, None::<Duration>);
+    for (start, end) in times {
+        first = Some(first.map_or(start, |f| f.min(start)));
+        last = Some(last.map_or(end, |l| l.max(end)));
+    }
+    let ms = |d: Duration| d.as_millis() as u64;
+    (first.map(ms), last.map(ms))
+}
+
+pub fn run(args: &InputArgs, json: bool) -> Result<ExitCode> {
+    let input = io::read(args)?;
+    let mut info = Info {
+        format: input.format.name(),
+        encoding: input.detection.encoding.name(),
+        events: 0,
+        comments: 0,
+        styles: Vec::new(),
+        fonts: Vec::new(),
+        attachments: Vec::new(),
+        resolution: None,
+        start_ms: None,
+        end_ms: None,
+    };
+
+    if let Format::Ass | Format::Ssa = input.format {
+        let script = io::script(&input)?;
+        let (comments, events): (Vec<_>, Vec<_>) = script
+            .events
+            .iter()
+            .partition(|e| e.kind.as_deref() == Some("Comment"));
+        info.events = events.len();
+        info.comments = comments.len();
+        info.styles = script.styles.keys().cloned().collect();
+        info.styles.sort();
+        info.fonts = fonts(&script);
+        info.attachments = substation::attachments::fonts(&input.text)
+            .into_iter()
+            .chain(substation::attachments::graphics(&input.text))
+            .map(|a| a.name)
+            .collect();
+        info.resolution = Some(script.play_res());
+        (info.start_ms, info.end_ms) = span(
+            events
+                .iter()
+                .filter_map(|e| Some((e.start?, e.end.or(e.start)?))),
+        );
+    } else {
+        let cues = subtitles::format(input.format).parse(&input.text)?;
+        info.events = cues.len();
+        (info.start_ms, info.end_ms) = span(cues.iter().map(|c| (c.start, c.end)));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let time = |ms: Option<u64>| {
+        ms.map(|ms| substation::Timestamp::from_millis_rounded(ms as i64).to_string())
+            .unwrap_or_else(|| "-".to_owned())
+    };
+    println!("format:      {}", info.format);
+    println!("encoding:    {}", info.encoding);
+    if let Some((x, y)) = info.resolution {
+        println!("resolution:  {}x{}", x, y);
+    }
+    println!("events:      {}", info.events);
+    if info.comments > 0 {
+        println!("comments:    {}", info.comments);
+    }
+    println!("start:       {}", time(info.start_ms));
+    println!("end:         {}", time(info.end_ms));
+    let list = |name: &str, items: &[String]| {
+        if !items.is_empty() {
+            println!("{:<13}{}", format!("{}:", name), items.join(", "));
+        }
+    };
+    list("styles", &info.styles);
+    list("fonts", &info.fonts);
+    list("attachments", &info.attachments);
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,88 @@
+//! Reading input and writing output, decoding and encoding on the way.
+
+use crate::{InputArgs, Result};
+use parsing_utils::encoding::{decode, decode_as, encode, Detection, Encoding};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use subtitles::Format;
+
+pub struct Input {
+    pub text: String,
+    pub format: Format,
+    pub detection: Detection,
+}
+
+// the path, or `None` for standard input or output
+fn file(path: Option<&Path>) -> Option<&Path> {
+    path.filter(|p| p.as_os_str() != "-")
+}
+
+pub fn encoding(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("unknown encoding {:?}", label).into())
+}
+
+pub fn read(args: &InputArgs) -> Result<Input> {
+    let bytes = match file(args.input.as_deref()) {
+        Some(path) => fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        }
+    };
+
+    let format = match args.from {
+        Some(format) => format,
+        None => subtitles::detect(&bytes).ok_or("couldn't tell the format, use --from")?,
+    };
+    let decoded = match &args.encoding {
+        Some(label) => decode_as(&bytes, encoding(label)?),
+        None if matches!(format, Format::Ass | Format::Ssa) => {
+            substation::encoding::decode_script(&bytes)
+        }
+        None => decode(&bytes),
+    };
+    Ok(Input {
+        text: decoded.text,
+        format,
+        detection: decoded.detection,
+    })
+}
+
+/// The encoding to write in: `label` if given, else `default`.
+pub fn output_encoding(
+    label: Option<&str>,
+    default: &'static Encoding,
+) -> Result<&'static Encoding> {
+    label.map_or(Ok(default), encoding)
+}
+
+/// Writes `text` to `path`, or standard output for `-` or none.
+pub fn write(
+    path: Option<&Path>,
+    text: &str,
+    encoding: &'static Encoding,
+    bom: bool,
+) -> Result<()> {
+    let (bytes, unmappable) = encode(text, encoding, bom);
+    if unmappable {
+        eprintln!(
+            "substation: some characters can't be written in {}",
+            encoding.name()
+        );
+    }
+    match file(path) {
+        Some(path) => fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => std::io::stdout().write_all(&bytes)?,
+    }
+    Ok(())
+}
+
+/// Parses an ASS or SSA script.
+pub fn script(input: &Input) -> Result<substation::Script> {
+    let (_, script) =
+        substation::parser::script(&input.text).map_err(|e| format!("invalid script: {}", e))?;
+    Ok(script)
+}
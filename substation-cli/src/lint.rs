@@ -0,0 +1,98 @@
+use crate::{io, InputArgs, Result};
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use substation::lint::{Config, Rule, Severity};
+use subtitles::Format;
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Rule settings, one `rule = error|warning|info|off` per line.
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Problem {
+    /// Only for scripts, whose checks are named.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<Rule>,
+    severity: Severity,
+    /// 1-based line, for formats that report it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    /// 1-based event number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<usize>,
+    message: String,
+}
+
+/// Exits with 1 when there are warnings or errors; info alone passes.
+pub fn run(options: &Options, json: bool) -> Result<ExitCode> {
+    let config = match &options.config {
+        Some(path) => Config::parse(&std::fs::read_to_string(path)?)?,
+        None => Config::default(),
+    };
+    let input = io::read(&options.input)?;
+    let problems: Vec<Problem> = match input.format {
+        Format::Ass | Format::Ssa => substation::lint::lint(&io::script(&input)?, &config)
+            .into_iter()
+            .map(|p| Problem {
+                rule: Some(p.rule),
+                severity: p.severity,
+                line: None,
+                event: p.event.map(|n| n + 1),
+                message: p.message,
+            })
+            .collect(),
+        Format::SubRip => subrip::reader::parse_lenient(&input.text)
+            .1
+            .into_iter()
+            .map(|d| Problem {
+                rule: None,
+                severity: Severity::Warning,
+                line: Some(d.line),
+                event: None,
+                message: d.issue.to_string(),
+            })
+            .collect(),
+        format => match subtitles::format(format).parse(&input.text) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![Problem {
+                rule: None,
+                severity: Severity::Error,
+                line: e.line,
+                event: None,
+                message: e.message,
+            }],
+        },
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&problems)?);
+    } else {
+        for problem in &problems {
+            let place = match (problem.line, problem.event) {
+                (Some(line), _) => format!("line {}: ", line),
+                (None, Some(event)) => format!("event {}: ", event),
+                (None, None) => String::new(),
+            };
+            match problem.rule {
+                Some(rule) => println!(
+                    "{}{} [{}] {}",
+                    place, problem.severity, rule, problem.message
+                ),
+                None => println!("{}{} {}", place, problem.severity, problem.message),
+            }
+        }
+    }
+    Ok(if problems.iter().any(|p| p.severity > Severity::Info) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,103 @@
+//! `substation`: converts, retimes, inspects and checks subtitle files.
+//!
+//! Every command reads a file, or standard input when given `-` or no file,
+//! and writes to standard output unless told otherwise.
+
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use subtitles::Format;
+
+mod convert;
+mod extract;
+mod fonts;
+mod info;
+mod io;
+mod lint;
+mod qc;
+mod shift;
+mod tags;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "substation", version, about)]
+struct Cli {
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert between subtitle formats.
+    Convert(convert::Options),
+    /// Shift or scale the timing of every event.
+    Shift(shift::Options),
+    /// Print events, styles, fonts and duration.
+    Info(InputArgs),
+    /// Check a script for mistakes.
+    Lint(lint::Options),
+    /// Check reading speed, line length and timing against subtitle
+    /// guidelines.
+    Qc(qc::Options),
+    /// Write out the fonts and images attached to a script.
+    Extract(extract::Options),
+    /// List the fonts a script uses and find them in font directories.
+    Fonts(fonts::Options),
+    /// Print each event's parsed override tags.
+    Tags(tags::Options),
+}
+
+/// The file to read and how to read it.
+#[derive(Args)]
+struct InputArgs {
+    /// Input file, `-` for standard input.
+    input: Option<PathBuf>,
+
+    /// Input format, detected from the contents if not given.
+    #[arg(long, value_parser = parse_format)]
+    from: Option<Format>,
+
+    /// Input encoding, such as `windows-1252` or `shift_jis`, detected if
+    /// not given.
+    #[arg(long)]
+    encoding: Option<String>,
+}
+
+fn parse_format(name: &str) -> std::result::Result<Format, String> {
+    Format::from_extension(name).ok_or_else(|| {
+        let known: Vec<&str> = Format::ALL.iter().map(|f| f.extensions()[0]).collect();
+        format!(
+            "unknown format {:?}, expected one of {}",
+            name,
+            known.join(", ")
+        )
+    })
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    match cli.command {
+        Command::Convert(options) => convert::run(&options),
+        Command::Shift(options) => shift::run(&options),
+        Command::Info(input) => info::run(&input, cli.json),
+        Command::Lint(options) => lint::run(&options, cli.json),
+        Command::Qc(options) => qc::run(&options, cli.json),
+        Command::Extract(options) => extract::run(&options, cli.json),
+        Command::Fonts(options) => fonts::run(&options, cli.json),
+        Command::Tags(options) => tags::run(&options, cli.json),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("substation: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
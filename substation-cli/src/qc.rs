@@ -0,0 +1,144 @@
+use crate::{io, InputArgs, Result};
+use clap::Args;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use substation::reading::{check, fix_gaps, Guidelines, Issue};
+use substation::srt::{from_srt, FromSrtOptions};
+use subtitles::Format;
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Guidelines to start from: `netflix` or `bbc`.
+    #[arg(long, default_value = "netflix", value_parser = ["netflix", "bbc"])]
+    preset: String,
+
+    /// Most characters per second.
+    #[arg(long)]
+    max_cps: Option<f64>,
+
+    /// Most characters per line.
+    #[arg(long)]
+    max_cpl: Option<usize>,
+
+    /// Most lines per event.
+    #[arg(long)]
+    max_lines: Option<usize>,
+
+    /// Shortest time on screen, in milliseconds.
+    #[arg(long)]
+    min_duration: Option<u64>,
+
+    /// Longest time on screen, in milliseconds.
+    #[arg(long)]
+    max_duration: Option<u64>,
+
+    /// Shortest gap between events, in milliseconds.
+    #[arg(long)]
+    min_gap: Option<u64>,
+
+    /// Close gaps shorter than this many milliseconds when fixing.
+    #[arg(long)]
+    chain: Option<u64>,
+
+    /// Extend short events and close small gaps, writing the result instead
+    /// of a report.
+    #[arg(long)]
+    fix: bool,
+
+    /// Output file for `--fix`, `-` for standard output.
+    #[arg(short, long, requires = "fix")]
+    output: Option<PathBuf>,
+}
+
+impl Options {
+    fn guidelines(&self) -> Guidelines {
+        let ms = |v: Option<u64>, preset| v.map(Duration::from_millis).or(preset);
+        let preset = Guidelines::preset(&self.preset).expect("checked by clap");
+        Guidelines {
+            max_cps: self.max_cps.or(preset.max_cps),
+            max_cpl: self.max_cpl.or(preset.max_cpl),
+            max_lines: self.max_lines.or(preset.max_lines),
+            min_duration: ms(self.min_duration, preset.min_duration),
+            max_duration: ms(self.max_duration, preset.max_duration),
+            min_gap: ms(self.min_gap, preset.min_gap),
+            chain_threshold: ms(self.chain, preset.chain_threshold),
+            ..preset
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Violation {
+    /// 1-based event number.
+    event: usize,
+    issue: Issue,
+    message: String,
+}
+
+pub fn run(options: &Options, json: bool) -> Result<ExitCode> {
+    let input = io::read(&options.input)?;
+    let guidelines = options.guidelines();
+
+    // other formats are checked as the script they convert to, event for
+    // cue, keeping the cues' times rather than rounding them to centiseconds
+    let (mut script, cues) = match input.format {
+        Format::Ass | Format::Ssa => (io::script(&input)?, None),
+        format => {
+            let cues = subtitles::format(format).parse(&input.text)?;
+            let entries = subrip::format::entries(&cues);
+            let mut script = from_srt(&entries, &FromSrtOptions::default());
+            for (event, entry) in script.events.iter_mut().zip(&entries) {
+                event.start = Some(entry.start);
+                event.end = Some(entry.end);
+            }
+            (script, Some(cues))
+        }
+    };
+
+    if options.fix {
+        let changes = fix_gaps(&mut script.events, &guidelines);
+        eprintln!("substation: changed {} events", changes.len());
+        let text = match cues {
+            None => substation::writer::to_string(&script),
+            Some(mut cues) => {
+                for change in &changes {
+                    cues[change.index].end = change.end.1;
+                }
+                subtitles::format(input.format).write(&cues)
+            }
+        };
+        io::write(
+            options.output.as_deref(),
+            &text,
+            input.detection.encoding,
+            input.detection.bom,
+        )?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let violations: Vec<Violation> = check(&script.events, &guidelines)
+        .into_iter()
+        .map(|v| Violation {
+            event: v.index + 1,
+            message: v.issue.to_string(),
+            issue: v.issue,
+        })
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&violations)?);
+    } else {
+        for violation in &violations {
+            println!("event {}: {}", violation.event, violation.message);
+        }
+    }
+    Ok(if violations.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,144 @@
+use crate::{io, InputArgs, Result};
+use clap::Args;
+use parsing_utils::timing::{framerate_ratio, scale_duration, shift_duration};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use substation::timing::Selection;
+use subtitles::Format;
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Output file, `-` for standard output.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// How far to move events: milliseconds, or seconds with an `s` suffix,
+    /// negative to move them earlier.
+    #[arg(long, allow_hyphen_values = true, value_parser = parse_offset)]
+    by: Option<i64>,
+
+    /// Multiply every time by this factor, before shifting.
+    #[arg(long, conflicts_with = "fps")]
+    scale: Option<f64>,
+
+    /// Retime from one framerate to another, as `FROM:TO` (e.g. `25:23.976`).
+    #[arg(long, value_parser = parse_fps)]
+    fps: Option<f64>,
+
+    /// Output encoding, the input's if not given.
+    #[arg(long)]
+    output_encoding: Option<String>,
+}
+
+fn parse_offset(value: &str) -> std::result::Result<i64, String> {
+    let invalid = || {
+        format!(
+            "invalid offset {:?}, expected e.g. 1500, -2.5s or 250ms",
+            value
+        )
+    };
+    if let Some(ms) = value.strip_suffix("ms") {
+        ms.trim().parse().map_err(|_| invalid())
+    } else if let Some(secs) = value.strip_suffix('s') {
+        let secs: f64 = secs.trim().parse().map_err(|_| invalid())?;
+        Ok((secs * 1000.0).round() as i64)
+    } else {
+        value.trim().parse().map_err(|_| invalid())
+    }
+}
+
+fn parse_fps(value: &str) -> std::result::Result<f64, String> {
+    let rates = value.split_once(':').and_then(|(from, to)| {
+        let from: f64 = from.trim().parse().ok()?;
+        let to: f64 = to.trim().parse().ok()?;
+        (from > 0.0 && to > 0.0).then_some((from, to))
+    });
+    rates
+        .map(|(from, to)| framerate_ratio(from, to))
+        .ok_or_else(|| format!("invalid framerates {:?}, expected FROM:TO", value))
+}
+
+pub fn run(options: &Options) -> Result<ExitCode> {
+    let input = io::read(&options.input)?;
+    let factor = options.scale.or(options.fps);
+    let offset = options.by.unwrap_or(0);
+
+    let text = match input.format {
+        Format::Ass | Format::Ssa => {
+            let mut script = io::script(&input)?;
+            let all = Selection::default();
+            if let Some(factor) = factor {
+                substation::timing::scale(&mut script.events, factor, &all);
+            }
+            substation::timing::shift(&mut script.events, offset, &all);
+            substation::writer::to_string(&script)
+        }
+        Format::SubRip => {
+            let (mut entries, _) = subrip::reader::parse_lenient(&input.text);
+            if let Some(factor) = factor {
+                subrip::timing::scale(&mut entries, factor, None);
+            }
+            subrip::timing::shift(&mut entries, offset, None);
+            subrip::writer::to_string(&entries, &Default::default())
+        }
+        format => {
+            let format = subtitles::format(format);
+            let mut cues = format.parse(&input.text)?;
+            for cue in &mut cues {
+                for time in [&mut cue.start, &mut cue.end] {
+                    if let Some(factor) = factor {
+                        *time = scale_duration(*time, factor);
+                    }
+                    *time = shift_duration(*time, offset);
+                }
+            }
+            format.write(&cues)
+        }
+    };
+
+    let encoding =
+        io::output_encoding(options.output_encoding.as_deref(), input.detection.encoding)?;
+    io::write(
+        options.output.as_deref(),
+        &text,
+        encoding,
+        input.detection.bom,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets() {
+        for (value, expected) in [
+            ("1500", Ok(1500)),
+            ("-1500", Ok(-1500)),
+            ("250ms", Ok(250)),
+            ("-2.5s", Ok(-2500)),
+            ("1.0004s", Ok(1000)),
+            (" 3 s", Ok(3000)),
+            ("1.5", Err(())),
+            ("2.5ms", Err(())),
+            ("s", Err(())),
+            ("soon", Err(())),
+        ] {
+            assert_eq!(parse_offset(value).map_err(|_| ()), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn framerates() {
+        assert_eq!(parse_fps("25:25"), Ok(1.0));
+        assert_eq!(parse_fps("25:23.976"), Ok(framerate_ratio(25.0, 23.976)));
+        assert_eq!(parse_fps(" 24 : 30 "), Ok(framerate_ratio(24.0, 30.0)));
+        for value in ["25", "0:25", "25:-1", "a:b", "25:23.976:1", ""] {
+            assert!(parse_fps(value).is_err(), "{:?}", value);
+        }
+    }
+}
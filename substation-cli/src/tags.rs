@@ -0,0 +1,77 @@
+use crate::{io, InputArgs, Result};
+use clap::Args;
+use serde::Serialize;
+use std::process::ExitCode;
+use substation::{Entry, TextSection, Timestamp};
+use subtitles::Format;
+
+#[derive(Args)]
+pub struct Options {
+    #[command(flatten)]
+    input: InputArgs,
+
+    /// Only this event, counting from 1.
+    #[arg(long)]
+    event: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Event {
+    event: usize,
+    text: String,
+    sections: Vec<TextSection>,
+}
+
+fn print(n: usize, entry: &Entry, sections: &[TextSection]) {
+    let time = |t: Option<std::time::Duration>| {
+        t.map(|t| Timestamp::from_duration(t).to_string())
+            .unwrap_or_else(|| "-".to_owned())
+    };
+    println!(
+        "#{} {} --> {} {}",
+        n,
+        time(entry.start),
+        time(entry.end),
+        entry.style.as_deref().unwrap_or("")
+    );
+    for section in sections {
+        match section {
+            TextSection::Text(text) => println!("  text {:?}", text),
+            TextSection::StyleOverride(overrides) => {
+                for o in overrides {
+                    println!("  tag  {:?}", o);
+                }
+            }
+            TextSection::Drawing(commands) => println!("  draw {:?}", commands),
+        }
+    }
+}
+
+pub fn run(options: &Options, json: bool) -> Result<ExitCode> {
+    let input = io::read(&options.input)?;
+    if !matches!(input.format, Format::Ass | Format::Ssa) {
+        return Err(format!("{} has no override tags", input.format.name()).into());
+    }
+    let script = io::script(&input)?;
+
+    let mut events = Vec::new();
+    for (n, entry) in script.events.iter().enumerate().map(|(n, e)| (n + 1, e)) {
+        if options.event.is_some_and(|only| only != n) {
+            continue;
+        }
+        let sections = entry.parsed_text();
+        if json {
+            events.push(Event {
+                event: n,
+                text: entry.text.clone(),
+                sections,
+            });
+        } else {
+            print(n, entry, &sections);
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&events)?);
+    }
+    Ok(ExitCode::SUCCESS)
+}
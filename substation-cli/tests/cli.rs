@@ -0,0 +1,101 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const SRT: &str = "1\n00:00:01,000 --> 00:00:02,500\n<i>Hello</i>\n\n\
+                   2\n00:00:03,000 --> 00:00:04,000\nthere\n";
+
+// runs the binary with `input` on standard input
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_substation"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // commands that fail on their arguments exit without reading it
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn convert() {
+    let vtt = stdout(&run(&["convert", "--to", "vtt"], SRT));
+    assert_eq!(
+        vtt,
+        "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\n<i>Hello</i>\n\n\
+         00:00:03.000 --> 00:00:04.000\nthere\n"
+    );
+
+    let ass = stdout(&run(&["convert", "--to", "ass"], SRT));
+    assert!(ass.starts_with("[Script Info]"), "{}", ass);
+    assert!(
+        ass.contains("Dialogue: 0,0:00:01.00,0:00:02.50,Default,,0,0,0,,{\\i1}Hello"),
+        "{}",
+        ass
+    );
+
+    // SubRip is written with CRLF line endings
+    let back = stdout(&run(&["convert", "--to", "srt"], &ass));
+    assert_eq!(back, SRT.replace('\n', "\r\n"));
+}
+
+#[test]
+fn convert_needs_an_output_format() {
+    let output = run(&["convert"], SRT);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--to"));
+}
+
+#[test]
+fn convert_refuses_to_write_ssa() {
+    for args in [
+        &["convert", "--to", "ssa"][..],
+        &["convert", "-o", "out.ssa"],
+    ] {
+        let output = run(args, SRT);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("SSA"));
+    }
+}
+
+#[test]
+fn shift() {
+    let shifted = stdout(&run(&["shift", "--by", "-500"], SRT));
+    assert!(
+        shifted.starts_with("1\r\n00:00:00,500 --> 00:00:02,000\r\n"),
+        "{}",
+        shifted
+    );
+    let shifted = stdout(&run(&["shift", "--by", "1.5s", "--scale", "2"], SRT));
+    assert!(
+        shifted.contains("00:00:07,500 --> 00:00:09,500"),
+        "{}",
+        shifted
+    );
+
+    let output = run(&["shift", "--by", "soon"], SRT);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid offset"));
+    let output = run(&["shift", "--fps", "25"], SRT);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn info_json() {
+    let info = stdout(&run(&["info", "--json"], SRT));
+    let info: serde_json::Value = serde_json::from_str(&info).unwrap();
+    assert_eq!(info["format"], "SubRip");
+    assert_eq!(info["events"], 2);
+    assert_eq!(info["start_ms"], 1000);
+    assert_eq!(info["end_ms"], 4000);
+}
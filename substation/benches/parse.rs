@@ -0,0 +1,102 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::fmt::Write as _;
+use substation::{borrowed, parser};
+
+const EVENT_FORMAT: &str =
+    "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+// a karaoke line split into syllables, each with its own move and fade, the
+// way karaoke templaters generate them
+fn karaoke_line(n: usize, syllables: usize) -> String {
+    let mut text = String::new();
+    for s in 0..syllables {
+        let _ = write!(
+            text,
+            "{{\\k{}\\1c&H{:06X}&\\move({},{},{},{},0,{})\\fad(80,120)\\t(0,200,\\fscx120\\fscy120)\\fnArial}}syl{}",
+            10 + s % 40,
+            (n * 7919 + s) % 0xFFFFFF,
+            100 + s * 20,
+            900,
+            100 + s * 20,
+            880,
+            200 + s * 10,
+            s
+        );
+    }
+    text
+}
+
+fn karaoke_script(events: usize) -> String {
+    let mut script = String::from(
+        "[Script Info]\nScriptType: v4.00+\nPlayResX: 1920\nPlayResY: 1080\n\n\
+         [V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, \
+         OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, \
+         Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Default,Arial,48,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,0,8,10,10,10,1\n\n",
+    );
+    let _ = writeln!(script, "[Events]\nFormat: {}", EVENT_FORMAT);
+    for n in 0..events {
+        let secs = n / 10;
+        let _ = writeln!(
+            script,
+            "Dialogue: 0,0:{:02}:{:02}.00,0:{:02}:{:02}.50,Default,,0,0,0,fx,{}",
+            secs / 60 % 60,
+            secs % 60,
+            secs / 60 % 60,
+            secs % 60,
+            karaoke_line(n, 12)
+        );
+    }
+    script
+}
+
+fn parse(c: &mut Criterion) {
+    let script = karaoke_script(20_000);
+    let lines: Vec<&str> = script
+        .lines()
+        .filter(|l| l.starts_with("Dialogue:"))
+        .collect();
+    let definition: Vec<String> = EVENT_FORMAT.split(", ").map(|f| f.to_owned()).collect();
+
+    let mut group = c.benchmark_group("karaoke script");
+    group.throughput(Throughput::Bytes(script.len() as u64));
+    group.sample_size(10);
+    group.bench_function("script", |b| {
+        b.iter(|| parser::script(black_box(&script)).unwrap())
+    });
+    #[cfg(feature = "rayon")]
+    group.bench_function("script in parallel", |b| {
+        b.iter(|| substation::parallel::script(black_box(&script)).unwrap())
+    });
+    group.bench_function("owned events and text", |b| {
+        b.iter(|| {
+            for line in &lines {
+                let (_, entry) = parser::subtitle(black_box(line), &definition).unwrap();
+                black_box(entry.parsed_text());
+            }
+        })
+    });
+    group.bench_function("borrowed events and text", |b| {
+        b.iter(|| {
+            for line in &lines {
+                let (_, entry) = borrowed::subtitle(black_box(line), &definition).unwrap();
+                black_box(entry.text_sections());
+            }
+        })
+    });
+    group.finish();
+
+    let long_line = karaoke_line(0, 2_000);
+    let mut group = c.benchmark_group("long line");
+    group.throughput(Throughput::Bytes(long_line.len() as u64));
+    group.bench_function("owned", |b| {
+        b.iter(|| parser::text_line(black_box(&long_line)).unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| borrowed::text_line(black_box(&long_line)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
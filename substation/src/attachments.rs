@@ -0,0 +1,218 @@
+//! Files embedded in a script's `[Fonts]` and `[Graphics]` sections.
+//!
+//! Each starts with a `fontname: name.ttf` (or `filename:`) line followed by
+//! its bytes in SSA's variant of uuencoding: every 6 bits plus 33 as a
+//! character, 80 characters to a line.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Attachment {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Decodes uuencoded text, ignoring line breaks.
+pub fn decode(text: &str) -> Vec<u8> {
+    let values: Vec<u8> = text
+        .bytes()
+        .filter(|b| (33..=96).contains(b))
+        .map(|b| b - 33)
+        .collect();
+    let mut data = Vec::with_capacity(values.len() * 3 / 4);
+    for group in values.chunks(4) {
+        let bits = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (n, v)| bits | (*v as u32) << (18 - 6 * n));
+        // 2 characters hold one byte, 3 hold two
+        let bytes = group.len() * 3 / 4;
+        data.extend(&bits.to_be_bytes()[1..1 + bytes]);
+    }
+    data
+}
+
+/// Encodes bytes the way they are stored in a script, with `\n` after every
+/// 80 characters.
+pub fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() * 4 / 3 + data.len() / 60 + 2);
+    let mut count = 0;
+    for group in data.chunks(3) {
+        let bits = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (n, b)| bits | (*b as u32) << (16 - 8 * n));
+        for n in 0..group.len() + 1 {
+            if count > 0 && count % 80 == 0 {
+                out.push('\n');
+            }
+            out.push((((bits >> (18 - 6 * n)) & 0x3f) as u8 + 33) as char);
+            count += 1;
+        }
+    }
+    out
+}
+
+/// The key naming each file in an attachment section.
+pub(crate) fn file_key(section: &str) -> Option<&'static str> {
+    match section {
+        "Fonts" => Some("fontname"),
+        "Graphics" => Some("filename"),
+        _ => None,
+    }
+}
+
+/// Reads the files of an attachment section from its lines, up to the next
+/// section header.
+pub(crate) fn files<'a>(lines: impl Iterator<Item = &'a str>, key: &str) -> Vec<Attachment> {
+    let mut files = Vec::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in lines.map(|l| l.trim_end_matches('\r')) {
+        if is_section_header(line) {
+            break;
+        }
+        match line.strip_prefix(key).and_then(|l| l.strip_prefix(':')) {
+            Some(file) => {
+                files.extend(current.take());
+                current = Some((file.trim().to_owned(), String::new()));
+            }
+            None => {
+                if let Some((_, text)) = &mut current {
+                    text.push_str(line.trim());
+                }
+            }
+        }
+    }
+    files.extend(current);
+
+    files
+        .into_iter()
+        .map(|(name, text)| Attachment {
+            name,
+            data: decode(&text),
+        })
+        .collect()
+}
+
+/// Whether a line is a `[Section]` header. Encoded lines can start with `[`
+/// too, but never hold a space or anything else outside the encoding.
+pub(crate) fn is_section_header(line: &str) -> bool {
+    line.trim_end()
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .is_some_and(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '+')
+        })
+        && (line.contains(' ') || line.len() < 20)
+}
+
+fn section(input: &str, name: &str) -> Vec<Attachment> {
+    let key = file_key(name).unwrap();
+    let header = format!("[{}]", name);
+    let mut lines = input.lines();
+    let mut found = Vec::new();
+    while let Some(line) = lines.next() {
+        if line.trim_end() == header {
+            found.extend(files(&mut lines, key));
+        }
+    }
+    found
+}
+
+/// The fonts in the `[Fonts]` section. Their names are as stored, which
+/// tools usually write as the file name with `_0` before the extension.
+pub fn fonts(input: &str) -> Vec<Attachment> {
+    section(input, "Fonts")
+}
+
+/// The images in the `[Graphics]` section.
+pub fn graphics(input: &str) -> Vec<Attachment> {
+    section(input, "Graphics")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        for (data, text) in [
+            (&b""[..], ""),
+            (b"a", "91"),
+            (b"ab", "97)"),
+            (b"abc", "97*D"),
+            (b"\0\0\0", "!!!!"),
+            (b"\xff\xff\xff", "````"),
+        ] {
+            assert_eq!(encode(data), text, "{:?}", data);
+            assert_eq!(decode(text), data, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for len in 0..200 {
+            let data: Vec<u8> = (0..len).map(|n| (n * 37 + len) as u8).collect();
+            let text = encode(&data);
+            assert!(text.lines().all(|l| l.len() <= 80), "{}", len);
+            assert_eq!(decode(&text), data, "{}", len);
+            assert_eq!(decode(&text.replace('\n', "\r\n")), data, "{}", len);
+        }
+    }
+
+    #[test]
+    fn section_headers() {
+        for (line, header) in [
+            ("[Fonts]", true),
+            ("[V4+ Styles]", true),
+            ("[Events]  ", true),
+            ("[Aegisub Project Garbage]", true),
+            ("[]", false),
+            ("Fonts", false),
+            ("[Fonts", false),
+            // an encoded line that happens to be in brackets
+            ("[7*D!!!!````97*D!!!!````97*D!!!!````]", false),
+            ("[a-b]", false),
+        ] {
+            assert_eq!(is_section_header(line), header, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn sections() {
+        let script = format!(
+            "[Script Info]\n\n[Fonts]\nfontname: a_0.ttf\n{}\nfontname: b_0.ttf\n{}\n\n\
+             [Graphics]\nfilename: c.png\n{}\n[Events]\n",
+            encode(&[1; 100]),
+            encode(b"b"),
+            encode(b"png")
+        );
+        let fonts = fonts(&script);
+        assert_eq!(
+            fonts,
+            [
+                Attachment {
+                    name: "a_0.ttf".to_owned(),
+                    data: vec![1; 100],
+                },
+                Attachment {
+                    name: "b_0.ttf".to_owned(),
+                    data: b"b".to_vec(),
+                },
+            ]
+        );
+        assert_eq!(graphics(&script)[0].data, b"png");
+
+        let (_, parsed) = crate::parser::script(&script).unwrap();
+        let written = crate::writer::to_string(&parsed);
+        assert_eq!(attachments_of(&written), attachments_of(&script));
+    }
+
+    fn attachments_of(script: &str) -> (Vec<Attachment>, Vec<Attachment>) {
+        (fonts(script), graphics(script))
+    }
+}
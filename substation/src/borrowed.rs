@@ -0,0 +1,294 @@
+//! Scripts parsed without copying: the same types as the crate root, with
+//! strings that borrow from the source text. `into_owned` turns them into
+//! the owned ones.
+
+use crate::parser::{borrowed_style, borrowed_subtitle, borrowed_text_line};
+use nom::{
+    bytes::complete::tag,
+    character::complete::{not_line_ending, space0},
+    sequence::preceded,
+    IResult,
+};
+use std::borrow::Cow;
+
+pub type Style<'a> = crate::Style<Cow<'a, str>>;
+pub type Entry<'a> = crate::Entry<Cow<'a, str>>;
+pub type TextSection<'a> = crate::TextSection<Cow<'a, str>>;
+pub type StyleOverride<'a> = crate::StyleOverride<Cow<'a, str>>;
+
+/// Parses the text of an event into sections that borrow from it.
+pub fn text_line(input: &str) -> IResult<&str, Vec<TextSection<'_>>> {
+    borrowed_text_line(input)
+}
+
+/// Parses an event line, its fields named by the `[Events]` format line.
+pub fn subtitle<'a>(input: &'a str, definition: &[String]) -> IResult<&'a str, Entry<'a>> {
+    borrowed_subtitle(input, definition)
+}
+
+/// Parses a `Style:` line, its fields named by the `[V4+ Styles]` format
+/// line.
+pub fn style<'a>(input: &'a str, definition: &[&str]) -> IResult<&'a str, Style<'a>> {
+    let (input, line) = preceded(tag("Style:"), preceded(space0, not_line_ending))(input)?;
+    let vals = line.split(',').map(|v| v.trim_start()).collect();
+    Ok((input, borrowed_style(definition, vals)))
+}
+
+impl Style<'_> {
+    pub fn into_owned(self) -> crate::Style {
+        crate::Style {
+            name: self.name.into_owned(),
+            fontname: self.fontname.into_owned(),
+            fontsize: self.fontsize,
+            primary_color: self.primary_color,
+            secondary_color: self.secondary_color,
+            outline_color: self.outline_color,
+            back_color: self.back_color,
+            bold: self.bold,
+            italic: self.italic,
+            underline: self.underline,
+            strikeout: self.strikeout,
+            scale_x: self.scale_x,
+            scale_y: self.scale_y,
+            spacing: self.spacing,
+            angle: self.angle,
+            border_style: self.border_style,
+            outline_size: self.outline_size,
+            shadow: self.shadow,
+            alignment: self.alignment,
+            margin_l: self.margin_l,
+            margin_r: self.margin_r,
+            margin_v: self.margin_v,
+            encoding: self.encoding,
+        }
+    }
+}
+
+impl<'a> Entry<'a> {
+    pub fn into_owned(self) -> crate::Entry {
+        crate::Entry {
+            kind: self.kind.map(Cow::into_owned),
+            layer: self.layer,
+            start: self.start,
+            end: self.end,
+            style: self.style.map(Cow::into_owned),
+            name: self.name.map(Cow::into_owned),
+            margin_l: self.margin_l,
+            margin_r: self.margin_r,
+            margin_v: self.margin_v,
+            effect: self.effect.map(Cow::into_owned),
+            read_order: self.read_order,
+            text: self.text.into_owned(),
+        }
+    }
+
+    /// The sections of the text, borrowing from it when the entry does.
+    pub fn text_sections(&self) -> Vec<TextSection<'_>> {
+        borrowed_text_line(&self.text)
+            .map(|(_, sections)| sections)
+            .unwrap_or_default()
+    }
+}
+
+impl TextSection<'_> {
+    pub fn into_owned(self) -> crate::TextSection {
+        match self {
+            TextSection::Text(text) => crate::TextSection::Text(text.into_owned()),
+            TextSection::StyleOverride(overrides) => crate::TextSection::StyleOverride(
+                overrides
+                    .into_iter()
+                    .map(StyleOverride::into_owned)
+                    .collect(),
+            ),
+            TextSection::Drawing(commands) => crate::TextSection::Drawing(commands),
+        }
+    }
+}
+
+impl StyleOverride<'_> {
+    pub fn into_owned(self) -> crate::StyleOverride {
+        use crate::StyleOverride::*;
+        match self {
+            Bold(v) => Bold(v),
+            Italic(v) => Italic(v),
+            Underline(v) => Underline(v),
+            StrikeOut(v) => StrikeOut(v),
+            Border(v) => Border(v),
+            Shadow(v) => Shadow(v),
+            BlurEdges(v) => BlurEdges(v),
+            FontName(name) => FontName(name.into_owned()),
+            FontSize(v) => FontSize(v),
+            ScaleX(v) => ScaleX(v),
+            ScaleY(v) => ScaleY(v),
+            LetterSpacing(v) => LetterSpacing(v),
+            RotationX(v) => RotationX(v),
+            RotationY(v) => RotationY(v),
+            RotationZ(v) => RotationZ(v),
+            Charset(v) => Charset(v),
+            Color(index, color) => Color(index, color),
+            Alpha(index, alpha) => Alpha(index, alpha),
+            Alignment(v) => Alignment(v),
+            NumpadLayoutAlignment(v) => NumpadLayoutAlignment(v),
+            KaraokeDuration(v) => KaraokeDuration(v),
+            WrappingStyle(v) => WrappingStyle(v),
+            Reset(style) => Reset(style.map(Cow::into_owned)),
+            DrawingMode(v) => DrawingMode(v),
+            BaselineOffset(v) => BaselineOffset(v),
+            Transition {
+                start,
+                end,
+                acceleration,
+                styles,
+            } => Transition {
+                start,
+                end,
+                acceleration,
+                styles: styles.into_iter().map(StyleOverride::into_owned).collect(),
+            },
+            Move {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                start,
+                end,
+            } => Move {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                start,
+                end,
+            },
+            Origin { x, y } => Origin { x, y },
+            Fade {
+                starting_alpha,
+                middle_alpha,
+                ending_alpha,
+                start_time,
+                in_between_time,
+                late_time,
+                ending_time,
+            } => Fade {
+                starting_alpha,
+                middle_alpha,
+                ending_alpha,
+                start_time,
+                in_between_time,
+                late_time,
+                ending_time,
+            },
+            FadeInAndOut {
+                fade_in_for,
+                fade_out_for,
+            } => FadeInAndOut {
+                fade_in_for,
+                fade_out_for,
+            },
+            Clip { a_x, a_y, b_x, b_y } => Clip { a_x, a_y, b_x, b_y },
+            ClipToDrawing(index, commands) => ClipToDrawing(index, commands),
+            EmptyClip => EmptyClip,
+            Other(tag) => Other(tag.into_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const STYLE_FORMAT: [&str; 6] = [
+        "Name",
+        "Fontname",
+        "Fontsize",
+        "PrimaryColour",
+        "Bold",
+        "Alignment",
+    ];
+
+    fn event_format() -> Vec<String> {
+        ["Layer", "Start", "End", "Style", "Name", "Effect", "Text"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    const TEXTS: [&str; 6] = [
+        "plain",
+        "{\\b1\\fnComic Sans\\c&H00FF00&}bold{\\r}\\Nline",
+        "{\\t(0,500,\\fs20\\1c&HFF&)\\move(1,2,3,4)}moving",
+        "{\\p1}m 0 0 l 10 0 10 10{\\p0} {\\clip(m 0 0 l 1 1)}",
+        "\\{escaped\\} {\\rTop}{\\fad(1)}",
+        "",
+    ];
+
+    #[test]
+    fn texts_match_the_owned_parser() {
+        for text in TEXTS {
+            let (rest, borrowed) = text_line(text).unwrap();
+            let owned: Vec<crate::TextSection> =
+                borrowed.into_iter().map(TextSection::into_owned).collect();
+            let (owned_rest, expected) = parser::text_line(text).unwrap();
+            assert_eq!(rest, owned_rest, "{:?}", text);
+            assert_eq!(
+                format!("{:?}", owned),
+                format!("{:?}", expected),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn events_match_the_owned_parser() {
+        let definition = event_format();
+        for text in TEXTS {
+            let line = format!(
+                "Comment: 2,0:00:01.50,1:02:03.04,Top,Someone,Banner;10,{}",
+                text
+            );
+            let (_, borrowed) = subtitle(&line, &definition).unwrap();
+            assert!(
+                text.is_empty() || matches!(borrowed.text, Cow::Borrowed(_)),
+                "{:?}",
+                line
+            );
+            assert_eq!(
+                format!("{:?}", borrowed.text_sections()),
+                format!("{:?}", text_line(text).unwrap().1),
+                "{:?}",
+                line
+            );
+            let (_, expected) = parser::subtitle(&line, &definition).unwrap();
+            assert_eq!(
+                format!("{:?}", borrowed.into_owned()),
+                format!("{:?}", expected),
+                "{:?}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn styles_match_the_whole_script() {
+        let styles =
+            "Style: Default,Arial,20,&H00FFFFFF,0,2\nStyle: Top,DejaVu Sans,18.5,&H000000FF,-1,8";
+        let script = format!(
+            "[V4+ Styles]\nFormat: {}\n{}\n",
+            STYLE_FORMAT.join(", "),
+            styles
+        );
+        let (_, parsed) = parser::script(&script).unwrap();
+        for line in styles.lines() {
+            let (_, borrowed) = style(line, &STYLE_FORMAT).unwrap();
+            assert!(matches!(borrowed.fontname, Cow::Borrowed(_)), "{:?}", line);
+            let owned = borrowed.into_owned();
+            assert_eq!(
+                format!("{:?}", owned),
+                format!("{:?}", parsed.styles[&owned.name]),
+                "{:?}",
+                line
+            );
+        }
+    }
+}
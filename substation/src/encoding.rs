@@ -0,0 +1,212 @@
+//! Reading and writing scripts in legacy encodings. A style's `Encoding`
+//! field and the `\fe` override hold a Windows GDI charset, which names the
+//! code page the text was written in.
+
+use crate::parser::script;
+use crate::writer::to_string;
+use crate::{Script, Style};
+use parsing_utils::encoding::{
+    code_page, decode, decode_as, encode, Decoded, Encoding, UTF_16BE, UTF_16LE, UTF_8,
+};
+
+/// The code page of a GDI charset. `1` (the system default) and `2` (symbol
+/// fonts) have none.
+pub fn charset_code_page(charset: u64) -> Option<u16> {
+    Some(match charset {
+        0 => 1252,
+        77 => 10000,
+        128 => 932,
+        129 => 949,
+        130 => 1361,
+        134 => 936,
+        136 => 950,
+        161 => 1253,
+        162 => 1254,
+        163 => 1258,
+        177 => 1255,
+        178 => 1256,
+        186 => 1257,
+        204 => 1251,
+        222 => 874,
+        238 => 1250,
+        255 => 437,
+        _ => return None,
+    })
+}
+
+pub fn charset_encoding(charset: u64) -> Option<&'static Encoding> {
+    charset_code_page(charset).and_then(code_page)
+}
+
+impl Style {
+    /// The encoding this style's `Encoding` field asks for.
+    pub fn text_encoding(&self) -> Option<&'static Encoding> {
+        self.encoding.and_then(|e| charset_encoding(e as u64))
+    }
+}
+
+/// The encoding the script's styles agree on, ignoring those in Western
+/// (`0`) or default (`1`) charsets, which say nothing about the file.
+pub fn script_encoding(script: &Script) -> Option<&'static Encoding> {
+    let mut found = None;
+    for style in script.styles.values() {
+        if matches!(style.encoding, None | Some(0) | Some(1)) {
+            continue;
+        }
+        match (found, style.text_encoding()) {
+            (_, None) => (),
+            (None, encoding) => found = encoding,
+            (Some(a), Some(b)) if a != b => return None,
+            _ => (),
+        }
+    }
+    found
+}
+
+/// Decodes a script file. When the encoding can only be guessed, a charset
+/// the styles agree on is used instead. Byte statistics are taken as a
+/// guess however sure of them the detector is, since short lines of dialogue
+/// fool it easily.
+pub fn decode_script(bytes: &[u8]) -> Decoded {
+    let decoded = decode(bytes);
+    let detection = decoded.detection;
+    if detection.bom || [UTF_8, UTF_16LE, UTF_16BE].contains(&detection.encoding) {
+        return decoded;
+    }
+    // the sections and fields are ASCII, so they survive a lossy read
+    match script(&String::from_utf8_lossy(bytes)) {
+        Ok((_, parsed)) => match script_encoding(&parsed) {
+            Some(encoding) => decode_as(bytes, encoding),
+            None => decoded,
+        },
+        Err(_) => decoded,
+    }
+}
+
+/// Writes the script in `encoding`, or in the one its styles agree on, or
+/// else UTF-8 with a byte order mark. As with [`encode`], the flag says if
+/// any characters had to be written as numeric references.
+pub fn encode_script(script: &Script, encoding: Option<&'static Encoding>) -> (Vec<u8>, bool) {
+    let encoding = encoding.or_else(|| script_encoding(script));
+    let bom = encoding.is_none();
+    let encoding = encoding.unwrap_or(UTF_8);
+    encode(&to_string(script), encoding, bom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsing_utils::encoding::detect;
+
+    fn styles(encodings: &[usize]) -> String {
+        let mut text = "[V4+ Styles]\nFormat: Name, Fontname, Encoding\n".to_owned();
+        for (n, encoding) in encodings.iter().enumerate() {
+            text.push_str(&format!("Style: S{},Arial,{}\n", n, encoding));
+        }
+        text
+    }
+
+    #[test]
+    fn charsets() {
+        for (charset, page) in [
+            (0, Some(1252)),
+            (1, None),
+            (2, None),
+            (128, Some(932)),
+            (134, Some(936)),
+            (136, Some(950)),
+            (204, Some(1251)),
+            (255, Some(437)),
+            (300, None),
+        ] {
+            assert_eq!(charset_code_page(charset), page, "{}", charset);
+        }
+        assert_eq!(charset_encoding(128), code_page(932));
+        // no encoding for the OEM code page
+        assert_eq!(charset_encoding(255), None);
+    }
+
+    #[test]
+    fn styles_agree() {
+        for (encodings, expected) in [
+            (&[][..], None),
+            (&[0, 1], None),
+            (&[128], code_page(932)),
+            (&[0, 128, 1, 128], code_page(932)),
+            (&[128, 134], None),
+            (&[128, 2], code_page(932)),
+        ] {
+            let (_, script) = script(&styles(encodings)).unwrap();
+            assert_eq!(script_encoding(&script), expected, "{:?}", encodings);
+        }
+    }
+
+    #[test]
+    fn decode_with_style_charset() {
+        // (charset, code page, text), each misread without the charset
+        for (charset, page, dialogue) in [(204, 1251, "Да"), (134, 936, "你好")] {
+            let text = format!(
+                "{}\n[Events]\nFormat: Style, Text\nDialogue: S0,{}\n",
+                styles(&[charset]),
+                dialogue
+            );
+            let encoding = code_page(page).unwrap();
+            let bytes = encode(&text, encoding, false).0;
+            assert_ne!(detect(&bytes).encoding, encoding);
+
+            let decoded = decode_script(&bytes);
+            assert_eq!(decoded.detection.encoding, encoding);
+            assert_eq!(decoded.text, text);
+        }
+
+        // UTF-8 and byte order marks are kept
+        let text = format!("{}\nDialogue: S0,日本\n", styles(&[128]));
+        let decoded = decode_script(text.as_bytes());
+        assert_eq!(decoded.detection.encoding, UTF_8);
+        assert_eq!(decoded.text, text);
+        let decoded = decode_script(&encode(&text, UTF_16LE, true).0);
+        assert_eq!(decoded.detection.encoding, UTF_16LE);
+        assert_eq!(decoded.text, text);
+    }
+
+    #[test]
+    fn encode_in_style_charset() {
+        let (_, script) = script(&format!(
+            "{}\n[Events]\nFormat: Style, Text\nDialogue: S0,日本\n",
+            styles(&[128])
+        ))
+        .unwrap();
+        let (bytes, unmappable) = encode_script(&script, None);
+        let decoded = decode_as(&bytes, code_page(932).unwrap());
+        assert!(decoded.text.contains(",日本\n"));
+        assert!(!decoded.had_errors);
+        assert!(!unmappable);
+
+        let (bytes, unmappable) = encode_script(&script, Some(UTF_8));
+        assert!(!bytes.starts_with(b"\xef\xbb\xbf"));
+        assert!(!unmappable);
+
+        let (_, plain) = crate::parser::script(&styles(&[0])).unwrap();
+        assert!(encode_script(&plain, None).0.starts_with(b"\xef\xbb\xbf"));
+    }
+
+    #[test]
+    fn unmappable_characters_are_reported() {
+        let (_, script) = script(&format!(
+            "{}\n[Events]\nFormat: Style, Text\nDialogue: S0,Да 日本\n",
+            styles(&[204])
+        ))
+        .unwrap();
+        let (bytes, unmappable) = encode_script(&script, None);
+        assert!(unmappable);
+        let decoded = decode_as(&bytes, code_page(1251).unwrap());
+        assert!(
+            decoded.text.contains(",Да &#26085;&#26412;\n"),
+            "{}",
+            decoded.text
+        );
+
+        let (_, unmappable) = encode_script(&script, Some(UTF_8));
+        assert!(!unmappable);
+    }
+}
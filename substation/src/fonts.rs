@@ -0,0 +1,461 @@
+//! The fonts a script needs: every family, weight and slant its events are
+//! drawn in, with the characters drawn in each.
+//!
+//! With the `fonts` feature, these can be matched against font files the
+//! way renderers pick them, by the names in their `name` tables.
+
+use crate::parser::borrowed_text_line;
+use crate::{unescape_text, Script, Style, StyleOverride, TextSection};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FontUsage {
+    /// The family as first written in the script, without a leading `@`.
+    pub family: String,
+    /// 400 for regular, 700 for bold, or the weight given to `\b`.
+    pub weight: u16,
+    pub italic: bool,
+    pub characters: BTreeSet<char>,
+}
+
+/// The font state while walking through an event.
+struct Font<'a> {
+    family: &'a str,
+    weight: u16,
+    italic: bool,
+}
+
+impl<'a> Font<'a> {
+    fn new(style: &'a Style) -> Font<'a> {
+        Font {
+            family: &style.fontname,
+            weight: if style.bold.unwrap_or(false) {
+                700
+            } else {
+                400
+            },
+            italic: style.italic.unwrap_or(false),
+        }
+    }
+}
+
+/// Looks a style up the way libass does, ignoring a leading `*` and falling
+/// back to `Default`.
+fn find_style<'a>(script: &'a Script, name: &str) -> Option<&'a Style> {
+    script
+        .styles
+        .get(name)
+        .or_else(|| script.styles.get(name.trim().trim_start_matches('*')))
+        .or_else(|| script.styles.get("Default"))
+}
+
+/// Collects the fonts used to draw text, from each event's style and its
+/// `\fn`, `\b`, `\i` and `\r` tags, sorted by family. Comments, drawings
+/// and styles no event shows text in are left out. `\h` counts as a
+/// non-breaking space.
+pub fn usage(script: &Script) -> Vec<FontUsage> {
+    let mut fonts: BTreeMap<(String, u16, bool), FontUsage> = BTreeMap::new();
+
+    for event in &script.events {
+        if event.kind.as_deref() == Some("Comment") {
+            continue;
+        }
+        let Some(style) = find_style(script, event.style.as_deref().unwrap_or("Default")) else {
+            continue;
+        };
+        let sections = borrowed_text_line(&event.text)
+            .map(|(_, s)| s)
+            .unwrap_or_default();
+
+        // the style `\r` last went back to, which an empty `\fn` resets to
+        let mut base = style;
+        let mut font = Font::new(style);
+        for section in &sections {
+            match section {
+                TextSection::StyleOverride(overrides) => {
+                    for o in overrides {
+                        match o {
+                            StyleOverride::FontName(name) if name.trim().is_empty() => {
+                                font.family = &base.fontname
+                            }
+                            StyleOverride::FontName(name) => font.family = name.trim(),
+                            StyleOverride::Bold(b) if *b == 0.0 => font.weight = 400,
+                            StyleOverride::Bold(b) if *b == 1.0 => font.weight = 700,
+                            StyleOverride::Bold(b) if *b >= 100.0 => font.weight = *b as u16,
+                            StyleOverride::Italic(i) => font.italic = *i,
+                            StyleOverride::Reset(name) => {
+                                let reset = name.as_deref().and_then(|n| script.styles.get(n));
+                                base = reset.unwrap_or(style);
+                                font = Font::new(base);
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                TextSection::Text(text) => {
+                    let text = unescape_text(
+                        &text
+                            .replace("\\N", "")
+                            .replace("\\n", " ")
+                            .replace("\\h", "\u{a0}"),
+                    );
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let family = font.family.trim_start_matches('@');
+                    fonts
+                        .entry((family.to_lowercase(), font.weight, font.italic))
+                        .or_insert_with(|| FontUsage {
+                            family: family.to_owned(),
+                            weight: font.weight,
+                            italic: font.italic,
+                            characters: BTreeSet::new(),
+                        })
+                        .characters
+                        .extend(text.chars());
+                }
+                TextSection::Drawing(_) => (),
+            }
+        }
+    }
+
+    fonts.into_values().collect()
+}
+
+#[cfg(feature = "fonts")]
+pub use files::*;
+
+#[cfg(feature = "fonts")]
+mod files {
+    use super::FontUsage;
+    use crate::{Script, Section};
+    use std::collections::HashSet;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use ttf_parser::{name_id, Face};
+
+    /// One face of a font file, or of a collection.
+    #[derive(Clone, Debug)]
+    pub struct FontFile {
+        pub path: PathBuf,
+        /// The face's index in a collection, 0 otherwise.
+        pub index: u32,
+        /// Family names in every language given. Typographic families are
+        /// left out, as VSFilter can't see them.
+        pub families: Vec<String>,
+        /// Full and PostScript names, which renderers also match.
+        pub full_names: Vec<String>,
+        pub weight: u16,
+        pub italic: bool,
+        data: Arc<[u8]>,
+    }
+
+    impl FontFile {
+        /// Reads every face in `data`, skipping those that don't parse.
+        /// `path` only names where the data came from.
+        pub fn parse(path: impl Into<PathBuf>, data: Vec<u8>) -> Vec<FontFile> {
+            let path = path.into();
+            let data: Arc<[u8]> = data.into();
+            let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+            (0..count)
+                .filter_map(|index| {
+                    let face = Face::parse(&data, index).ok()?;
+                    let names = |ids: &[u16]| {
+                        let mut names: Vec<String> = Vec::new();
+                        for name in face.names() {
+                            if !ids.contains(&name.name_id) {
+                                continue;
+                            }
+                            if let Some(name) = name.to_string() {
+                                if !names.contains(&name) {
+                                    names.push(name);
+                                }
+                            }
+                        }
+                        names
+                    };
+                    Some(FontFile {
+                        path: path.clone(),
+                        index,
+                        families: names(&[name_id::FAMILY]),
+                        full_names: names(&[name_id::FULL_NAME, name_id::POST_SCRIPT_NAME]),
+                        weight: face.weight().to_number(),
+                        italic: face.is_italic() || face.is_oblique(),
+                        data: data.clone(),
+                    })
+                })
+                .collect()
+        }
+
+        pub fn load(path: &Path) -> io::Result<Vec<FontFile>> {
+            Ok(FontFile::parse(path, std::fs::read(path)?))
+        }
+
+        pub fn has_glyph(&self, c: char) -> bool {
+            self.missing_glyphs([c]).is_empty()
+        }
+
+        /// The characters the face has no glyph for, reading it only once.
+        pub fn missing_glyphs(&self, characters: impl IntoIterator<Item = char>) -> Vec<char> {
+            let face = Face::parse(&self.data, self.index).ok();
+            characters
+                .into_iter()
+                .filter(|&c| face.as_ref().and_then(|f| f.glyph_index(c)).is_none())
+                .collect()
+        }
+
+        fn has_family(&self, family: &str) -> bool {
+            self.families
+                .iter()
+                .chain(&self.full_names)
+                .any(|name| name.to_lowercase() == family)
+        }
+    }
+
+    /// Loads the `.ttf`, `.otf`, `.ttc` and `.otc` files in `dir` and the
+    /// directories under it. Symbolic links are followed, but no directory
+    /// is read twice.
+    pub fn scan_dir(dir: &Path) -> io::Result<Vec<FontFile>> {
+        let mut fonts = Vec::new();
+        scan(dir, &mut HashSet::new(), &mut fonts)?;
+        Ok(fonts)
+    }
+
+    fn scan(dir: &Path, seen: &mut HashSet<PathBuf>, fonts: &mut Vec<FontFile>) -> io::Result<()> {
+        if !seen.insert(std::fs::canonicalize(dir)?) {
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                scan(&path, seen, fonts)?;
+                continue;
+            }
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_ascii_lowercase());
+            if let Some("ttf" | "otf" | "ttc" | "otc") = extension.as_deref() {
+                fonts.extend(FontFile::load(&path)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// The fonts in the script's `[Fonts]` section, named by attachment.
+    pub fn attached(script: &Script) -> Vec<FontFile> {
+        script
+            .other
+            .iter()
+            .filter_map(|s| match s {
+                Section::Attachments { name, files } if name == "Fonts" => Some(files),
+                _ => None,
+            })
+            .flatten()
+            .flat_map(|f| FontFile::parse(&f.name, f.data.clone()))
+            .collect()
+    }
+
+    /// The file a font would be drawn with, if any.
+    #[derive(Clone, Debug)]
+    pub struct FontMatch<'a> {
+        pub usage: &'a FontUsage,
+        pub file: Option<&'a FontFile>,
+        /// Bold is asked for but the file isn't, so renderers embolden it.
+        pub faux_bold: bool,
+        pub faux_italic: bool,
+        /// Characters the file has no glyph for, leaving out control
+        /// characters. Empty when there is no file.
+        pub missing_glyphs: Vec<char>,
+    }
+
+    impl FontMatch<'_> {
+        pub fn is_missing(&self) -> bool {
+            self.file.is_none()
+        }
+    }
+
+    /// Finds a file for each font, by family, full or PostScript name
+    /// ignoring case, then the closest slant and weight among those.
+    pub fn match_fonts<'a>(usage: &'a [FontUsage], files: &'a [FontFile]) -> Vec<FontMatch<'a>> {
+        usage
+            .iter()
+            .map(|usage| {
+                let family = usage.family.to_lowercase();
+                let file = files
+                    .iter()
+                    .filter(|f| f.has_family(&family))
+                    .min_by_key(|f| (f.italic != usage.italic, f.weight.abs_diff(usage.weight)));
+                let characters = usage.characters.iter().filter(|c| !c.is_control());
+                FontMatch {
+                    usage,
+                    file,
+                    faux_bold: file.is_some_and(|f| usage.weight >= f.weight.saturating_add(150)),
+                    faux_italic: file.is_some_and(|f| usage.italic && !f.italic),
+                    missing_glyphs: match file {
+                        Some(file) => file.missing_glyphs(characters.copied()),
+                        None => Vec::new(),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::collections::BTreeSet;
+
+        // not a font, so it has no glyphs
+        fn file(family: &str, weight: u16, italic: bool) -> FontFile {
+            FontFile {
+                path: PathBuf::from(family),
+                index: 0,
+                families: vec![family.to_owned()],
+                full_names: vec![format!("{} Full", family)],
+                weight,
+                italic,
+                data: Vec::new().into(),
+            }
+        }
+
+        fn usage(family: &str, weight: u16, italic: bool) -> FontUsage {
+            FontUsage {
+                family: family.to_owned(),
+                weight,
+                italic,
+                characters: BTreeSet::from(['a', '\n']),
+            }
+        }
+
+        #[test]
+        fn matching() {
+            let files = [
+                file("Arial", 400, false),
+                file("Arial", 700, false),
+                file("Arial", 400, true),
+                file("Heavy", u16::MAX - 100, false),
+            ];
+            let usage = [
+                usage("arial", 400, false),
+                usage("ARIAL", 900, false),
+                usage("Arial", 700, true),
+                usage("Arial Full", 400, false),
+                usage("Heavy", u16::MAX, false),
+                usage("Missing", 400, false),
+            ];
+            let found: Vec<_> = match_fonts(&usage, &files)
+                .iter()
+                .map(|m| {
+                    (
+                        m.file.map(|f| (f.families[0].as_str(), f.weight, f.italic)),
+                        m.faux_bold,
+                        m.faux_italic,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                found,
+                [
+                    (Some(("Arial", 400, false)), false, false),
+                    (Some(("Arial", 700, false)), true, false),
+                    (Some(("Arial", 400, true)), true, false),
+                    (Some(("Arial", 400, false)), false, false),
+                    (Some(("Heavy", u16::MAX - 100, false)), true, false),
+                    (None, false, false),
+                ]
+            );
+        }
+
+        #[test]
+        fn missing_glyphs_leave_out_control_characters() {
+            let files = [file("Arial", 400, false)];
+            let usage = [usage("Arial", 400, false)];
+            let matches = match_fonts(&usage, &files);
+            assert_eq!(matches[0].missing_glyphs, ['a']);
+            assert!(!files[0].has_glyph('a'));
+        }
+
+        #[test]
+        fn real_fonts() {
+            let Ok(data) = std::fs::read("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf") else {
+                return;
+            };
+            let files = FontFile::parse("DejaVuSans.ttf", data);
+            assert_eq!(files[0].families, ["DejaVu Sans"]);
+            assert_eq!(files[0].weight, 400);
+            assert!(files[0].has_glyph('é'));
+            assert_eq!(files[0].missing_glyphs(['a', '\u{10fffd}']), ['\u{10fffd}']);
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn scan_dir_survives_symlink_cycles() {
+            let dir = std::env::temp_dir().join(format!("substation-fonts-{}", std::process::id()));
+            std::fs::create_dir_all(dir.join("sub")).unwrap();
+            let _ = std::os::unix::fs::symlink(&dir, dir.join("sub").join("loop"));
+            std::fs::write(dir.join("broken.ttf"), b"not a font").unwrap();
+            let fonts = scan_dir(&dir);
+            std::fs::remove_dir_all(&dir).unwrap();
+            assert!(fonts.unwrap().is_empty());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(events: &[&str]) -> Script {
+        let input = format!(
+            "[V4+ Styles]\n\
+             Format: Name, Fontname, Fontsize, Bold, Italic\n\
+             Style: Default,Arial,20,0,0\n\
+             Style: Title,Georgia,20,-1,0\n\
+             \n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Text\n\
+             {}\n",
+            events.join("\n")
+        );
+        crate::parser::script(&input).unwrap().1
+    }
+
+    fn fonts(script: &Script) -> Vec<(String, u16, bool, String)> {
+        usage(script)
+            .into_iter()
+            .map(|u| {
+                (
+                    u.family,
+                    u.weight,
+                    u.italic,
+                    u.characters.into_iter().collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn usage_follows_tags() {
+        let script = script(&[
+            "Dialogue: 0,0:00:01.00,0:00:02.00,Default,ab{\\b1}c{\\i1\\fn@Verdana}d{\\r}e",
+            "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\b900\\fnarial}f{\\rTitle}g{\\fn}h",
+            "Dialogue: 0,0:00:01.00,0:00:02.00,Nope,x\\hy\\N",
+            "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\p1}m 0 0 l 1 1{\\p0}",
+            "Comment: 0,0:00:01.00,0:00:02.00,Default,{\\fnComic Sans}z",
+        ]);
+        assert_eq!(
+            fonts(&script),
+            [
+                ("Arial".to_owned(), 400, false, "abexy\u{a0}".to_owned()),
+                ("Arial".to_owned(), 700, false, "c".to_owned()),
+                ("arial".to_owned(), 900, false, "f".to_owned()),
+                ("Georgia".to_owned(), 700, false, "gh".to_owned()),
+                ("Verdana".to_owned(), 700, true, "d".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,34 @@
+//! ASS and SSA through the workspace's common [`SubtitleFormat`] trait,
+//! converting by way of SubRip entries with [`crate::srt`].
+
+use crate::parser::script;
+use crate::srt::{from_srt, to_srt, FromSrtOptions, ToSrtOptions};
+use crate::writer::to_string;
+use parsing_utils::format::{Cue, Format, FormatError, SubtitleFormat};
+
+/// Reads both ASS and SSA scripts and writes ASS. There is no SSA writer,
+/// so [`SubtitleFormat::format`] is always [`Format::Ass`], including when
+/// this is what [`Format::Ssa`] is read with.
+#[derive(Clone, Debug, Default)]
+pub struct SubStation {
+    pub to_srt: ToSrtOptions,
+    pub from_srt: FromSrtOptions,
+}
+
+impl SubtitleFormat for SubStation {
+    fn format(&self) -> Format {
+        Format::Ass
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        let (_, script) = script(input).map_err(|e| FormatError {
+            line: None,
+            message: format!("invalid script: {}", e),
+        })?;
+        Ok(subrip::format::cues(&to_srt(&script, &self.to_srt)))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        to_string(&from_srt(&subrip::format::entries(cues), &self.from_srt))
+    }
+}
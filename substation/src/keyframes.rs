@@ -0,0 +1,575 @@
+use crate::timecodes::{TimeKind, Timecodes};
+use crate::timing::Selection;
+use crate::Entry;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyframesError {
+    UnknownFormat,
+    InvalidLine { line: usize, content: String },
+}
+
+impl fmt::Display for KeyframesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyframesError::UnknownFormat => write!(f, "unrecognized keyframe file"),
+            KeyframesError::InvalidLine { line, content } => {
+                write!(f, "invalid keyframe on line {}: {:?}", line, content)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyframesError {}
+
+/// A sorted list of the frames scene changes happen on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keyframes {
+    frames: Vec<i64>,
+    fps: Option<f64>,
+}
+
+impl Keyframes {
+    pub fn new(mut frames: Vec<i64>) -> Keyframes {
+        frames.sort_unstable();
+        frames.dedup();
+        Keyframes { frames, fps: None }
+    }
+
+    /// Parses an Aegisub keyframe file, an XviD or SCXvid pass log, an x264
+    /// stats file or a plain list of frame numbers (qpfiles included).
+    pub fn parse(input: &str) -> Result<Keyframes, KeyframesError> {
+        let input = input.trim_start_matches('\u{feff}');
+        let first = input.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+
+        if first.starts_with("# keyframe format v1") {
+            parse_aegisub(input)
+        } else if first.starts_with("# XviD 2pass stat file")
+            || first.starts_with("# ffmpeg 2-pass log file")
+        {
+            Ok(parse_xvid(input))
+        } else if first.starts_with("#options:") {
+            Ok(parse_x264(input))
+        } else if first.starts_with('#')
+            || first.trim_start().starts_with(|c: char| c.is_ascii_digit())
+        {
+            parse_list(input)
+        } else {
+            Err(KeyframesError::UnknownFormat)
+        }
+    }
+
+    pub fn frames(&self) -> &[i64] {
+        &self.frames
+    }
+
+    /// The framerate recorded in an Aegisub keyframe file, if any.
+    pub fn fps(&self) -> Option<f64> {
+        self.fps
+    }
+
+    pub fn is_keyframe(&self, frame: i64) -> bool {
+        self.frames.binary_search(&frame).is_ok()
+    }
+
+    /// The last keyframe at or before `frame`.
+    pub fn previous(&self, frame: i64) -> Option<i64> {
+        let n = self.frames.partition_point(|&k| k <= frame);
+        n.checked_sub(1).map(|n| self.frames[n])
+    }
+
+    /// The first keyframe strictly after `frame`.
+    pub fn next(&self, frame: i64) -> Option<i64> {
+        let n = self.frames.partition_point(|&k| k <= frame);
+        self.frames.get(n).copied()
+    }
+
+    /// The keyframe in `from..=to` closest to `frame`, preferring the earlier
+    /// one on ties.
+    fn closest(&self, frame: i64, from: i64, to: i64) -> Option<i64> {
+        let lo = self.frames.partition_point(|&k| k < from);
+        let hi = self.frames.partition_point(|&k| k <= to);
+        self.frames[lo..hi]
+            .iter()
+            .copied()
+            .min_by_key(|&k| ((k - frame).abs(), k))
+    }
+}
+
+fn invalid(line: usize, content: &str) -> KeyframesError {
+    KeyframesError::InvalidLine {
+        line: line + 1,
+        content: content.to_owned(),
+    }
+}
+
+// # keyframe format v1
+// fps 23.976
+// one keyframe per line
+fn parse_aegisub(input: &str) -> Result<Keyframes, KeyframesError> {
+    let mut fps = None;
+    let mut frames = Vec::new();
+    for (n, line) in input.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = trimmed.strip_prefix("fps ") {
+            fps = value.trim().parse::<f64>().ok().filter(|&v| v > 0.0);
+            continue;
+        }
+        frames.push(trimmed.parse::<i64>().map_err(|_| invalid(n, line))?);
+    }
+    let mut keyframes = Keyframes::new(frames);
+    keyframes.fps = fps;
+    Ok(keyframes)
+}
+
+// one line per frame, starting with its type; `i` frames are keyframes
+fn parse_xvid(input: &str) -> Keyframes {
+    let mut frames = Vec::new();
+    let mut count = 0;
+    for line in input.lines() {
+        match line.trim_start().chars().next() {
+            Some('i') => {
+                frames.push(count);
+                count += 1;
+            }
+            Some('p') | Some('b') => count += 1,
+            _ => (),
+        }
+    }
+    Keyframes::new(frames)
+}
+
+// in:0 out:0 type:I ...
+fn parse_x264(input: &str) -> Keyframes {
+    let mut frames = Vec::new();
+    let mut count = 0;
+    for line in input.lines() {
+        let Some(pos) = line.find("type:") else {
+            continue;
+        };
+        let frame = line
+            .split_whitespace()
+            .find_map(|f| f.strip_prefix("in:"))
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(count);
+        if matches!(line[pos + 5..].chars().next(), Some('I') | Some('i')) {
+            frames.push(frame);
+        }
+        count += 1;
+    }
+    Keyframes::new(frames)
+}
+
+// `123` or qpfile style `123 I -1`
+fn parse_list(input: &str) -> Result<Keyframes, KeyframesError> {
+    let mut frames = Vec::new();
+    for (n, line) in input.lines().enumerate() {
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+        if first.starts_with('#') {
+            continue;
+        }
+        let frame = first.parse::<i64>().map_err(|_| invalid(n, line))?;
+        match fields.next() {
+            None | Some("I") | Some("K") => frames.push(frame),
+            Some(_) => (),
+        }
+    }
+    Ok(Keyframes::new(frames))
+}
+
+/// Timing post-processing in the spirit of Aegisub's Timing Post-Processor:
+/// add lead-in and lead-out, close small gaps between consecutive lines, then
+/// snap starts and ends to nearby keyframes.
+///
+/// Keyframe distances are in frames: a line starting up to `starts_before`
+/// frames before a keyframe or `starts_after` frames after one is moved onto
+/// it, and likewise for ends.
+#[derive(Clone, Debug)]
+pub struct PostProcessor {
+    pub selection: Selection,
+    pub lead_in: Duration,
+    pub lead_out: Duration,
+    /// Stops lead-in and lead-out from extending a line across a keyframe.
+    pub keep_within_keyframes: bool,
+    /// Lines separated by a gap or overlap up to this long are made
+    /// continuous.
+    pub adjacent_threshold: Option<Duration>,
+    /// Where in the gap adjacent lines meet, from 0 (the earlier line's end)
+    /// to 1 (the later line's start).
+    pub adjacent_bias: f64,
+    pub starts_before: i64,
+    pub starts_after: i64,
+    pub ends_before: i64,
+    pub ends_after: i64,
+}
+
+impl Default for PostProcessor {
+    fn default() -> PostProcessor {
+        PostProcessor {
+            selection: Selection::default(),
+            lead_in: Duration::ZERO,
+            lead_out: Duration::ZERO,
+            keep_within_keyframes: false,
+            adjacent_threshold: None,
+            adjacent_bias: 0.5,
+            starts_before: 5,
+            starts_after: 4,
+            ends_before: 5,
+            ends_after: 4,
+        }
+    }
+}
+
+/// A line whose timing was changed by post-processing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub index: usize,
+    pub start: (Duration, Duration),
+    pub end: (Duration, Duration),
+}
+
+impl PostProcessor {
+    /// Processes `entries` in place, returning the lines that changed. Comments
+    /// and lines without times are left alone. Keyframe rules only apply when
+    /// `keyframes` are given.
+    pub fn process(
+        &self,
+        entries: &mut [Entry],
+        timecodes: &Timecodes,
+        keyframes: Option<&Keyframes>,
+    ) -> Vec<Change> {
+        let mut lines: Vec<usize> = (0..entries.len())
+            .filter(|&n| {
+                let e = &entries[n];
+                e.start.is_some()
+                    && e.end.is_some()
+                    && e.kind.as_deref() != Some("Comment")
+                    && self.selection.matches(e)
+            })
+            .collect();
+        lines.sort_by_key(|&n| entries[n].start);
+
+        let before: Vec<(Duration, Duration)> = lines.iter().map(|&n| times(&entries[n])).collect();
+
+        for &n in &lines {
+            self.lead(&mut entries[n], timecodes, keyframes);
+        }
+        if let Some(threshold) = self.adjacent_threshold {
+            for pair in lines.windows(2) {
+                self.join(entries, pair[0], pair[1], threshold);
+            }
+        }
+        if let Some(keyframes) = keyframes {
+            for &n in &lines {
+                self.snap(&mut entries[n], timecodes, keyframes);
+            }
+        }
+
+        lines
+            .iter()
+            .zip(before)
+            .filter_map(|(&index, (start, end))| {
+                let (new_start, new_end) = times(&entries[index]);
+                (start != new_start || end != new_end).then_some(Change {
+                    index,
+                    start: (start, new_start),
+                    end: (end, new_end),
+                })
+            })
+            .collect()
+    }
+
+    fn lead(&self, entry: &mut Entry, timecodes: &Timecodes, keyframes: Option<&Keyframes>) {
+        let (start, end) = times(entry);
+        let mut new_start = start.saturating_sub(self.lead_in);
+        let mut new_end = end + self.lead_out;
+
+        if let (true, Some(keyframes)) = (self.keep_within_keyframes, keyframes) {
+            let first = timecodes.frame_at(start, TimeKind::Start);
+            if let Some(k) = keyframes.previous(first) {
+                new_start = new_start.max(timecodes.time_at(k, TimeKind::Start).min(start));
+            }
+            let last = timecodes.frame_at(end, TimeKind::End);
+            if let Some(k) = keyframes.next(last) {
+                new_end = new_end.min(timecodes.time_at(k - 1, TimeKind::End).max(end));
+            }
+        }
+
+        entry.start = Some(new_start);
+        entry.end = Some(new_end);
+    }
+
+    fn join(&self, entries: &mut [Entry], cur: usize, next: usize, threshold: Duration) {
+        let (_, end) = times(&entries[cur]);
+        let (start, next_end) = times(&entries[next]);
+        let (lo, hi) = if end <= start {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        if hi - lo > threshold || end == start {
+            return;
+        }
+
+        let point = lo + (hi - lo).mul_f64(self.adjacent_bias.clamp(0.0, 1.0));
+        if point <= entries[cur].start.unwrap_or_default() || point >= next_end {
+            return;
+        }
+        entries[cur].end = Some(point);
+        entries[next].start = Some(point);
+    }
+
+    fn snap(&self, entry: &mut Entry, timecodes: &Timecodes, keyframes: &Keyframes) {
+        let (start, end) = times(entry);
+        let first = timecodes.frame_at(start, TimeKind::Start);
+        // the frame the line disappears on
+        let gone = timecodes.frame_at(end, TimeKind::End) + 1;
+
+        let new_first = keyframes
+            .closest(first, first - self.starts_after, first + self.starts_before)
+            .unwrap_or(first);
+        let new_gone = keyframes
+            .closest(gone, gone - self.ends_after, gone + self.ends_before)
+            .unwrap_or(gone);
+        if new_gone <= new_first {
+            return;
+        }
+
+        if new_first != first {
+            entry.start = Some(timecodes.time_at(new_first, TimeKind::Start));
+        }
+        if new_gone != gone {
+            entry.end = Some(timecodes.time_at(new_gone - 1, TimeKind::End));
+        }
+    }
+}
+
+fn times(entry: &Entry) -> (Duration, Duration) {
+    (
+        entry.start.unwrap_or_default(),
+        entry.end.unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        for (input, frames, fps) in [
+            (
+                "# keyframe format v1\nfps 23.976\n\n0\n50\n20\n20\n",
+                &[0, 20, 50][..],
+                Some(23.976),
+            ),
+            ("\u{feff}# keyframe format v1\nfps 0\n5\n", &[5], None),
+            (
+                "# XviD 2pass stat file\n# a comment\ni 1 2\np 3\nb 4\n\ni 5\n",
+                &[0, 3],
+                None,
+            ),
+            (
+                "# ffmpeg 2-pass log file, using xvid codec\np\ni\n",
+                &[1],
+                None,
+            ),
+            (
+                "#options: 1920x1080 fps=24000/1001\n\
+                 in:0 out:0 type:I dur:2\n\
+                 in:2 out:1 type:P dur:2\n\
+                 in:1 out:2 type:b dur:2\n\
+                 in:3 out:3 type:i dur:2\n",
+                &[0, 3],
+                None,
+            ),
+            (
+                "0\r\n24 I -1\r\n30 P -1\r\n48 K\r\n# done\r\n",
+                &[0, 24, 48],
+                None,
+            ),
+            ("# frames\n10\n5\n", &[5, 10], None),
+        ] {
+            let keyframes = Keyframes::parse(input).unwrap();
+            assert_eq!(keyframes.frames(), frames, "{:?}", input);
+            assert_eq!(keyframes.fps(), fps, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let line = |line, content: &str| KeyframesError::InvalidLine {
+            line,
+            content: content.to_owned(),
+        };
+        for (input, error) in [
+            ("", KeyframesError::UnknownFormat),
+            ("keyframes\n1\n", KeyframesError::UnknownFormat),
+            ("# keyframe format v1\nfps 25\nabc\n", line(3, "abc")),
+            ("1\nx 2\n", line(2, "x 2")),
+        ] {
+            assert_eq!(Keyframes::parse(input), Err(error), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn neighbours() {
+        let keyframes = Keyframes::new(vec![30, 10, 20]);
+        for (frame, is_keyframe, previous, next) in [
+            (0, false, None, Some(10)),
+            (10, true, Some(10), Some(20)),
+            (15, false, Some(10), Some(20)),
+            (30, true, Some(30), None),
+            (40, false, Some(30), None),
+        ] {
+            assert_eq!(keyframes.is_keyframe(frame), is_keyframe, "{}", frame);
+            assert_eq!(keyframes.previous(frame), previous, "{}", frame);
+            assert_eq!(keyframes.next(frame), next, "{}", frame);
+        }
+        assert_eq!(keyframes.closest(15, 10, 20), Some(10));
+        assert_eq!(keyframes.closest(16, 10, 20), Some(20));
+        assert_eq!(keyframes.closest(16, 11, 19), None);
+    }
+
+    fn event(start: u64, end: u64) -> Entry {
+        Entry {
+            kind: Some("Dialogue".to_owned()),
+            start: Some(Duration::from_millis(start)),
+            end: Some(Duration::from_millis(end)),
+            ..Entry::default()
+        }
+    }
+
+    fn ms(entry: &Entry) -> (u128, u128) {
+        (
+            entry.start.unwrap().as_millis(),
+            entry.end.unwrap().as_millis(),
+        )
+    }
+
+    // at 25 fps frame n starts at 40n ms, lines starting on it at 40n - 20
+    // and lines ending on it at 40n + 20
+    #[test]
+    fn post_processing() {
+        let timecodes = Timecodes::cfr(25.0).unwrap();
+        let lead = PostProcessor {
+            lead_in: Duration::from_millis(200),
+            lead_out: Duration::from_millis(300),
+            ..PostProcessor::default()
+        };
+        let within = PostProcessor {
+            keep_within_keyframes: true,
+            ..lead.clone()
+        };
+        let join = |bias| PostProcessor {
+            adjacent_threshold: Some(Duration::from_millis(300)),
+            adjacent_bias: bias,
+            ..PostProcessor::default()
+        };
+        let snap = PostProcessor::default();
+
+        // processor, keyframes, lines before and after
+        for (processor, keyframes, before, after) in [
+            (&lead, None, vec![(1000, 2000)], vec![(800, 2300)]),
+            // snapped to keyframe 23 after the lead-in
+            (
+                &lead,
+                Some(vec![23, 52]),
+                vec![(1000, 2000)],
+                vec![(900, 2300)],
+            ),
+            (
+                &within,
+                Some(vec![23, 52]),
+                vec![(1000, 2000)],
+                vec![(900, 2060)],
+            ),
+            (&within, None, vec![(100, 2000)], vec![(0, 2300)]),
+            (
+                &join(0.5),
+                None,
+                vec![(0, 1000), (1200, 2000)],
+                vec![(0, 1100), (1100, 2000)],
+            ),
+            (
+                &join(0.5),
+                None,
+                vec![(0, 1000), (900, 2000)],
+                vec![(0, 950), (950, 2000)],
+            ),
+            (
+                &join(0.0),
+                None,
+                vec![(0, 1000), (1200, 2000)],
+                vec![(0, 1000), (1000, 2000)],
+            ),
+            (
+                &join(0.5),
+                None,
+                vec![(0, 1000), (1400, 2000)],
+                vec![(0, 1000), (1400, 2000)],
+            ),
+            // starts 3 frames before a keyframe, disappears 2 before one
+            (
+                &snap,
+                Some(vec![30, 52]),
+                vec![(1060, 2000)],
+                vec![(1180, 2060)],
+            ),
+            // too far from either
+            (
+                &snap,
+                Some(vec![40, 60]),
+                vec![(1060, 2000)],
+                vec![(1060, 2000)],
+            ),
+            // keyframes behind: starts 4 frames after one
+            (
+                &snap,
+                Some(vec![23, 46]),
+                vec![(1060, 2000)],
+                vec![(900, 1820)],
+            ),
+        ] {
+            let mut entries: Vec<Entry> = before.iter().map(|&(s, e)| event(s, e)).collect();
+            let keyframes = keyframes.map(Keyframes::new);
+            let changes = processor.process(&mut entries, &timecodes, keyframes.as_ref());
+            let found: Vec<(u128, u128)> = entries.iter().map(ms).collect();
+            assert_eq!(found, after, "{:?}", before);
+            let changed = before
+                .iter()
+                .zip(&after)
+                .filter(|(b, a)| (b.0 as u128, b.1 as u128) != **a)
+                .count();
+            assert_eq!(changes.len(), changed, "{:?}", before);
+        }
+    }
+
+    #[test]
+    fn post_processing_skips_comments_and_reports_changes() {
+        let timecodes = Timecodes::cfr(25.0).unwrap();
+        let mut entries = vec![event(2000, 3000), event(1000, 2000)];
+        entries[0].kind = Some("Comment".to_owned());
+        let processor = PostProcessor {
+            lead_in: Duration::from_millis(100),
+            ..PostProcessor::default()
+        };
+        let changes = processor.process(&mut entries, &timecodes, None);
+        assert_eq!(
+            changes,
+            [Change {
+                index: 1,
+                start: (Duration::from_millis(1000), Duration::from_millis(900)),
+                end: (Duration::from_millis(2000), Duration::from_millis(2000)),
+            }]
+        );
+        assert_eq!(ms(&entries[0]), (2000, 3000));
+    }
+}
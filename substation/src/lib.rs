@@ -1,8 +1,31 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
+pub mod attachments;
+pub mod borrowed;
+pub mod encoding;
+pub mod fonts;
+pub mod format;
+pub mod keyframes;
+pub mod lint;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod parser;
+pub mod reading;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod srt;
+pub mod stream;
+pub mod timecodes;
+pub mod timestamp;
+pub mod timing;
+pub mod writer;
 
+#[cfg(feature = "schema")]
+pub use serialize::json_schema;
+pub use timestamp::Timestamp;
+
+/// With the `serde` feature, written as a `#RRGGBB` or `#RRGGBBAA` string.
 #[derive(Clone, Debug)]
 pub struct Color {
     pub alpha: Option<u8>,
@@ -11,11 +34,16 @@ pub struct Color {
     pub blue: u8,
 }
 
+/// A style line. `S` is the string type, [`String`] unless the style
+/// borrows from the script it was parsed from; see [`borrowed`].
 #[derive(Default, Clone, Debug)]
-pub struct Style {
-    pub name: String,
-    pub fontname: String,
-    pub fontsize: usize,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "Style"))]
+pub struct Style<S = String> {
+    pub name: S,
+    pub fontname: S,
+    pub fontsize: f64,
     pub primary_color: Option<Color>,
     pub secondary_color: Option<Color>,
     pub outline_color: Option<Color>,
@@ -38,36 +66,136 @@ pub struct Style {
     pub encoding: Option<usize>,
 }
 
+/// An event line, with `S` as for [`Style`]. With the `serde` feature,
+/// `start` and `end` are written as whole milliseconds.
 #[derive(Default, Clone, Debug)]
-pub struct Entry {
-    pub kind: Option<String>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "Entry"))]
+pub struct Entry<S = String> {
+    pub kind: Option<S>,
     pub layer: Option<isize>,
+    #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis::option"))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
     pub start: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis::option"))]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
     pub end: Option<Duration>,
-    pub style: Option<String>,
-    pub name: Option<String>,
+    pub style: Option<S>,
+    pub name: Option<S>,
     pub margin_l: Option<usize>,
     pub margin_r: Option<usize>,
     pub margin_v: Option<usize>,
-    pub effect: Option<String>,
+    pub effect: Option<S>,
     pub read_order: Option<isize>,
-    pub text: String,
+    pub text: S,
 }
 
-impl Entry {
+impl<S: AsRef<str>> Entry<S> {
     pub fn parsed_text(&self) -> Vec<TextSection> {
-        parser::text_line(&self.text).unwrap().1
+        parser::text_line(self.text.as_ref()).unwrap().1
+    }
+}
+
+impl<S> Entry<S> {
+    /// The left, right and vertical margins the event is drawn with: its
+    /// own where they are set and non-zero, its style's otherwise.
+    pub fn margins(&self, style: Option<&Style<S>>) -> (f64, f64, f64) {
+        let margin = |event: Option<usize>, style: Option<usize>| {
+            event.filter(|&m| m > 0).or(style).unwrap_or(0) as f64
+        };
+        (
+            margin(self.margin_l, style.and_then(|s| s.margin_l)),
+            margin(self.margin_r, style.and_then(|s| s.margin_r)),
+            margin(self.margin_v, style.and_then(|s| s.margin_v)),
+        )
+    }
+}
+
+/// A whole script: `[Script Info]`, styles, events and any other sections
+/// as they were parsed.
+#[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Script {
+    pub info: HashMap<String, String>,
+    pub styles: HashMap<String, Style>,
+    pub events: Vec<Entry>,
+    pub other: Vec<Section>,
+}
+
+impl Script {
+    /// The script resolution, following libass when only one side is given.
+    pub fn play_res(&self) -> (f64, f64) {
+        let get = |key: &str| {
+            self.info
+                .get(key)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|v| *v > 0.0)
+        };
+        match (get("PlayResX"), get("PlayResY")) {
+            (Some(x), Some(y)) => (x, y),
+            (Some(x), None) if x == 1280.0 => (x, 1024.0),
+            (Some(x), None) => (x, x * 3.0 / 4.0),
+            (None, Some(y)) if y == 1024.0 => (1280.0, y),
+            (None, Some(y)) => (y * 4.0 / 3.0, y),
+            (None, None) => (384.0, 288.0),
+        }
     }
 }
 
+/// The column (0 left, 1 center, 2 right) and row (0 bottom, 1 middle,
+/// 2 top) of a numpad alignment. Anything outside 1 to 9, such as `\an0`,
+/// is read as 2, bottom center.
+pub fn alignment_cell(alignment: u8) -> (u8, u8) {
+    let alignment = Some(alignment).filter(|a| (1..=9).contains(a)).unwrap_or(2);
+    ((alignment - 1) % 3, (alignment - 1) / 3)
+}
+
+/// Escapes plain text so it shows as written in an event: `{` and `}`
+/// become `\{` and `\}`, and a zero-width joiner goes between `\` and an
+/// `n`, `N` or `h` after it so they aren't read as line breaks or spaces.
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' => escaped.push('\\'),
+            '\\' if matches!(chars.peek(), Some('n' | 'N' | 'h')) => {
+                escaped.push_str("\\\u{200d}");
+                continue;
+            }
+            _ => (),
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Undoes [`escape_text`] on text that has had its `\N`, `\n` and `\h`
+/// replaced.
+pub fn unescape_text(text: &str) -> String {
+    text.replace("\\\u{200d}", "\\")
+        .replace("\\{", "{")
+        .replace("\\}", "}")
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Section {
     EventsHeader(Vec<String>),
     Other {
         name: String,
         settings: HashMap<String, String>,
     },
+    /// `[V4+ Styles]`, or SSA's `[V4 Styles]` read as ASS.
     Styles(HashMap<String, Style>),
+    /// `[Fonts]` or `[Graphics]`, with the files decoded.
+    Attachments {
+        name: String,
+        files: Vec<attachments::Attachment>,
+    },
 }
 
 impl Section {
@@ -80,14 +208,22 @@ impl Section {
 }
 
 #[derive(Clone, Debug)]
-pub enum TextSection {
-    Text(String),
-    StyleOverride(Vec<StyleOverride>),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "TextSection"))]
+pub enum TextSection<S = String> {
+    Text(S),
+    StyleOverride(Vec<StyleOverride<S>>),
     Drawing(Vec<DrawingCommand>),
 }
 
+/// An override tag. With the `serde` feature, its times are written as
+/// whole milliseconds.
 #[derive(Clone, Debug)]
-pub enum StyleOverride {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(rename = "StyleOverride"))]
+pub enum StyleOverride<S = String> {
     Bold(f64),
     Italic(bool),
     Underline(bool),
@@ -95,7 +231,7 @@ pub enum StyleOverride {
     Border(f64),
     Shadow(f64),
     BlurEdges(bool),
-    FontName(String),
+    FontName(S),
     FontSize(f64),
     ScaleX(f64),
     ScaleY(f64),
@@ -108,23 +244,35 @@ pub enum StyleOverride {
     Alpha(u64, u8),    // same color index
     Alignment(f64),
     NumpadLayoutAlignment(f64),
-    KaraokeDuration(Duration),
+    KaraokeDuration(
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+        Duration,
+    ),
     WrappingStyle(f64),
-    Reset(Option<String>),
+    Reset(Option<S>),
     DrawingMode(f64),
     BaselineOffset(f64),
     Transition {
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis::option"))]
+        #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
         start: Option<Duration>,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis::option"))]
+        #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
         end: Option<Duration>,
         acceleration: Option<f64>,
-        styles: Vec<StyleOverride>,
+        styles: Vec<StyleOverride<S>>,
     },
     Move {
         start_x: f64,
         start_y: f64,
         end_x: f64,
         end_y: f64,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis::option"))]
+        #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
         start: Option<Duration>,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis::option"))]
+        #[cfg_attr(feature = "schema", schemars(with = "Option<u64>"))]
         end: Option<Duration>,
     },
     Origin {
@@ -135,13 +283,25 @@ pub enum StyleOverride {
         starting_alpha: u8,
         middle_alpha: u8,
         ending_alpha: u8,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
         start_time: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
         in_between_time: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
         late_time: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
         ending_time: Duration,
     },
     FadeInAndOut {
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
         fade_in_for: Duration,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
         fade_out_for: Duration,
     },
     Clip {
@@ -152,10 +312,12 @@ pub enum StyleOverride {
     },
     ClipToDrawing(Option<f64>, Vec<DrawingCommand>),
     EmptyClip,
-    Other(String),
+    Other(S),
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum DrawingCommand {
     Move {
         x: f64,
@@ -184,3 +346,56 @@ pub enum DrawingCommand {
     },
     CloseBspline,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping() {
+        for (text, escaped) in [
+            ("plain", "plain"),
+            ("{a}", "\\{a\\}"),
+            ("\\n \\N \\h", "\\\u{200d}n \\\u{200d}N \\\u{200d}h"),
+            ("\\b1 \\", "\\b1 \\"),
+            ("\\{", "\\\\{"),
+        ] {
+            assert_eq!(escape_text(text), escaped, "{:?}", text);
+            assert_eq!(unescape_text(escaped), text, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn alignment_cells() {
+        for (alignment, cell) in [
+            (1, (0, 0)),
+            (2, (1, 0)),
+            (3, (2, 0)),
+            (5, (1, 1)),
+            (7, (0, 2)),
+            (9, (2, 2)),
+            (0, (1, 0)),
+            (10, (1, 0)),
+            (255, (1, 0)),
+        ] {
+            assert_eq!(alignment_cell(alignment), cell, "{}", alignment);
+        }
+    }
+
+    #[test]
+    fn margins() {
+        let style: Style = Style {
+            margin_l: Some(10),
+            margin_r: Some(20),
+            margin_v: Some(30),
+            ..Style::default()
+        };
+        let entry: Entry = Entry {
+            margin_l: Some(0),
+            margin_r: Some(5),
+            ..Entry::default()
+        };
+        assert_eq!(entry.margins(Some(&style)), (10.0, 5.0, 30.0));
+        assert_eq!(entry.margins(None), (0.0, 5.0, 0.0));
+    }
+}
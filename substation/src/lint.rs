@@ -0,0 +1,966 @@
+//! Quality checks for scripts: a set of named rules, each reported at a
+//! severity that a [`Config`] can change or turn off.
+
+use crate::parser::borrowed_text_line;
+use crate::{Script, Section, Style, StyleOverride, TextSection};
+use nom::{
+    bytes::complete::take_while1,
+    character::complete::{alpha1, char, space0},
+    combinator::{all_consuming, opt, rest},
+    sequence::{preceded, tuple},
+    IResult,
+};
+use parsing_utils::ws;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Rule {
+    /// The event's style isn't in `[V4+ Styles]`.
+    UndefinedStyle,
+    /// A `\fn` font has no matching file in `[Fonts]`.
+    FontNotAttached,
+    MissingTime,
+    EndBeforeStart,
+    ZeroLength,
+    /// Dialogue on the same style and layer shown at the same time.
+    Overlap,
+    /// A `{` without a `}` after it, shown as text.
+    UnclosedBrace,
+    /// An override tag libass doesn't know, or a `\pos`, `\move`, `\org`,
+    /// `\fad` or `\fade` with the wrong arguments.
+    UnknownTag,
+    /// A tag set again later in the same block, or set to the value already
+    /// in effect.
+    RedundantOverride,
+    /// A `\pos` or `\move` point outside the script resolution.
+    PositionOutside,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 10] = [
+        Rule::UndefinedStyle,
+        Rule::FontNotAttached,
+        Rule::MissingTime,
+        Rule::EndBeforeStart,
+        Rule::ZeroLength,
+        Rule::Overlap,
+        Rule::UnclosedBrace,
+        Rule::UnknownTag,
+        Rule::RedundantOverride,
+        Rule::PositionOutside,
+    ];
+
+    /// The name used in config files and reports, such as `zero-length`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UndefinedStyle => "undefined-style",
+            Rule::FontNotAttached => "font-not-attached",
+            Rule::MissingTime => "missing-time",
+            Rule::EndBeforeStart => "end-before-start",
+            Rule::ZeroLength => "zero-length",
+            Rule::Overlap => "overlap",
+            Rule::UnclosedBrace => "unclosed-brace",
+            Rule::UnknownTag => "unknown-tag",
+            Rule::RedundantOverride => "redundant-override",
+            Rule::PositionOutside => "position-outside",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|r| r.name() == name)
+    }
+
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::UndefinedStyle
+            | Rule::MissingTime
+            | Rule::EndBeforeStart
+            | Rule::UnclosedBrace => Severity::Error,
+            Rule::FontNotAttached
+            | Rule::ZeroLength
+            | Rule::Overlap
+            | Rule::UnknownTag
+            | Rule::PositionOutside => Severity::Warning,
+            Rule::RedundantOverride => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    UnknownRule { line: usize, name: String },
+    UnknownSeverity { line: usize, value: String },
+    InvalidLine { line: usize, content: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnknownRule { line, name } => {
+                write!(f, "unknown rule on line {}: {:?}", line, name)
+            }
+            ConfigError::UnknownSeverity { line, value } => {
+                write!(f, "unknown severity on line {}: {:?}", line, value)
+            }
+            ConfigError::InvalidLine { line, content } => {
+                write!(f, "invalid setting on line {}: {:?}", line, content)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Which rules run and how severe their problems are. The default runs
+/// every rule at its [`Rule::default_severity`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    severities: HashMap<Rule, Option<Severity>>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            severities: Rule::ALL
+                .into_iter()
+                .map(|r| (r, Some(r.default_severity())))
+                .collect(),
+        }
+    }
+}
+
+// `rule = severity`, with an optional `# comment`
+fn setting(input: &str) -> IResult<&str, (&str, &str)> {
+    let (input, (name, _, value, _)) = tuple((
+        preceded(
+            space0,
+            take_while1(|c: char| c.is_ascii_alphanumeric() || c == '-'),
+        ),
+        ws(char('=')),
+        alpha1,
+        tuple((space0, opt(preceded(char('#'), rest)))),
+    ))(input)?;
+    Ok((input, (name, value)))
+}
+
+impl Config {
+    /// Reads settings on top of the defaults, one `rule = severity` per
+    /// line, where the severity is `error`, `warning`, `info` or `off`.
+    /// `all` sets every rule, so `all = off` followed by a few rules runs
+    /// just those. Lines starting with `#` are comments.
+    ///
+    /// ```text
+    /// all = off
+    /// overlap = error
+    /// zero-length = warning  # blank lines are fine too
+    /// ```
+    pub fn parse(input: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        for (n, line) in input.trim_start_matches('\u{feff}').lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (_, (name, value)) =
+                all_consuming(setting)(line.trim_end()).map_err(|_| ConfigError::InvalidLine {
+                    line: n + 1,
+                    content: line.to_owned(),
+                })?;
+            let severity = match value.to_ascii_lowercase().as_str() {
+                "off" => None,
+                "info" => Some(Severity::Info),
+                "warning" => Some(Severity::Warning),
+                "error" => Some(Severity::Error),
+                _ => {
+                    return Err(ConfigError::UnknownSeverity {
+                        line: n + 1,
+                        value: value.to_owned(),
+                    })
+                }
+            };
+            if name == "all" {
+                for rule in Rule::ALL {
+                    config.set(rule, severity);
+                }
+            } else {
+                let rule = Rule::from_name(name).ok_or_else(|| ConfigError::UnknownRule {
+                    line: n + 1,
+                    name: name.to_owned(),
+                })?;
+                config.set(rule, severity);
+            }
+        }
+        Ok(config)
+    }
+
+    /// The severity `rule` is reported at, `None` when it is off.
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.severities.get(&rule).copied().flatten()
+    }
+
+    pub fn set(&mut self, rule: Rule, severity: Option<Severity>) {
+        self.severities.insert(rule, severity);
+    }
+}
+
+/// Something a rule found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Problem {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// The index of the event in [`Script::events`], if it is about one.
+    pub event: Option<usize>,
+    pub message: String,
+}
+
+struct Problems<'a> {
+    config: &'a Config,
+    found: Vec<Problem>,
+}
+
+impl Problems<'_> {
+    fn add(&mut self, rule: Rule, event: usize, message: String) {
+        if let Some(severity) = self.config.severity(rule) {
+            self.found.push(Problem {
+                rule,
+                severity,
+                event: Some(event),
+                message,
+            });
+        }
+    }
+}
+
+// where the last `brace` not escaped with a `\` is
+fn last_brace(text: &str, brace: char) -> Option<usize> {
+    text.rmatch_indices(brace)
+        .map(|(n, _)| n)
+        .find(|&n| !text[..n].ends_with('\\'))
+}
+
+/// Runs the rules `config` enables over the events of `script`, returning
+/// problems in event order. Comments are skipped.
+pub fn lint(script: &Script, config: &Config) -> Vec<Problem> {
+    let mut problems = Problems {
+        config,
+        found: Vec::new(),
+    };
+    let fonts = attached_fonts(script);
+    let mut missing_fonts: Vec<String> = Vec::new();
+    let (width, height) = script.play_res();
+
+    for (n, event) in script.events.iter().enumerate() {
+        if event.kind.as_deref() == Some("Comment") {
+            continue;
+        }
+
+        let style = match event.style.as_deref() {
+            Some(name) => {
+                let style = find_style(script, name);
+                if style.is_none() {
+                    problems.add(
+                        Rule::UndefinedStyle,
+                        n,
+                        format!("style {:?} isn't defined", name),
+                    );
+                }
+                style
+            }
+            None => find_style(script, "Default"),
+        };
+
+        match (event.start, event.end) {
+            (Some(start), Some(end)) if end < start => {
+                problems.add(Rule::EndBeforeStart, n, "ends before it starts".to_owned())
+            }
+            (Some(start), Some(end)) if end == start => {
+                problems.add(Rule::ZeroLength, n, "lasts no time".to_owned())
+            }
+            (None, _) | (_, None) => {
+                problems.add(Rule::MissingTime, n, "has no start or end time".to_owned())
+            }
+            _ => (),
+        }
+
+        if last_brace(&event.text, '{') > last_brace(&event.text, '}') {
+            problems.add(
+                Rule::UnclosedBrace,
+                n,
+                "override block is never closed".to_owned(),
+            );
+        }
+
+        let sections = borrowed_text_line(&event.text)
+            .map(|(_, s)| s)
+            .unwrap_or_default();
+        let mut state = State::new(style);
+        for section in &sections {
+            let TextSection::StyleOverride(overrides) = section else {
+                continue;
+            };
+            for (o, message) in redundant(overrides, &mut state, script, style) {
+                problems.add(
+                    Rule::RedundantOverride,
+                    n,
+                    format!("{} {}", tag_name(o), message),
+                );
+            }
+            for o in overrides {
+                check_tag(o, n, &mut problems, &event.text);
+                match o {
+                    StyleOverride::FontName(name) if !name.trim().is_empty() => {
+                        let name = name.trim();
+                        if !is_attached(&fonts, name) && !missing_fonts.iter().any(|f| f == name) {
+                            missing_fonts.push(name.to_owned());
+                            problems.add(
+                                Rule::FontNotAttached,
+                                n,
+                                format!("font {:?} isn't attached", name),
+                            );
+                        }
+                    }
+                    StyleOverride::Move {
+                        start_x,
+                        start_y,
+                        end_x,
+                        end_y,
+                        ..
+                    } => {
+                        let outside = |x: f64, y: f64| {
+                            !(0.0..=width).contains(&x) || !(0.0..=height).contains(&y)
+                        };
+                        if outside(*start_x, *start_y) || outside(*end_x, *end_y) {
+                            problems.add(
+                                Rule::PositionOutside,
+                                n,
+                                format!(
+                                    "{} is outside the {}x{} script resolution",
+                                    tag_name(o),
+                                    width,
+                                    height
+                                ),
+                            );
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    overlaps(script, &mut problems);
+    let mut found = problems.found;
+    found.sort_by_key(|p| p.event);
+    found
+}
+
+/// Looks a style up the way libass does, ignoring a leading `*`.
+fn find_style<'a>(script: &'a Script, name: &str) -> Option<&'a Style> {
+    script
+        .styles
+        .get(name)
+        .or_else(|| script.styles.get(name.trim().trim_start_matches('*')))
+}
+
+fn overlaps(script: &Script, problems: &mut Problems) {
+    let mut lines: Vec<(usize, Duration, Duration)> = script
+        .events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.kind.as_deref() != Some("Comment"))
+        .filter_map(|(n, e)| Some((n, e.start?, e.end?)))
+        .filter(|(_, start, end)| start < end)
+        .collect();
+    let key = |n: usize| {
+        let e = &script.events[n];
+        (
+            e.style.as_deref().unwrap_or("Default"),
+            e.layer.unwrap_or(0),
+        )
+    };
+    lines.sort_by(|a, b| key(a.0).cmp(&key(b.0)).then(a.1.cmp(&b.1)));
+
+    // the line that ends last among the earlier ones in the same group
+    let mut last: Option<(usize, Duration)> = None;
+    for &(n, start, end) in &lines {
+        match last {
+            Some((m, last_end)) if key(m) == key(n) => {
+                if start < last_end {
+                    problems.add(Rule::Overlap, n, format!("overlaps event {}", m + 1));
+                }
+                if end > last_end {
+                    last = Some((n, end));
+                }
+            }
+            _ => last = Some((n, end)),
+        }
+    }
+}
+
+// every tag libass reads, including ones the parser keeps as `Other` and
+// ones that end up there when their arguments are malformed
+const TAGS: [&str; 53] = [
+    "1a", "1c", "2a", "2c", "3a", "3c", "4a", "4c", "K", "a", "alpha", "an", "b", "be", "blur",
+    "bord", "c", "clip", "fad", "fade", "fax", "fay", "fe", "fn", "fr", "frx", "fry", "frz", "fs",
+    "fsc", "fscx", "fscy", "fsp", "i", "iclip", "k", "kf", "ko", "move", "org", "p", "pbo", "pos",
+    "q", "r", "s", "shad", "t", "u", "xbord", "xshad", "ybord", "yshad",
+];
+
+// tags the parser reads the arguments of, that it keeps as text otherwise
+const FUNCTIONS: [&str; 5] = ["fad", "fade", "move", "org", "pos"];
+
+fn check_tag(o: &StyleOverride<Cow<str>>, event: usize, problems: &mut Problems, text: &str) {
+    match o {
+        StyleOverride::Transition { styles, .. } => {
+            for o in styles {
+                check_tag(o, event, problems, text);
+            }
+        }
+        // text in a block that isn't after a `\` is a comment
+        StyleOverride::Other(tag) if text.contains(&format!("\\{}", tag)) => {
+            let digits = tag.chars().take_while(|c| c.is_ascii_digit()).count();
+            let letters = tag[digits..]
+                .chars()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .count();
+            let name = &tag[..digits + letters];
+            if !TAGS.contains(&name) {
+                problems.add(
+                    Rule::UnknownTag,
+                    event,
+                    format!("unknown override tag \\{}", tag.trim()),
+                );
+            } else if FUNCTIONS.contains(&name) {
+                // only left as text when its arguments don't parse
+                problems.add(
+                    Rule::UnknownTag,
+                    event,
+                    format!("malformed override tag \\{}", tag.trim()),
+                );
+            }
+        }
+        _ => (),
+    }
+}
+
+fn tag_name(o: &StyleOverride<Cow<str>>) -> String {
+    use StyleOverride::*;
+    let name = match o {
+        Bold(_) => "b",
+        Italic(_) => "i",
+        Underline(_) => "u",
+        StrikeOut(_) => "s",
+        Border(_) => "bord",
+        Shadow(_) => "shad",
+        BlurEdges(_) => "be",
+        FontName(_) => "fn",
+        FontSize(_) => "fs",
+        ScaleX(_) => "fscx",
+        ScaleY(_) => "fscy",
+        LetterSpacing(_) => "fsp",
+        RotationX(_) => "frx",
+        RotationY(_) => "fry",
+        RotationZ(_) => "frz",
+        Charset(_) => "fe",
+        Color(n, _) => return format!("\\{}c", n),
+        Alpha(n, _) => return format!("\\{}a", n),
+        Alignment(_) => "a",
+        NumpadLayoutAlignment(_) => "an",
+        KaraokeDuration(_) => "k",
+        WrappingStyle(_) => "q",
+        Reset(_) => "r",
+        DrawingMode(_) => "p",
+        BaselineOffset(_) => "pbo",
+        Transition { .. } => "t",
+        Move {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            start: Some(start),
+            end: Some(end),
+        } if start_x == end_x && start_y == end_y && start.is_zero() && end.is_zero() => "pos",
+        Move { .. } => "move",
+        Origin { .. } => "org",
+        Fade { .. } => "fade",
+        FadeInAndOut { .. } => "fad",
+        Clip { .. } | ClipToDrawing(..) | EmptyClip => "clip",
+        Other(tag) => return format!("\\{}", tag.trim()),
+    };
+    format!("\\{}", name)
+}
+
+/// What a tag sets, for finding one set twice in a block. Tags that take
+/// turns setting the same thing share a slot.
+fn slot(o: &StyleOverride<Cow<str>>) -> Option<String> {
+    use StyleOverride::*;
+    match o {
+        Transition { .. } | KaraokeDuration(_) | Reset(_) | Other(_) => None,
+        Alignment(_) | NumpadLayoutAlignment(_) => Some("\\an".to_owned()),
+        Move { .. } => Some("\\move".to_owned()),
+        Fade { .. } | FadeInAndOut { .. } => Some("\\fade".to_owned()),
+        o => Some(tag_name(o)),
+    }
+}
+
+/// The style values a line is drawn with so far.
+struct State {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikeout: bool,
+    fontname: Option<String>,
+    fontsize: Option<f64>,
+}
+
+impl State {
+    fn new(style: Option<&Style>) -> State {
+        State {
+            bold: style.and_then(|s| s.bold).unwrap_or(false),
+            italic: style.and_then(|s| s.italic).unwrap_or(false),
+            underline: style.and_then(|s| s.underline).unwrap_or(false),
+            strikeout: style.and_then(|s| s.strikeout).unwrap_or(false),
+            fontname: style.map(|s| s.fontname.clone()),
+            fontsize: style.map(|s| s.fontsize),
+        }
+    }
+}
+
+/// The tags in a block that change nothing, with why.
+fn redundant<'o, 'a>(
+    overrides: &'o [StyleOverride<Cow<'a, str>>],
+    state: &mut State,
+    script: &Script,
+    style: Option<&Style>,
+) -> Vec<(&'o StyleOverride<Cow<'a, str>>, &'static str)> {
+    use StyleOverride::*;
+    let mut found = Vec::new();
+    for (n, o) in overrides.iter().enumerate() {
+        // \pos, \move, \org, \fade and \an keep the first, the rest the last
+        let first_wins = matches!(
+            o,
+            Move { .. }
+                | Origin { .. }
+                | Fade { .. }
+                | FadeInAndOut { .. }
+                | Alignment(_)
+                | NumpadLayoutAlignment(_)
+        );
+        let this = slot(o);
+        let (before, after) = (&overrides[..n], &overrides[n + 1..]);
+        let again = this.is_some()
+            && if first_wins {
+                before.iter().any(|b| slot(b) == this)
+            } else {
+                after
+                    .iter()
+                    .take_while(|a| !matches!(a, Reset(_)))
+                    .any(|a| slot(a) == this)
+            };
+        if again {
+            let why = if first_wins {
+                "is ignored after the first in the same block"
+            } else {
+                "is overridden in the same block"
+            };
+            found.push((o, why));
+            continue;
+        }
+
+        let unchanged = match o {
+            Bold(b) if *b == 0.0 || *b == 1.0 => {
+                let same = state.bold == (*b == 1.0);
+                state.bold = *b == 1.0;
+                same
+            }
+            Italic(v) => std::mem::replace(&mut state.italic, *v) == *v,
+            Underline(v) => std::mem::replace(&mut state.underline, *v) == *v,
+            StrikeOut(v) => std::mem::replace(&mut state.strikeout, *v) == *v,
+            FontName(name) if !name.trim().is_empty() => {
+                let name = name.trim();
+                let same = state.fontname.as_deref() == Some(name);
+                state.fontname = Some(name.to_owned());
+                same
+            }
+            FontSize(size) if *size > 0.0 => state.fontsize.replace(*size) == Some(*size),
+            Reset(name) => {
+                let reset = name
+                    .as_deref()
+                    .and_then(|name| find_style(script, name))
+                    .or(style);
+                *state = State::new(reset);
+                false
+            }
+            _ => false,
+        };
+        if unchanged {
+            found.push((o, "sets the value already in effect"));
+        }
+    }
+    found
+}
+
+/// The fonts in `[Fonts]`, as their names with the extension and the `_0`
+/// tools add taken off.
+fn attached_fonts(script: &Script) -> Vec<String> {
+    script
+        .other
+        .iter()
+        .filter_map(|s| match s {
+            Section::Attachments { name, files } if name == "Fonts" => Some(files),
+            _ => None,
+        })
+        .flatten()
+        .map(|f| {
+            let name = f.name.rsplit_once('.').map_or(f.name.as_str(), |(n, _)| n);
+            let name = match name.rsplit_once('_') {
+                Some((n, count)) if count.chars().all(|c| c.is_ascii_digit()) => n,
+                _ => name,
+            };
+            normalize(name)
+        })
+        .collect()
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Whether a file looks like it holds `family`. Without reading the fonts
+/// this can only compare names, so `arialbd.ttf` counts for `Arial`.
+fn is_attached(fonts: &[String], family: &str) -> bool {
+    let family = normalize(family.trim_start_matches('@'));
+    !family.is_empty()
+        && fonts
+            .iter()
+            .any(|f| f.contains(&family) || family.contains(f.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attachments::Attachment;
+
+    const HEADER: &str = "[Script Info]
+PlayResX: 640
+PlayResY: 480
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, Bold, Italic
+Style: Default,Arial,20,0,0
+Style: Italic,Arial,20,0,-1
+
+[Events]
+Format: Layer, Start, End, Style, Text
+";
+
+    fn script(events: &[&str]) -> Script {
+        let input = format!("{}{}\n", HEADER, events.join("\n"));
+        crate::parser::script(&input).unwrap().1
+    }
+
+    fn found(script: &Script, rule: Rule) -> Vec<(Option<usize>, String)> {
+        lint(script, &Config::default())
+            .into_iter()
+            .filter(|p| p.rule == rule)
+            .map(|p| (p.event, p.message))
+            .collect()
+    }
+
+    fn events(rule: Rule, events: &[&str]) -> Vec<Option<usize>> {
+        found(&script(events), rule)
+            .into_iter()
+            .map(|(e, _)| e)
+            .collect()
+    }
+
+    #[test]
+    fn undefined_style() {
+        let found = events(
+            Rule::UndefinedStyle,
+            &[
+                "Dialogue: 0,0:00:01.00,0:00:02.00,Default,a",
+                "Dialogue: 0,0:00:03.00,0:00:04.00,*Default,b",
+                "Dialogue: 0,0:00:05.00,0:00:06.00,Nope,c",
+                "Comment: 0,0:00:05.00,0:00:06.00,Nope,d",
+            ],
+        );
+        assert_eq!(found, [Some(2)]);
+    }
+
+    #[test]
+    fn font_not_attached() {
+        let lines = [
+            "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\fnArial}a",
+            "Dialogue: 0,0:00:03.00,0:00:04.00,Default,{\\fnComic Sans}b",
+            "Dialogue: 0,0:00:05.00,0:00:06.00,Default,{\\fnComic Sans}c",
+        ];
+        let mut script = script(&lines);
+        assert_eq!(
+            found(&script, Rule::FontNotAttached)
+                .into_iter()
+                .map(|(e, _)| e)
+                .collect::<Vec<_>>(),
+            [Some(0), Some(1)]
+        );
+
+        script.other.push(Section::Attachments {
+            name: "Fonts".to_owned(),
+            files: vec![Attachment {
+                name: "arialbd_0.ttf".to_owned(),
+                data: Vec::new(),
+            }],
+        });
+        assert_eq!(
+            found(&script, Rule::FontNotAttached),
+            [(Some(1), "font \"Comic Sans\" isn't attached".to_owned())]
+        );
+    }
+
+    #[test]
+    fn timing() {
+        let lines = [
+            "Dialogue: 0,0:00:02.00,0:00:01.00,Default,a",
+            "Dialogue: 0,0:00:03.00,0:00:03.00,Default,b",
+            "Comment: 0,0:00:05.00,0:00:05.00,Default,c",
+            "Dialogue: 0,0:00:06.00,0:00:07.00,Default,d",
+        ];
+        assert_eq!(events(Rule::EndBeforeStart, &lines), [Some(0)]);
+        assert_eq!(events(Rule::ZeroLength, &lines), [Some(1)]);
+
+        let mut script = script(&lines);
+        script.events[3].end = None;
+        assert_eq!(
+            found(&script, Rule::MissingTime)
+                .into_iter()
+                .map(|(e, _)| e)
+                .collect::<Vec<_>>(),
+            [Some(3)]
+        );
+    }
+
+    #[test]
+    fn overlap() {
+        let lines = [
+            "Dialogue: 0,0:00:01.00,0:00:10.00,Default,long",
+            "Dialogue: 0,0:00:02.00,0:00:03.00,Default,inside",
+            "Dialogue: 0,0:00:04.00,0:00:05.00,Default,also inside",
+            "Dialogue: 0,0:00:10.00,0:00:11.00,Default,touching",
+            "Dialogue: 1,0:00:02.00,0:00:03.00,Default,other layer",
+            "Dialogue: 0,0:00:02.00,0:00:03.00,Italic,other style",
+            "Comment: 0,0:00:02.00,0:00:03.00,Default,comment",
+        ];
+        let found = found(&script(&lines), Rule::Overlap);
+        assert_eq!(
+            found,
+            [
+                (Some(1), "overlaps event 1".to_owned()),
+                (Some(2), "overlaps event 1".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_brace() {
+        let found = events(
+            Rule::UnclosedBrace,
+            &[
+                "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\i1}fine{\\i0}",
+                "Dialogue: 0,0:00:03.00,0:00:04.00,Default,{\\i1 oops",
+                "Dialogue: 0,0:00:05.00,0:00:06.00,Default,a}{b",
+                "Dialogue: 0,0:00:07.00,0:00:08.00,Default,{",
+                "Dialogue: 0,0:00:09.00,0:00:10.00,Default,escaped \\{",
+            ],
+        );
+        assert_eq!(found, [Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn unknown_tag() {
+        let found = found(
+            &script(&[
+                "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\foo3}a",
+                "Dialogue: 0,0:00:03.00,0:00:04.00,Default,{\\blur2\\xbord1\\K20}b",
+                "Dialogue: 0,0:00:05.00,0:00:06.00,Default,{TL note: hello}c",
+                "Dialogue: 0,0:00:07.00,0:00:08.00,Default,{\\t(0,100,\\bar1)}d",
+                "Dialogue: 0,0:00:09.00,0:00:10.00,Default,{\\fad(200)}e",
+            ]),
+            Rule::UnknownTag,
+        );
+        assert_eq!(
+            found,
+            [
+                (Some(0), "unknown override tag \\foo3".to_owned()),
+                (Some(3), "unknown override tag \\bar1".to_owned()),
+                (Some(4), "malformed override tag \\fad(200)".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn redundant_override() {
+        let found = found(
+            &script(&[
+                // the first \b is overridden, the second sets what's in effect
+                "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\b1\\b0}a",
+                // \pos keeps the first
+                "Dialogue: 0,0:00:03.00,0:00:04.00,Default,{\\pos(1,1)\\pos(2,2)}b",
+                "Dialogue: 0,0:00:05.00,0:00:06.00,Italic,{\\i1}c",
+                // \r goes back to the style, so \i1 does something again
+                "Dialogue: 0,0:00:07.00,0:00:08.00,Default,{\\i1}d{\\r\\i1}e",
+                "Dialogue: 0,0:00:09.00,0:00:10.00,Default,{\\b1}f{\\b1}g",
+                "Dialogue: 0,0:00:11.00,0:00:12.00,Default,{\\fs20\\fnArial}h",
+            ]),
+            Rule::RedundantOverride,
+        );
+        assert_eq!(
+            found,
+            [
+                (Some(0), "\\b is overridden in the same block".to_owned()),
+                (Some(0), "\\b sets the value already in effect".to_owned()),
+                (
+                    Some(1),
+                    "\\pos is ignored after the first in the same block".to_owned()
+                ),
+                (Some(2), "\\i sets the value already in effect".to_owned()),
+                (Some(4), "\\b sets the value already in effect".to_owned()),
+                (Some(5), "\\fs sets the value already in effect".to_owned()),
+                (Some(5), "\\fn sets the value already in effect".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn position_outside() {
+        let found = found(
+            &script(&[
+                "Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\pos(320,240)}a",
+                "Dialogue: 0,0:00:03.00,0:00:04.00,Default,{\\pos(700,240)}b",
+                "Dialogue: 0,0:00:05.00,0:00:06.00,Default,{\\move(0,0,640,-1)}c",
+            ]),
+            Rule::PositionOutside,
+        );
+        assert_eq!(
+            found,
+            [
+                (
+                    Some(1),
+                    "\\pos is outside the 640x480 script resolution".to_owned()
+                ),
+                (
+                    Some(2),
+                    "\\move is outside the 640x480 script resolution".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn config() {
+        let config = Config::parse(
+            "\u{feff}# only these\r\nall = off\r\n\n  overlap = error # yes\r\nzero-length=Info\r\n",
+        )
+        .unwrap();
+        for rule in Rule::ALL {
+            let expected = match rule {
+                Rule::Overlap => Some(Severity::Error),
+                Rule::ZeroLength => Some(Severity::Info),
+                _ => None,
+            };
+            assert_eq!(config.severity(rule), expected, "{}", rule);
+        }
+
+        let script = script(&[
+            "Dialogue: 0,0:00:01.00,0:00:01.00,Nope,{\\foo}a",
+            "Dialogue: 0,0:00:01.00,0:00:02.00,Default,b",
+            "Dialogue: 0,0:00:01.50,0:00:03.00,Default,c",
+        ]);
+        let problems: Vec<(Rule, Severity, Option<usize>)> = lint(&script, &config)
+            .into_iter()
+            .map(|p| (p.rule, p.severity, p.event))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (Rule::ZeroLength, Severity::Info, Some(0)),
+                (Rule::Overlap, Severity::Error, Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_errors() {
+        assert_eq!(
+            Config::parse("overlap = error\nnope = off"),
+            Err(ConfigError::UnknownRule {
+                line: 2,
+                name: "nope".to_owned()
+            })
+        );
+        assert_eq!(
+            Config::parse("overlap = loud"),
+            Err(ConfigError::UnknownSeverity {
+                line: 1,
+                value: "loud".to_owned()
+            })
+        );
+        assert_eq!(
+            Config::parse("overlap error"),
+            Err(ConfigError::InvalidLine {
+                line: 1,
+                content: "overlap error".to_owned()
+            })
+        );
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn rule_names() {
+        for rule in Rule::ALL {
+            assert_eq!(Rule::from_name(rule.name()), Some(rule));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Parsing and transforming events on all cores, with the `rayon` feature.
+//!
+//! Events are independent of each other once the `Format:` line is known,
+//! so these give the same results as their sequential counterparts.
+
+use crate::parser::script_without_events;
+use crate::timecodes::Timecodes;
+use crate::timing::{scale_entry, shift_entry, Selection};
+use crate::{Entry, Script, TextSection};
+use nom::IResult;
+use rayon::prelude::*;
+
+/// [`crate::parser::script`], parsing the event lines in parallel.
+pub fn script(input: &str) -> IResult<&str, Script> {
+    let (input, (mut script, events)) = script_without_events(input)?;
+    script.events = events
+        .lines
+        .par_iter()
+        .filter_map(|line| events.parse(*line))
+        .collect();
+    Ok((input, script))
+}
+
+/// The parsed text of each event.
+pub fn parsed_texts(entries: &[Entry]) -> Vec<Vec<TextSection>> {
+    entries.par_iter().map(Entry::parsed_text).collect()
+}
+
+/// Applies `f` to every event.
+pub fn transform<F>(entries: &mut [Entry], f: F)
+where
+    F: Fn(&mut Entry) + Sync + Send,
+{
+    entries.par_iter_mut().for_each(f);
+}
+
+/// [`crate::timing::shift`] in parallel.
+pub fn shift(entries: &mut [Entry], offset_ms: i64, selection: &Selection) {
+    transform(entries, |entry| {
+        if selection.matches(entry) {
+            shift_entry(entry, offset_ms);
+        }
+    });
+}
+
+/// [`crate::timing::scale`] in parallel, override tag times included.
+pub fn scale(entries: &mut [Entry], factor: f64, selection: &Selection) {
+    transform(entries, |entry| {
+        if selection.matches(entry) {
+            scale_entry(entry, factor);
+        }
+    });
+}
+
+/// [`Timecodes::snap`] in parallel.
+pub fn snap(timecodes: &Timecodes, entries: &mut [Entry]) {
+    transform(entries, |entry| {
+        if let Some((start, end)) = timecodes.entry_frames(entry) {
+            timecodes.set_entry_frames(entry, start, end);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser, timing};
+    use parsing_utils::timing::TimeRange;
+    use std::fmt::Write as _;
+    use std::time::Duration;
+
+    // enough events to be split between threads, with a second format line
+    // part way through
+    fn script_text() -> String {
+        let mut text = "[Script Info]\nTitle: Parallel\n\n[Events]\n\
+                        Format: Layer, Start, End, Style, Text\n"
+            .to_owned();
+        for n in 0..2000 {
+            if n == 1000 {
+                text.push_str("Format: Start, End, Style, Name, Text\n");
+            }
+            let style = if n % 3 == 0 { "Top" } else { "Default" };
+            let _ = match n {
+                _ if n % 97 == 0 => writeln!(text, "broken {}", n),
+                _ if n < 1000 => writeln!(
+                    text,
+                    "Dialogue: {},0:00:{:02}.{:02},0:01:00.00,{},{{\\fad(100,200)}}Line {}",
+                    n % 4,
+                    n / 100 % 60,
+                    n % 100,
+                    style,
+                    n
+                ),
+                _ => writeln!(
+                    text,
+                    "Comment: 0:00:{:02}.{:02},0:02:00.00,{},Name,{{\\t(0,300,\\fs20)}}Line {}",
+                    n / 100 % 60,
+                    n % 100,
+                    style,
+                    n
+                ),
+            };
+        }
+        text
+    }
+
+    fn events(text: &str) -> Vec<Entry> {
+        parser::script(text).unwrap().1.events
+    }
+
+    #[test]
+    fn script_matches_the_sequential_parser() {
+        let text = script_text();
+        let (rest, parallel) = script(&text).unwrap();
+        let (sequential_rest, sequential) = parser::script(&text).unwrap();
+        assert_eq!(rest, sequential_rest);
+        assert!(parallel.events.len() > 1900);
+        assert_eq!(
+            format!("{:?}", parallel.events),
+            format!("{:?}", sequential.events)
+        );
+        assert_eq!(parallel.info, sequential.info);
+    }
+
+    #[test]
+    fn parsed_texts_match() {
+        let events = events(&script_text());
+        let sequential: Vec<_> = events.iter().map(Entry::parsed_text).collect();
+        assert_eq!(
+            format!("{:?}", parsed_texts(&events)),
+            format!("{:?}", sequential)
+        );
+    }
+
+    #[test]
+    fn timing_matches_the_sequential_functions() {
+        let text = script_text();
+        let selections = [
+            Selection::default(),
+            Selection {
+                range: Some(TimeRange::new(
+                    Duration::from_secs(3),
+                    Duration::from_secs(12),
+                )),
+                styles: Some(vec!["Top".to_owned()]),
+            },
+        ];
+        for selection in &selections {
+            let (mut parallel, mut sequential) = (events(&text), events(&text));
+            shift(&mut parallel, -1500, selection);
+            timing::shift(&mut sequential, -1500, selection);
+            assert_eq!(format!("{:?}", parallel), format!("{:?}", sequential));
+
+            scale(&mut parallel, 25.0 / 23.976, selection);
+            timing::scale(&mut sequential, 25.0 / 23.976, selection);
+            assert_eq!(format!("{:?}", parallel), format!("{:?}", sequential));
+        }
+
+        let timecodes = Timecodes::cfr(24000.0 / 1001.0).unwrap();
+        let (mut parallel, mut sequential) = (events(&text), events(&text));
+        snap(&timecodes, &mut parallel);
+        timecodes.snap(&mut sequential);
+        assert_eq!(format!("{:?}", parallel), format!("{:?}", sequential));
+    }
+}
@@ -1,14 +1,18 @@
+use crate::attachments::{file_key, is_section_header};
 use crate::*;
 use nom::{
     branch::alt,
-    bytes::complete::{is_not, tag, take_until, take_while},
-    character::complete::{char, line_ending, not_line_ending, one_of, space1, u64 as decimal},
+    bytes::complete::{is_not, tag, take_while, take_while1},
+    character::complete::{
+        char, line_ending, multispace0, not_line_ending, one_of, space1, u64 as decimal,
+    },
     combinator::{consumed, map, not, opt, peek},
     multi::{many0, many_m_n, separated_list0},
     sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 use parsing_utils::*;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -27,8 +31,32 @@ fn full_color(input: &str) -> IResult<&str, Color> {
     ))
 }
 
+// `&HAABBGGRR`, or the same as a number in hex after `&H` or in decimal,
+// the way SSA scripts often write style colours
+fn style_color(input: &str) -> Option<Color> {
+    if let Ok((_, color)) = full_color(input) {
+        return Some(color);
+    }
+    let input = input.trim();
+    let value = match input
+        .strip_prefix("&H")
+        .or_else(|| input.strip_prefix("&h"))
+    {
+        Some(hex) => u32::from_str_radix(hex.trim_end_matches('&'), 16).ok()?,
+        None => input.parse::<i64>().ok()? as u32,
+    };
+    let [red, green, blue, alpha] = value.to_le_bytes();
+    Some(Color {
+        alpha: Some(alpha),
+        red,
+        green,
+        blue,
+    })
+}
+
+// BBGGRR
 fn partial_color(input: &str) -> IResult<&str, Color> {
-    let (input, (red, green, blue)) = tuple((hex_primary, hex_primary, hex_primary))(input)?;
+    let (input, (blue, green, red)) = tuple((hex_primary, hex_primary, hex_primary))(input)?;
     Ok((
         input,
         Color {
@@ -41,17 +69,32 @@ fn partial_color(input: &str) -> IResult<&str, Color> {
 }
 
 // Hrs:Mins:Secs:hundredths or Hrs:Mins:Secs.hundredths
-fn duration(input: &str) -> IResult<&str, Duration> {
+pub fn timestamp(input: &str) -> IResult<&str, Timestamp> {
     let (input, (hours, mins, secs, hundredths)) = tuple((
         terminated(decimal, char(':')),
         terminated(decimal, char(':')),
         terminated(decimal, one_of(":.")),
         decimal,
     ))(input)?;
-    Ok((
-        input,
-        Duration::from_millis(hours * 3_600_000 + mins * 60_000 + secs * 1_000 + hundredths * 10),
-    ))
+    let too_large = || {
+        nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TooLarge,
+        ))
+    };
+    let field = |value: u64| i64::try_from(value).map_err(|_| too_large());
+    let timestamp = Timestamp::checked_from_hms(
+        field(hours)?,
+        field(mins)?,
+        field(secs)?,
+        field(hundredths)?,
+    )
+    .ok_or_else(too_large)?;
+    Ok((input, timestamp))
+}
+
+fn duration(input: &str) -> IResult<&str, Duration> {
+    map(timestamp, Timestamp::as_duration)(input)
 }
 
 fn draw_move(input: &str) -> IResult<&str, Vec<DrawingCommand>> {
@@ -165,7 +208,13 @@ fn drawing(input: &str) -> IResult<&str, Vec<DrawingCommand>> {
     )(input.trim_start())
 }
 
-fn function(input: &str) -> IResult<&str, StyleOverride> {
+// a function tag with the wrong number of arguments, left for
+// `fallback_style` to keep as text
+fn wrong_arguments(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Verify))
+}
+
+fn function(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     let (input, kind) = alt((
         tag("move"),
         tag("pos"),
@@ -252,46 +301,40 @@ fn function(input: &str) -> IResult<&str, StyleOverride> {
                 },
             ))
         }
-        "org" => {
-            let (_arg_string, args) = separated_list0(ws(char(',')), decimal_or_float)(arg_string)?;
-            Ok((
-                input,
-                StyleOverride::Origin {
-                    x: args[0],
-                    y: args[1],
-                },
-            ))
-        }
-        "fade" => {
-            let (_arg_string, args) = separated_list0(ws(char(',')), decimal)(arg_string)?;
-            Ok((
+        "org" => match separated_list0(ws(char(',')), decimal_or_float)(arg_string)?.1[..] {
+            [x, y] => Ok((input, StyleOverride::Origin { x, y })),
+            _ => Err(wrong_arguments(input)),
+        },
+        "fade" => match separated_list0(ws(char(',')), decimal)(arg_string)?.1[..] {
+            [a1, a2, a3, t1, t2, t3, t4] => Ok((
                 input,
                 StyleOverride::Fade {
-                    starting_alpha: args[0] as u8,
-                    middle_alpha: args[1] as u8,
-                    ending_alpha: args[2] as u8,
-                    start_time: Duration::from_millis(args[3]),
-                    in_between_time: Duration::from_millis(args[4]),
-                    late_time: Duration::from_millis(args[5]),
-                    ending_time: Duration::from_millis(args[6]),
+                    starting_alpha: a1 as u8,
+                    middle_alpha: a2 as u8,
+                    ending_alpha: a3 as u8,
+                    start_time: Duration::from_millis(t1),
+                    in_between_time: Duration::from_millis(t2),
+                    late_time: Duration::from_millis(t3),
+                    ending_time: Duration::from_millis(t4),
                 },
-            ))
-        }
-        "fad" => {
-            let (_arg_string, args) = separated_list0(ws(char(',')), decimal)(arg_string)?;
-            Ok((
+            )),
+            _ => Err(wrong_arguments(input)),
+        },
+        "fad" => match separated_list0(ws(char(',')), decimal)(arg_string)?.1[..] {
+            [fade_in, fade_out] => Ok((
                 input,
                 StyleOverride::FadeInAndOut {
-                    fade_in_for: Duration::from_millis(args[0]),
-                    fade_out_for: Duration::from_millis(args[1]),
+                    fade_in_for: Duration::from_millis(fade_in),
+                    fade_out_for: Duration::from_millis(fade_out),
                 },
-            ))
-        }
+            )),
+            _ => Err(wrong_arguments(input)),
+        },
         _ => unimplemented!(), // todo: not!
     }
 }
 
-fn bool_style(input: &str) -> IResult<&str, StyleOverride> {
+fn bool_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     use StyleOverride::*;
     let (input, (kind, n)) = alt((
         pair(tag("be"), one_of("01")),
@@ -314,7 +357,7 @@ fn bool_style(input: &str) -> IResult<&str, StyleOverride> {
     ))
 }
 
-fn number_style(input: &str) -> IResult<&str, StyleOverride> {
+fn number_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     use StyleOverride::*;
 
     let (input, (kind, n)) = alt((
@@ -359,27 +402,32 @@ fn number_style(input: &str) -> IResult<&str, StyleOverride> {
     ))
 }
 
-fn string_style(input: &str) -> IResult<&str, StyleOverride> {
+fn string_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     use StyleOverride::*;
-    let (input, (_, name)) = pair(tag("fn"), take_until("\\"))(input)?;
-    Ok((input, FontName(name.to_owned())))
+    let (input, (_, name)) = pair(tag("fn"), take_while(|c| c != '\\' && c != '}'))(input)?;
+    Ok((input, FontName(name.into())))
 }
 
-fn color_style(input: &str) -> IResult<&str, StyleOverride> {
+fn color_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     use StyleOverride::*;
     let (input, idx) = opt(decimal)(input)?;
-    let (input, color) = delimited(tag("c&H"), is_not("&"), char('&'))(input)?;
-    let color = color
-        .chars()
-        .rev()
-        .chain(['0'].into_iter().cycle())
-        .take(6)
-        .collect::<String>();
-    let (_, color) = partial_color(&color).unwrap(); // TODO: don't unwrap
-    Ok((input, Color(idx.unwrap_or(1), color)))
-}
-
-fn alpha_style(input: &str) -> IResult<&str, StyleOverride> {
+    let (rest, color) = delimited(tag("c&H"), is_not("&"), char('&'))(input)?;
+    // leading zeros may be left out, `&HFF&` is red, and of more than six
+    // digits only the last six count, as an alpha given with `\c` is ignored
+    let color = match color.char_indices().rev().nth(5) {
+        Some((n, _)) => color[n..].to_owned(),
+        None => format!("{:0>6}", color),
+    };
+    let (_, color) = partial_color(&color).map_err(|_| {
+        nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::HexDigit,
+        ))
+    })?;
+    Ok((rest, Color(idx.unwrap_or(1), color)))
+}
+
+fn alpha_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     use StyleOverride::*;
     let (input, idx) = opt(decimal)(input)?;
     let (input, alpha) =
@@ -387,13 +435,22 @@ fn alpha_style(input: &str) -> IResult<&str, StyleOverride> {
     Ok((input, Alpha(idx.unwrap_or(1), alpha)))
 }
 
-fn fallback_style(input: &str) -> IResult<&str, StyleOverride> {
+fn reset_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     use StyleOverride::*;
-    let (input, what) = take_until("\\")(input)?;
-    Ok((input, Other(what.to_owned())))
+    let (input, name) = preceded(char('r'), take_while(|c| c != '\\' && c != '}'))(input)?;
+    Ok((
+        input,
+        Reset(Some(name).filter(|n| !n.is_empty()).map(Cow::Borrowed)),
+    ))
 }
 
-fn style(input: &str) -> IResult<&str, StyleOverride> {
+fn fallback_style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
+    use StyleOverride::*;
+    let (input, what) = take_while1(|c| c != '\\' && c != '}')(input)?;
+    Ok((input, Other(what.into())))
+}
+
+fn style(input: &str) -> IResult<&str, borrowed::StyleOverride<'_>> {
     let (input, _) = char('\\')(input)?;
     alt((
         bool_style,
@@ -402,17 +459,18 @@ fn style(input: &str) -> IResult<&str, StyleOverride> {
         alpha_style,
         number_style,
         function,
+        reset_style,
         fallback_style,
     ))(input)
 }
 
-fn style_override(input: &str) -> IResult<&str, TextSection> {
+fn style_override(input: &str) -> IResult<&str, borrowed::TextSection<'_>> {
     map(
         delimited(
             char('{'),
             many0(alt((
                 style,
-                map(is_not("}"), |v: &str| StyleOverride::Other(v.to_owned())),
+                map(is_not("}"), |v: &str| StyleOverride::Other(v.into())),
             ))),
             char('}'),
         ),
@@ -420,18 +478,35 @@ fn style_override(input: &str) -> IResult<&str, TextSection> {
     )(input)
 }
 
-fn text(input: &str) -> IResult<&str, TextSection> {
-    map(is_not("{"), |v: &str| TextSection::Text(v.to_owned()))(input)
+// up to the next `{` that isn't escaped as `\{`, as libass reads it
+fn text(input: &str) -> IResult<&str, borrowed::TextSection<'_>> {
+    let end = input
+        .match_indices('{')
+        .find(|(n, _)| !input[..*n].ends_with('\\'))
+        .map_or(input.len(), |(n, _)| n);
+    if end == 0 {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::IsNot,
+        )));
+    }
+    Ok((&input[end..], TextSection::Text(input[..end].into())))
 }
 
-pub fn text_line(input: &str) -> IResult<String, Vec<TextSection>> {
-    let mut sections: Vec<TextSection> = Vec::new();
-    let (input, sect) = alt((text, style_override))(input).map_err(|e| e.to_owned())?;
-    let mut input = input.to_owned();
-    sections.push(sect);
+pub fn text_line(input: &str) -> IResult<&str, Vec<TextSection>> {
+    let (input, sections) = borrowed_text_line(input)?;
+    Ok((
+        input,
+        sections.into_iter().map(|s| s.into_owned()).collect(),
+    ))
+}
+
+pub(crate) fn borrowed_text_line(input: &str) -> IResult<&str, Vec<borrowed::TextSection<'_>>> {
+    let mut sections: Vec<borrowed::TextSection> = Vec::new();
+    let mut input = input;
 
     while !input.is_empty() {
-        if let TextSection::StyleOverride(styles) = sections.last().unwrap() {
+        if let Some(TextSection::StyleOverride(styles)) = sections.last() {
             if styles.iter().any(|v| {
                 if let StyleOverride::DrawingMode(x) = v {
                     x > &0.0
@@ -439,16 +514,24 @@ pub fn text_line(input: &str) -> IResult<String, Vec<TextSection>> {
                     false
                 }
             }) {
-                let (remaining, new_sect) = drawing(&input).map_err(|e| e.to_owned())?;
-                input = remaining.to_owned();
+                let (remaining, new_sect) = drawing(input)?;
+                input = remaining;
                 sections.push(TextSection::Drawing(new_sect));
                 continue;
             }
         }
 
-        let (ninput, nsect) = alt((text, style_override))(&input).map_err(|e| e.to_owned())?;
-        input = ninput.to_owned();
-        sections.push(nsect);
+        match alt((text, style_override))(input) {
+            Ok((remaining, sect)) => {
+                input = remaining;
+                sections.push(sect);
+            }
+            // a `{` that is never closed is shown as text, as libass does
+            Err(_) => {
+                sections.push(TextSection::Text(input.into()));
+                input = "";
+            }
+        }
     }
 
     Ok((input, sections))
@@ -479,28 +562,36 @@ fn setting(input: &str) -> IResult<&str, Option<(&str, &str)>> {
     }
 }
 
-pub fn subtitle<'a>(input: &'a str, definition: &'a Vec<String>) -> IResult<&'a str, Entry> {
-    let mut entry = Entry::default();
+pub fn subtitle<'a>(input: &'a str, definition: &'a [String]) -> IResult<&'a str, Entry> {
+    let (input, entry) = borrowed_subtitle(input, definition)?;
+    Ok((input, entry.into_owned()))
+}
+
+pub(crate) fn borrowed_subtitle<'a>(
+    input: &'a str,
+    definition: &[String],
+) -> IResult<&'a str, borrowed::Entry<'a>> {
+    let mut entry = borrowed::Entry::default();
     let (input, kind) = opt(terminated(is_not(":,"), char(':')))(input)?;
-    entry.kind = kind.map(|v| v.to_owned());
-
-    let (input, settings) = many_m_n(
-        1,
-        definition.len() - 1,
-        terminated(opt(is_not(",")), char(',')),
-    )(input.trim_start())?;
-    for (n, possible_val) in settings.into_iter().enumerate() {
+    entry.kind = kind.map(Cow::Borrowed);
+
+    // every field but the last, the text, ends with a comma
+    let (input, settings) = match definition.len() {
+        0 | 1 => (input.trim_start(), Vec::new()),
+        n => many_m_n(1, n - 1, terminated(opt(is_not(",")), char(',')))(input.trim_start())?,
+    };
+    for (name, possible_val) in definition.iter().zip(settings) {
         if let Some(val) = possible_val {
-            match definition[n].as_str() {
+            match name.as_str() {
                 "Layer" => entry.layer = val.parse::<isize>().ok(),
                 "Start" => entry.start = duration(val).ok().map(|v| v.1),
                 "End" => entry.end = duration(val).ok().map(|v| v.1),
-                "Style" => entry.style = Some(val.to_owned()),
-                "Name" => entry.name = Some(val.to_owned()),
+                "Style" => entry.style = Some(val.into()),
+                "Name" => entry.name = Some(val.into()),
                 "MarginL" => entry.margin_l = val.parse::<usize>().ok(),
                 "MarginR" => entry.margin_r = val.parse::<usize>().ok(),
                 "MarginV" => entry.margin_v = val.parse::<usize>().ok(),
-                "Effect" => entry.effect = Some(val.to_owned()),
+                "Effect" => entry.effect = Some(val.into()),
                 "ReadOrder" => entry.read_order = val.parse::<isize>().ok(),
                 _ => (),
             }
@@ -508,16 +599,91 @@ pub fn subtitle<'a>(input: &'a str, definition: &'a Vec<String>) -> IResult<&'a
     }
 
     if !input.is_empty() {
-        entry.text = input.to_owned();
+        entry.text = input.into();
     }
 
     Ok((input, entry))
 }
 
+// a style from the values of a `Style:` line, named by the `Format:` line
+pub(crate) fn borrowed_style<'a>(definition: &[&str], vals: Vec<&'a str>) -> borrowed::Style<'a> {
+    let mut style = borrowed::Style::default();
+
+    for (&name, val) in definition.iter().zip(vals) {
+        match name {
+            "Name" => style.name = val.into(),
+            "Fontname" => style.fontname = val.into(),
+            "Fontsize" => style.fontsize = val.trim().parse::<f64>().unwrap_or_default(),
+            "PrimaryColour" => style.primary_color = style_color(val),
+            "SecondaryColour" => style.secondary_color = style_color(val),
+            "OutlineColour" => style.outline_color = style_color(val),
+            "BackColour" => style.back_color = style_color(val),
+            "Bold" => style.bold = Some(val == "-1"),
+            "Italic" => style.italic = Some(val == "-1"),
+            "Underline" => style.underline = Some(val == "-1"),
+            "StrikeOut" => style.strikeout = Some(val == "-1"),
+            "ScaleX" => style.scale_x = val.parse::<usize>().ok(),
+            "ScaleY" => style.scale_y = val.parse::<usize>().ok(),
+            "Spacing" => style.spacing = val.parse::<usize>().ok(),
+            "Angle" => style.angle = val.parse::<f64>().ok(),
+            "BorderStyle" => style.border_style = val.parse::<usize>().ok(),
+            "Outline" => style.outline_size = val.parse::<usize>().ok(),
+            "Shadow" => style.shadow = val.parse::<usize>().ok(),
+            "Alignment" => style.alignment = val.parse::<usize>().ok(),
+            "MarginL" => style.margin_l = val.parse::<usize>().ok(),
+            "MarginR" => style.margin_r = val.parse::<usize>().ok(),
+            "MarginV" => style.margin_v = val.parse::<usize>().ok(),
+            "Encoding" => style.encoding = val.parse::<usize>().ok(),
+            _ => (),
+        }
+    }
+    style
+}
+
+// the numpad alignment of an SSA `Alignment`: 1 to 3 along the bottom,
+// plus 4 for the top and 8 for the middle
+fn legacy_alignment(alignment: usize) -> usize {
+    match alignment {
+        5..=7 => alignment + 2,
+        9..=11 => alignment - 5,
+        _ => alignment,
+    }
+}
+
+// turns a `[V4 Styles]` style into what it is in ASS, as VSFilter reads it:
+// the outline is drawn in the shadow colour, leaving `TertiaryColour`
+// unused, and `AlphaLevel` is the alpha of every colour but the shadow's,
+// which is half transparent
+fn legacy_style(style: &mut Style, definition: &[&str], vals: &[&str]) {
+    style.alignment = style.alignment.map(legacy_alignment);
+    style.outline_color = style.back_color.clone();
+
+    let alpha = definition
+        .iter()
+        .zip(vals)
+        .find(|(name, _)| **name == "AlphaLevel")
+        .and_then(|(_, val)| val.trim().parse::<i64>().ok())
+        .unwrap_or(0)
+        .clamp(0, 255) as u8;
+    for color in [
+        &mut style.primary_color,
+        &mut style.secondary_color,
+        &mut style.outline_color,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        color.alpha = Some(alpha);
+    }
+    if let Some(back) = &mut style.back_color {
+        back.alpha = Some(0x80);
+    }
+}
+
 pub fn section(input: &str) -> IResult<&str, Section> {
     let (input, header) = delimited(char('['), is_not("]"), char(']'))(input)?;
     match header {
-        "V4+ Styles" => {
+        "V4+ Styles" | "V4 Styles" => {
             let (input, definition) = preceded(tag("Format:"), line_list)(input.trim_start())?;
             let (input, lines) = separated_list0(line_ending, preceded(tag("Style:"), line_list))(
                 input.trim_start(),
@@ -525,43 +691,41 @@ pub fn section(input: &str) -> IResult<&str, Section> {
             let mut h = HashMap::new();
 
             for vals in lines {
-                let mut style = Style::default();
-
-                for (n, val) in vals.into_iter().enumerate() {
-                    match definition[n] {
-                        "Name" => style.name = val.to_owned(),
-                        "Fontname" => style.fontname = val.to_owned(),
-                        "Fontsize" => style.fontsize = val.parse::<usize>().unwrap(),
-                        "PrimaryColour" => style.primary_color = full_color(val).ok().map(|v| v.1),
-                        "SecondaryColour" => {
-                            style.secondary_color = full_color(val).ok().map(|v| v.1)
-                        }
-                        "OutlineColour" => style.outline_color = full_color(val).ok().map(|v| v.1),
-                        "BackColour" => style.back_color = full_color(val).ok().map(|v| v.1),
-                        "Bold" => style.bold = Some(val == "-1"),
-                        "Italic" => style.italic = Some(val == "-1"),
-                        "Underline" => style.underline = Some(val == "-1"),
-                        "ScaleX" => style.scale_x = val.parse::<usize>().ok(),
-                        "ScaleY" => style.scale_y = val.parse::<usize>().ok(),
-                        "Spacing" => style.spacing = val.parse::<usize>().ok(),
-                        "Angle" => style.angle = val.parse::<f64>().ok(),
-                        "BorderStyle" => style.border_style = val.parse::<usize>().ok(),
-                        "Outline" => style.outline_size = val.parse::<usize>().ok(),
-                        "Shadow" => style.shadow = val.parse::<usize>().ok(),
-                        "Alignment" => style.alignment = val.parse::<usize>().ok(),
-                        "MarginL" => style.margin_l = val.parse::<usize>().ok(),
-                        "MarginR" => style.margin_r = val.parse::<usize>().ok(),
-                        "MarginV" => style.margin_v = val.parse::<usize>().ok(),
-                        "Encoding" => style.encoding = val.parse::<usize>().ok(),
-                        _ => (),
-                    }
+                let mut style = borrowed_style(&definition, vals.clone()).into_owned();
+                if header == "V4 Styles" {
+                    legacy_style(&mut style, &definition, &vals);
                 }
                 h.insert(style.name.clone(), style);
             }
 
             Ok((input, Section::Styles(h)))
         }
+        "Fonts" | "Graphics" => {
+            let (input, _) = line_ending(input)?;
+            let mut end = input.len();
+            let mut offset = 0;
+            for line in input.split_inclusive('\n') {
+                if is_section_header(line.trim_end_matches(['\r', '\n'])) {
+                    end = offset;
+                    break;
+                }
+                offset += line.len();
+            }
+            let files = attachments::files(input[..end].lines(), file_key(header).unwrap());
+            Ok((
+                &input[end..],
+                Section::Attachments {
+                    name: header.to_owned(),
+                    files,
+                },
+            ))
+        }
         "Events" => {
+            // comment lines may come before the format line
+            let (input, _) = many0(preceded(
+                multispace0,
+                delimited(char(';'), not_line_ending, line_ending),
+            ))(input)?;
             let (input, definition) = preceded(tag("Format:"), line_list)(input.trim_start())?;
             Ok((
                 input,
@@ -594,3 +758,256 @@ pub fn section(input: &str) -> IResult<&str, Section> {
 pub fn section_with_input(input: &str) -> IResult<&str, (&str, Section)> {
     consumed(section)(input)
 }
+
+/// The event lines of a script, left unparsed, each with the index of the
+/// `Format:` line naming its fields.
+pub(crate) struct EventLines<'a> {
+    pub definitions: Vec<Vec<String>>,
+    pub lines: Vec<(usize, &'a str)>,
+}
+
+impl EventLines<'_> {
+    pub fn parse(&self, (definition, line): (usize, &str)) -> Option<Entry> {
+        let (_, entry) = borrowed_subtitle(line, &self.definitions[definition]).ok()?;
+        Some(entry.into_owned())
+    }
+}
+
+/// Parses a whole script, skipping lines that don't belong to any section
+/// it understands.
+pub fn script(input: &str) -> IResult<&str, Script> {
+    let (input, (mut script, events)) = script_without_events(input)?;
+    script.events = events
+        .lines
+        .iter()
+        .filter_map(|line| events.parse(*line))
+        .collect();
+    Ok((input, script))
+}
+
+pub(crate) fn script_without_events(input: &str) -> IResult<&str, (Script, EventLines<'_>)> {
+    let mut script = Script::default();
+    let mut events = EventLines {
+        definitions: Vec::new(),
+        lines: Vec::new(),
+    };
+    let mut in_events = false;
+    let mut input = input.trim_start_matches('\u{feff}');
+
+    while !input.is_empty() {
+        if input.starts_with('[') {
+            if let Ok((remaining, section)) = section(input) {
+                in_events = false;
+                match section {
+                    Section::Other { name, settings } if name == "Script Info" => {
+                        script.info.extend(settings)
+                    }
+                    Section::Styles(styles) => script.styles.extend(styles),
+                    Section::EventsHeader(fields) => {
+                        events.definitions.push(fields);
+                        in_events = true;
+                    }
+                    other => script.other.push(other),
+                }
+                input = remaining;
+                continue;
+            }
+        }
+
+        let (remaining, line) = terminated(not_line_ending, opt(line_ending))(input)?;
+        if remaining.len() == input.len() {
+            // a lone \r that isn't a line ending
+            input = &input[1..];
+            continue;
+        }
+        input = remaining;
+
+        if in_events && !line.trim().is_empty() && !line.starts_with(';') {
+            events.lines.push((events.definitions.len() - 1, line));
+        }
+    }
+
+    Ok((input, (script, events)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(text: &str) -> Vec<StyleOverride> {
+        text_line(text)
+            .unwrap()
+            .1
+            .into_iter()
+            .flat_map(|s| match s {
+                TextSection::StyleOverride(o) => o,
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escaped_braces_are_text() {
+        let (_, sections) = text_line("a \\{b\\} \\\\{c\\} C:\\ {\\i1}c").unwrap();
+        match &sections[..] {
+            [TextSection::Text(a), TextSection::StyleOverride(o), TextSection::Text(c)] => {
+                assert_eq!(a, "a \\{b\\} \\\\{c\\} C:\\ ");
+                assert!(matches!(o[..], [StyleOverride::Italic(true)]));
+                assert_eq!(c, "c");
+            }
+            s => panic!("{:?}", s),
+        }
+    }
+
+    #[test]
+    fn functions_with_wrong_argument_counts_are_kept_as_text() {
+        for (text, tag) in [
+            ("{\\fad(200)}a", "fad(200)"),
+            ("{\\fad(1,2,3)}a", "fad(1,2,3)"),
+            ("{\\org(5)}a", "org(5)"),
+            ("{\\fade(255,0,255,0,100)}a", "fade(255,0,255,0,100)"),
+        ] {
+            match &overrides(text)[..] {
+                [StyleOverride::Other(other)] => assert_eq!(other, tag),
+                o => panic!("{:?}: {:?}", text, o),
+            }
+        }
+    }
+
+    #[test]
+    fn functions() {
+        assert!(matches!(
+            overrides("{\\fad(200,300)}")[..],
+            [StyleOverride::FadeInAndOut { fade_in_for, fade_out_for }]
+                if fade_in_for == Duration::from_millis(200)
+                    && fade_out_for == Duration::from_millis(300)
+        ));
+        assert!(matches!(
+            overrides("{\\org(1.5, 2)}")[..],
+            [StyleOverride::Origin { x, y }] if x == 1.5 && y == 2.0
+        ));
+        assert!(matches!(
+            overrides("{\\fade(255,0,255,0,100,200,300)}")[..],
+            [StyleOverride::Fade { middle_alpha: 0, ending_time, .. }]
+                if ending_time == Duration::from_millis(300)
+        ));
+    }
+
+    #[test]
+    fn fractional_font_size() {
+        let style = borrowed_style(
+            &["Name", "Fontname", "Fontsize"],
+            vec!["A", "Arial", "20.5"],
+        );
+        assert_eq!(style.fontsize, 20.5);
+        let style = borrowed_style(&["Fontsize"], vec!["big"]);
+        assert_eq!(style.fontsize, 0.0);
+    }
+
+    #[test]
+    fn more_values_than_format_fields() {
+        let style = borrowed_style(&["Name", "Fontname"], vec!["A", "Arial", "20", "extra"]);
+        assert_eq!(style.fontname, "Arial");
+
+        let definition = vec!["Start".to_owned(), "End".to_owned(), "Text".to_owned()];
+        let (_, entry) = subtitle("Dialogue: 0:00:01.00,0:00:02.00,a,b,c", &definition).unwrap();
+        assert_eq!(entry.start, Some(Duration::from_secs(1)));
+        assert_eq!(entry.text, "a,b,c");
+
+        let (_, entry) = subtitle("Dialogue: a,b", &["Text".to_owned()]).unwrap();
+        assert_eq!(entry.text, "a,b");
+        assert!(subtitle("Dialogue: a,b", &[]).is_ok());
+    }
+
+    #[test]
+    fn ssa_styles() {
+        let (_, script) = script(
+            "[Script Info]\n\
+             ScriptType: v4.00\n\
+             \n\
+             [V4 Styles]\n\
+             Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, TertiaryColour, \
+             BackColour, Bold, Italic, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, \
+             MarginV, AlphaLevel, Encoding\n\
+             Style: Default,Arial,20,16777215,&H00ffff,&H0000ff,&H000080,-1,0,1,3,0,6,30,30,30,64,0\n\
+             \n\
+             [Events]\n\
+             Format: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+             Dialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0000,0000,0000,,Hello\n",
+        )
+        .unwrap();
+        assert!(script.other.is_empty());
+        let style = &script.styles["Default"];
+        assert_eq!(style.alignment, Some(8));
+        assert_eq!(style.bold, Some(true));
+        let color = |c: &Option<Color>| c.as_ref().map(|c| (c.alpha, c.red, c.green, c.blue));
+        assert_eq!(color(&style.primary_color), Some((Some(64), 255, 255, 255)));
+        assert_eq!(color(&style.secondary_color), Some((Some(64), 255, 255, 0)));
+        // the outline takes the shadow's colour, not TertiaryColour
+        assert_eq!(color(&style.outline_color), Some((Some(64), 128, 0, 0)));
+        assert_eq!(color(&style.back_color), Some((Some(128), 128, 0, 0)));
+        assert_eq!(script.events[0].style.as_deref(), Some("Default"));
+
+        let written = crate::writer::to_string(&script);
+        assert!(written.contains("ScriptType: v4.00+\n"));
+        assert!(!written.contains("[V4 Styles]"));
+        assert!(written.contains("Style: Default,Arial,20,&H40FFFFFF,"));
+    }
+
+    #[test]
+    fn legacy_alignments() {
+        for (ssa, numpad) in [
+            (1, 1),
+            (2, 2),
+            (3, 3),
+            (5, 7),
+            (6, 8),
+            (7, 9),
+            (9, 4),
+            (10, 5),
+            (11, 6),
+        ] {
+            assert_eq!(legacy_alignment(ssa), numpad, "{}", ssa);
+        }
+    }
+
+    #[test]
+    fn colors() {
+        for (text, rgb) in [
+            ("{\\c&HFF0000&}", (0, 0, 255)),
+            ("{\\c&HFF&}", (255, 0, 0)),
+            ("{\\c&H00FF0000&}", (0, 0, 255)),
+            ("{\\1c&H80123456&}", (0x56, 0x34, 0x12)),
+        ] {
+            match &overrides(text)[..] {
+                [StyleOverride::Color(1, c)] => {
+                    assert_eq!((c.red, c.green, c.blue), rgb, "{:?}", text)
+                }
+                o => panic!("{:?}: {:?}", text, o),
+            }
+        }
+        assert!(matches!(
+            &overrides("{\\c&H00FF00é0&}")[..],
+            [StyleOverride::Other(_)]
+        ));
+    }
+
+    #[test]
+    fn unclosed_braces_are_text() {
+        for (line, last) in [
+            ("{", "{"),
+            ("{\\i1 oops", "{\\i1 oops"),
+            ("a{\\b1}b{c", "{c"),
+            ("{\\i1}a{", "{"),
+        ] {
+            let (rest, sections) = text_line(line).unwrap();
+            assert_eq!(rest, "");
+            match sections.last() {
+                Some(TextSection::Text(text)) => assert_eq!(text, last, "{:?}", line),
+                s => panic!("{:?}: {:?}", line, s),
+            }
+        }
+        let (_, sections) = text_line("a{\\b1}b{c").unwrap();
+        assert_eq!(sections.len(), 4);
+    }
+}
@@ -0,0 +1,513 @@
+//! Reading speed and layout guidelines: characters per second (CPS) and per
+//! line (CPL), how long events stay up and the gaps between them.
+//!
+//! Characters are counted as grapheme clusters of the plain text, with
+//! override blocks and drawings left out.
+
+use crate::keyframes::Change;
+use crate::parser::borrowed_text_line;
+use crate::timing::Selection;
+use crate::{unescape_text, Entry, TextSection};
+use std::fmt;
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Limits to check events against, each skipped when `None`. The default has
+/// none set, for building custom guidelines; [`Guidelines::netflix`] and
+/// [`Guidelines::bbc`] are the common ones.
+#[derive(Clone, Debug)]
+pub struct Guidelines {
+    pub selection: Selection,
+    pub max_cps: Option<f64>,
+    pub max_cpl: Option<usize>,
+    pub max_lines: Option<usize>,
+    pub min_duration: Option<Duration>,
+    pub max_duration: Option<Duration>,
+    pub min_gap: Option<Duration>,
+    /// Gaps shorter than this are closed down to `min_gap` by [`fix_gaps`],
+    /// so lines follow each other without a flash in between.
+    pub chain_threshold: Option<Duration>,
+    /// Whether spaces count towards CPS. They always count towards CPL.
+    pub count_spaces: bool,
+}
+
+impl Default for Guidelines {
+    fn default() -> Guidelines {
+        Guidelines {
+            selection: Selection::default(),
+            max_cps: None,
+            max_cpl: None,
+            max_lines: None,
+            min_duration: None,
+            max_duration: None,
+            min_gap: None,
+            chain_threshold: None,
+            count_spaces: true,
+        }
+    }
+}
+
+impl Guidelines {
+    /// Netflix's English timed text style guide: 20 CPS, 42 CPL, two lines,
+    /// 5/6 of a second to 7 seconds on screen, two frames between events and
+    /// gaps under half a second closed.
+    pub fn netflix() -> Guidelines {
+        Guidelines {
+            max_cps: Some(20.0),
+            max_cpl: Some(42),
+            max_lines: Some(2),
+            min_duration: Some(Duration::from_millis(833)),
+            max_duration: Some(Duration::from_secs(7)),
+            min_gap: Some(Duration::from_millis(83)),
+            chain_threshold: Some(Duration::from_millis(500)),
+            ..Guidelines::default()
+        }
+    }
+
+    /// The BBC subtitle guidelines: 37 CPL, two lines, about 180 words a
+    /// minute, at least a second on screen and a frame between events.
+    pub fn bbc() -> Guidelines {
+        Guidelines {
+            max_cps: Some(16.5),
+            max_cpl: Some(37),
+            max_lines: Some(2),
+            min_duration: Some(Duration::from_secs(1)),
+            min_gap: Some(Duration::from_millis(40)),
+            ..Guidelines::default()
+        }
+    }
+
+    /// `netflix` or `bbc`.
+    pub fn preset(name: &str) -> Option<Guidelines> {
+        match name.to_ascii_lowercase().as_str() {
+            "netflix" => Some(Guidelines::netflix()),
+            "bbc" => Some(Guidelines::bbc()),
+            _ => None,
+        }
+    }
+}
+
+/// The plain text of an event, one string per line. `\N` breaks lines,
+/// `\n` and `\h` are read as spaces.
+pub fn plain_lines<S: AsRef<str>>(entry: &Entry<S>) -> Vec<String> {
+    let sections = borrowed_text_line(entry.text.as_ref())
+        .map(|(_, s)| s)
+        .unwrap_or_default();
+    let text: String = sections
+        .iter()
+        .filter_map(|s| match s {
+            TextSection::Text(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect();
+    text.replace("\\n", " ")
+        .replace("\\h", " ")
+        .split("\\N")
+        .map(|l| unescape_text(l.trim()))
+        .collect()
+}
+
+/// How much there is to read in an event and how fast.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stats {
+    /// Characters counted towards CPS.
+    pub characters: usize,
+    /// The length of each line.
+    pub lines: Vec<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+    #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+    pub duration: Duration,
+    /// Zero for events without time to read them in.
+    pub cps: f64,
+}
+
+pub fn stats<S: AsRef<str>>(entry: &Entry<S>, count_spaces: bool) -> Stats {
+    let lines: Vec<String> = plain_lines(entry);
+    let lengths: Vec<usize> = lines.iter().map(|l| l.graphemes(true).count()).collect();
+    let characters = lines
+        .iter()
+        .flat_map(|l| l.graphemes(true))
+        .filter(|g| count_spaces || !g.trim().is_empty())
+        .count();
+    let duration = match (entry.start, entry.end) {
+        (Some(start), Some(end)) => end.saturating_sub(start),
+        _ => Duration::ZERO,
+    };
+    let cps = if duration.is_zero() {
+        0.0
+    } else {
+        characters as f64 / duration.as_secs_f64()
+    };
+    Stats {
+        characters,
+        lines: lengths,
+        duration,
+        cps,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Issue {
+    ReadingSpeed(f64),
+    /// A line, counting from 0, and its length.
+    LineLength {
+        line: usize,
+        length: usize,
+    },
+    TooManyLines(usize),
+    TooShort(
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+        Duration,
+    ),
+    TooLong(
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+        Duration,
+    ),
+    /// Too little time before the event at index `next`.
+    Gap {
+        next: usize,
+        #[cfg_attr(feature = "serde", serde(with = "parsing_utils::millis"))]
+        #[cfg_attr(feature = "schema", schemars(with = "u64"))]
+        gap: Duration,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::ReadingSpeed(cps) => write!(f, "{:.1} characters per second", cps),
+            Issue::LineLength { line, length } => {
+                write!(f, "line {} is {} characters long", line + 1, length)
+            }
+            Issue::TooManyLines(lines) => write!(f, "{} lines", lines),
+            Issue::TooShort(d) => write!(f, "only {}ms on screen", d.as_millis()),
+            Issue::TooLong(d) => write!(f, "{}ms on screen", d.as_millis()),
+            Issue::Gap { next, gap } => {
+                write!(f, "{}ms before event {}", gap.as_millis(), next + 1)
+            }
+        }
+    }
+}
+
+/// An event outside the guidelines.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Violation {
+    pub index: usize,
+    pub issue: Issue,
+}
+
+/// The timed, selected lines that aren't comments, in order of start.
+fn lines(entries: &[Entry], selection: &Selection) -> Vec<usize> {
+    let mut lines: Vec<usize> = (0..entries.len())
+        .filter(|&n| {
+            let e = &entries[n];
+            e.start.is_some()
+                && e.end.is_some()
+                && e.kind.as_deref() != Some("Comment")
+                && selection.matches(e)
+        })
+        .collect();
+    lines.sort_by_key(|&n| entries[n].start);
+    lines
+}
+
+/// Checks every selected event against `guidelines`, in order of start.
+/// Gaps are only checked between events that don't overlap.
+pub fn check(entries: &[Entry], guidelines: &Guidelines) -> Vec<Violation> {
+    let lines = lines(entries, &guidelines.selection);
+    let mut violations = Vec::new();
+
+    for (i, &n) in lines.iter().enumerate() {
+        let entry = &entries[n];
+        let stats = stats(entry, guidelines.count_spaces);
+        let mut add = |issue| violations.push(Violation { index: n, issue });
+
+        if let Some(max) = guidelines.max_cps {
+            if stats.cps > max {
+                add(Issue::ReadingSpeed(stats.cps));
+            }
+        }
+        if let Some(max) = guidelines.max_cpl {
+            for (line, &length) in stats.lines.iter().enumerate() {
+                if length > max {
+                    add(Issue::LineLength { line, length });
+                }
+            }
+        }
+        if let Some(max) = guidelines.max_lines {
+            if stats.lines.len() > max {
+                add(Issue::TooManyLines(stats.lines.len()));
+            }
+        }
+        if let Some(min) = guidelines.min_duration {
+            if stats.duration < min {
+                add(Issue::TooShort(stats.duration));
+            }
+        }
+        if let Some(max) = guidelines.max_duration {
+            if stats.duration > max {
+                add(Issue::TooLong(stats.duration));
+            }
+        }
+        if let (Some(min), Some(&next)) = (guidelines.min_gap, lines.get(i + 1)) {
+            let end = entry.end.unwrap_or_default();
+            let start = entries[next].start.unwrap_or_default();
+            if start >= end && start - end < min {
+                add(Issue::Gap {
+                    next,
+                    gap: start - end,
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Fixes timing in place where it can, returning the events that changed:
+/// events shorter than `min_duration` are extended, and ends too close to
+/// the next event or within `chain_threshold` of it are moved to leave
+/// exactly `min_gap`. Extensions never run into the next event.
+pub fn fix_gaps(entries: &mut [Entry], guidelines: &Guidelines) -> Vec<Change> {
+    let lines = lines(entries, &guidelines.selection);
+    let min_gap = guidelines.min_gap.unwrap_or_default();
+    let mut changes = Vec::new();
+
+    for (i, &n) in lines.iter().enumerate() {
+        let start = entries[n].start.unwrap_or_default();
+        let end = entries[n].end.unwrap_or_default();
+        // the latest this event can end, if the next one doesn't overlap it
+        let limit = lines
+            .get(i + 1)
+            .map(|&next| entries[next].start.unwrap_or_default())
+            .filter(|&next| next >= end)
+            .map(|next| next.saturating_sub(min_gap));
+
+        let mut new_end = end;
+        if let Some(min) = guidelines.min_duration {
+            if end.saturating_sub(start) < min {
+                let wanted = start + min;
+                new_end = limit.map_or(wanted, |limit| wanted.min(limit)).max(end);
+            }
+        }
+        if let Some(limit) = limit {
+            let gap = (limit + min_gap).saturating_sub(new_end);
+            let chain = guidelines.chain_threshold.is_some_and(|t| gap < t);
+            if (gap < min_gap || chain) && limit > start {
+                new_end = limit;
+            }
+        }
+
+        if new_end != end {
+            entries[n].end = Some(new_end);
+            changes.push(Change {
+                index: n,
+                start: (start, start),
+                end: (end, new_end),
+            });
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(start: u64, end: u64, text: &str) -> Entry {
+        Entry {
+            kind: Some("Dialogue".to_owned()),
+            start: Some(Duration::from_millis(start)),
+            end: Some(Duration::from_millis(end)),
+            style: Some("Default".to_owned()),
+            text: text.to_owned(),
+            ..Entry::default()
+        }
+    }
+
+    fn ms(millis: u64) -> Option<Duration> {
+        Some(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn stats_skip_tags_and_count_graphemes() {
+        let stats = stats(&event(0, 2000, "{\\i1}Caf\u{e9}e\u{301}\\Nsix\\hsix"), true);
+        assert_eq!(stats.lines, [5, 7]);
+        assert_eq!(stats.characters, 12);
+        assert_eq!(stats.cps, 6.0);
+
+        let stats = super::stats(&event(0, 1000, "a b c"), false);
+        assert_eq!(stats.characters, 3);
+        assert_eq!(super::stats(&event(0, 0, "abc"), true).cps, 0.0);
+    }
+
+    #[test]
+    fn check_each_limit() {
+        let entries = [
+            event(0, 1000, "far too much to read in a second"),
+            event(2000, 2400, "short"),
+            event(3000, 12000, "long"),
+            event(
+                12050,
+                16000,
+                "one\\Ntwo\\Nthree and a line that is much too long",
+            ),
+            // overlapping events aren't checked for gaps
+            event(13000, 15000, "overlaps"),
+        ];
+        let guidelines = Guidelines {
+            max_cps: Some(20.0),
+            max_cpl: Some(30),
+            max_lines: Some(2),
+            min_duration: ms(500),
+            max_duration: ms(7000),
+            min_gap: ms(83),
+            ..Guidelines::default()
+        };
+        assert_eq!(
+            check(&entries, &guidelines),
+            [
+                Violation {
+                    index: 0,
+                    issue: Issue::ReadingSpeed(32.0),
+                },
+                Violation {
+                    index: 0,
+                    issue: Issue::LineLength {
+                        line: 0,
+                        length: 32
+                    },
+                },
+                Violation {
+                    index: 1,
+                    issue: Issue::TooShort(Duration::from_millis(400)),
+                },
+                Violation {
+                    index: 2,
+                    issue: Issue::TooLong(Duration::from_millis(9000)),
+                },
+                Violation {
+                    index: 2,
+                    issue: Issue::Gap {
+                        next: 3,
+                        gap: Duration::from_millis(50),
+                    },
+                },
+                Violation {
+                    index: 3,
+                    issue: Issue::LineLength {
+                        line: 2,
+                        length: 38
+                    },
+                },
+                Violation {
+                    index: 3,
+                    issue: Issue::TooManyLines(3),
+                },
+            ]
+        );
+        assert_eq!(check(&entries, &Guidelines::default()), []);
+    }
+
+    #[test]
+    fn check_in_order_of_start_skipping_comments() {
+        let mut entries = vec![
+            event(1050, 2000, "b"),
+            event(1000, 1040, "comment"),
+            event(0, 1000, "a"),
+        ];
+        entries[1].kind = Some("Comment".to_owned());
+        let guidelines = Guidelines {
+            min_gap: ms(83),
+            ..Guidelines::default()
+        };
+        assert_eq!(
+            check(&entries, &guidelines),
+            [Violation {
+                index: 2,
+                issue: Issue::Gap {
+                    next: 0,
+                    gap: Duration::from_millis(50),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn fix_gaps_table() {
+        let netflix = Guidelines::netflix();
+        let no_chain = Guidelines {
+            chain_threshold: None,
+            ..Guidelines::netflix()
+        };
+        // each case is two events and the first's end after fixing
+        for (guidelines, first, second, end) in [
+            // closed down to the minimum gap, being under the chain threshold
+            (&netflix, (0, 1000), (1300, 3000), 1217),
+            // left alone, being over it
+            (&netflix, (0, 1000), (1600, 3000), 1000),
+            (&no_chain, (0, 1000), (1300, 3000), 1000),
+            // widened to the minimum gap
+            (&no_chain, (0, 1000), (1050, 3000), 967),
+            // extended to the minimum duration
+            (&no_chain, (0, 300), (2000, 3000), 833),
+            // but only up to the minimum gap before the next event
+            (&no_chain, (0, 300), (500, 3000), 417),
+            // never shortened to before its start
+            (&no_chain, (1000, 1050), (1060, 3000), 1050),
+            // overlapping events are left alone
+            (&netflix, (0, 1000), (500, 3000), 1000),
+        ] {
+            let mut entries = [event(first.0, first.1, "a"), event(second.0, second.1, "b")];
+            let changes = fix_gaps(&mut entries, guidelines);
+            let expected = Duration::from_millis(end);
+            assert_eq!(entries[0].end, Some(expected), "{:?} {:?}", first, second);
+            assert_eq!(entries[1].end, ms(second.1));
+            if end == first.1 {
+                assert_eq!(changes, [], "{:?} {:?}", first, second);
+            } else {
+                assert_eq!(
+                    changes,
+                    [Change {
+                        index: 0,
+                        start: (
+                            Duration::from_millis(first.0),
+                            Duration::from_millis(first.0)
+                        ),
+                        end: (Duration::from_millis(first.1), expected),
+                    }]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fix_gaps_extends_the_last_event_freely() {
+        let mut entries = [event(0, 300, "a")];
+        fix_gaps(&mut entries, &Guidelines::netflix());
+        assert_eq!(entries[0].end, ms(833));
+    }
+
+    #[test]
+    fn fix_gaps_respects_the_selection() {
+        let mut entries = [event(0, 1000, "a"), event(1300, 3000, "b")];
+        entries[0].style = Some("Signs".to_owned());
+        let guidelines = Guidelines {
+            selection: Selection {
+                range: None,
+                styles: Some(vec!["Default".to_owned()]),
+            },
+            ..Guidelines::netflix()
+        };
+        assert_eq!(fix_gaps(&mut entries, &guidelines), []);
+        assert_eq!(entries[0].end, ms(1000));
+    }
+}
@@ -0,0 +1,159 @@
+//! The `serde` representation of the types that don't derive it.
+
+use crate::Color;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+impl Color {
+    /// `#RRGGBB`, or `#RRGGBBAA` with an alpha, where `AA` is the opacity as
+    /// in CSS, the inverse of ASS's transparency.
+    pub fn to_hex(&self) -> String {
+        let rgb = format!("#{:02X}{:02X}{:02X}", self.red, self.green, self.blue);
+        match self.alpha {
+            Some(alpha) => format!("{}{:02X}", rgb, 255 - alpha),
+            None => rgb,
+        }
+    }
+
+    /// Reads what [`Color::to_hex`] writes.
+    pub fn from_hex(hex: &str) -> Option<Color> {
+        let digits = hex.strip_prefix('#')?;
+        if !matches!(digits.len(), 6 | 8) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let byte = |n: usize| u8::from_str_radix(&digits[n..n + 2], 16).ok();
+        Some(Color {
+            red: byte(0)?,
+            green: byte(2)?,
+            blue: byte(4)?,
+            alpha: if digits.len() == 8 {
+                Some(255 - byte(6)?)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Color::from_hex(&hex).ok_or_else(|| {
+            de::Error::custom(format!("expected #RRGGBB or #RRGGBBAA, found {:?}", hex))
+        })
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for Color {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Color".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "pattern": "^#[0-9A-Fa-f]{6}([0-9A-Fa-f]{2})?$",
+            "description": "#RRGGBB, or #RRGGBBAA with AA the opacity"
+        })
+    }
+}
+
+/// The JSON schema of a [`crate::Script`], as written with the `serde`
+/// feature.
+#[cfg(feature = "schema")]
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(crate::Script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser, writer, Script};
+
+    #[test]
+    fn hex_colors() {
+        for (color, hex) in [
+            (
+                Color {
+                    red: 0x12,
+                    green: 0xAB,
+                    blue: 0xFF,
+                    alpha: None,
+                },
+                "#12ABFF",
+            ),
+            (
+                Color {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                    alpha: Some(0),
+                },
+                "#000000FF",
+            ),
+            (
+                Color {
+                    red: 255,
+                    green: 255,
+                    blue: 255,
+                    alpha: Some(0x80),
+                },
+                "#FFFFFF7F",
+            ),
+        ] {
+            assert_eq!(color.to_hex(), hex);
+            assert_eq!(
+                format!("{:?}", Color::from_hex(hex)),
+                format!("{:?}", Some(color.clone())),
+                "{}",
+                hex
+            );
+            assert_eq!(serde_json::to_string(&color).unwrap(), format!("{:?}", hex));
+        }
+        assert_eq!(
+            Color::from_hex("#abcdef").map(|c| (c.red, c.green, c.blue)),
+            Some((0xAB, 0xCD, 0xEF))
+        );
+        for hex in [
+            "", "#", "123456", "#12345", "#1234567", "#12345G", "#+12345",
+        ] {
+            assert!(Color::from_hex(hex).is_none(), "{:?}", hex);
+            assert!(
+                serde_json::from_str::<Color>(&format!("{:?}", hex)).is_err(),
+                "{:?}",
+                hex
+            );
+        }
+    }
+
+    #[test]
+    fn scripts_round_trip_through_json() {
+        let input = "[Script Info]\nTitle: JSON\nPlayResX: 640\n\n\
+                     [V4+ Styles]\n\
+                     Format: Name, Fontname, Fontsize, PrimaryColour, OutlineColour, Bold, Alignment\n\
+                     Style: Default,Arial,20,&H00FFFFFF,&H80000000,0,2\n\
+                     Style: Top,Arial,18.5,&H000000FF,&H00000000,-1,8\n\n\
+                     [Events]\n\
+                     Format: Layer, Start, End, Style, Name, Effect, Text\n\
+                     Dialogue: 0,0:00:01.50,0:00:02.00,Default,,,One {\\i1}two\n\
+                     Comment: 1,1:02:03.04,1:02:05.00,Top,Someone,Banner;10,\\{not a tag\\}\\Nthree\n\n\
+                     [Aegisub Project Garbage]\nActive Line: 2\n";
+        let (_, script) = parser::script(input).unwrap();
+        let json = serde_json::to_string(&script).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["events"][0]["start"], 1500);
+        assert_eq!(value["events"][1]["end"], 3725000);
+        assert_eq!(value["styles"]["Default"]["outline_color"], "#0000007F");
+
+        let read: Script = serde_json::from_str(&json).unwrap();
+        assert_eq!(writer::to_string(&read), writer::to_string(&script));
+        assert_eq!(format!("{:?}", read.events), format!("{:?}", script.events));
+    }
+}
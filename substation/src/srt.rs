@@ -0,0 +1,446 @@
+//! Conversion between SubRip entries and ASS scripts.
+
+use crate::{
+    escape_text, unescape_text, Color, Entry, Script, Style, StyleOverride, TextSection, Timestamp,
+};
+use subrip::text::{Span, TextStyle};
+
+#[derive(Clone, Debug)]
+pub struct FromSrtOptions {
+    /// The style every event uses, also written to `[V4+ Styles]`.
+    pub style: Style,
+    pub play_res_x: usize,
+    pub play_res_y: usize,
+    pub title: Option<String>,
+}
+
+impl Default for FromSrtOptions {
+    fn default() -> FromSrtOptions {
+        FromSrtOptions {
+            style: Style {
+                name: "Default".to_owned(),
+                fontname: "Arial".to_owned(),
+                fontsize: 48.0,
+                primary_color: Some(Color {
+                    alpha: Some(0),
+                    red: 255,
+                    green: 255,
+                    blue: 255,
+                }),
+                secondary_color: Some(Color {
+                    alpha: Some(0),
+                    red: 255,
+                    green: 0,
+                    blue: 0,
+                }),
+                outline_color: Some(Color {
+                    alpha: Some(0),
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                }),
+                back_color: Some(Color {
+                    alpha: Some(0),
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                }),
+                bold: Some(false),
+                italic: Some(false),
+                underline: Some(false),
+                strikeout: Some(false),
+                scale_x: Some(100),
+                scale_y: Some(100),
+                spacing: Some(0),
+                angle: Some(0.0),
+                border_style: Some(1),
+                outline_size: Some(2),
+                shadow: Some(2),
+                alignment: Some(2),
+                margin_l: Some(10),
+                margin_r: Some(10),
+                margin_v: Some(10),
+                encoding: Some(1),
+            },
+            play_res_x: 1920,
+            play_res_y: 1080,
+            title: None,
+        }
+    }
+}
+
+fn ass_color(red: u8, green: u8, blue: u8) -> String {
+    format!("&H{:02X}{:02X}{:02X}&", blue, green, red)
+}
+
+fn overrides(from: &TextStyle, to: &TextStyle, style: &Style) -> String {
+    let mut tags = String::new();
+    let mut toggle = |tag: &str, from: bool, to: bool| {
+        if from != to {
+            tags.push_str(&format!("\\{}{}", tag, to as u8));
+        }
+    };
+    toggle("b", from.bold, to.bold);
+    toggle("i", from.italic, to.italic);
+    toggle("u", from.underline, to.underline);
+    toggle("s", from.strikeout, to.strikeout);
+
+    if from.font.rgb() != to.font.rgb() {
+        let (r, g, b) = to.font.rgb().unwrap_or_else(|| {
+            let c = style.primary_color.clone().unwrap_or(Color {
+                alpha: None,
+                red: 255,
+                green: 255,
+                blue: 255,
+            });
+            (c.red, c.green, c.blue)
+        });
+        tags.push_str(&format!("\\c{}", ass_color(r, g, b)));
+    }
+    if from.font.face != to.font.face {
+        let face = to.font.face.as_deref().unwrap_or(&style.fontname);
+        tags.push_str(&format!("\\fn{}", face));
+    }
+    let size = |s: &TextStyle| {
+        s.font
+            .size
+            .as_deref()
+            .and_then(|v| v.trim().parse::<f64>().ok())
+    };
+    if size(from) != size(to) {
+        match size(to) {
+            Some(size) => tags.push_str(&format!("\\fs{}", size)),
+            None => tags.push_str(&format!("\\fs{}", style.fontsize)),
+        }
+    }
+
+    tags
+}
+
+/// Converts SubRip text to ASS: formatting tags become override tags
+/// relative to `style`, `{\anN}` is kept as an override and newlines become
+/// `\N`. Braces and backslashes left in the text are escaped with
+/// [`escape_text`].
+pub fn ass_text(entry: &subrip::Entry, style: &Style) -> String {
+    let mut text = String::new();
+    if let Some(alignment) = entry.alignment {
+        text.push_str(&format!("{{\\an{}}}", alignment));
+    }
+
+    let plain = TextStyle::default();
+    let mut current = &plain;
+    let spans: Vec<Span> = entry.parsed_text();
+    for span in &spans {
+        let tags = overrides(current, &span.style, style);
+        if !tags.is_empty() {
+            if text.ends_with('}') {
+                text.pop();
+                text.push_str(&tags);
+                text.push('}');
+            } else {
+                text.push('{');
+                text.push_str(&tags);
+                text.push('}');
+            }
+        }
+        let lines: Vec<String> = span
+            .text
+            .replace("\r\n", "\n")
+            .split('\n')
+            .map(escape_text)
+            .collect();
+        text.push_str(&lines.join("\\N"));
+        current = &span.style;
+    }
+
+    text
+}
+
+/// Builds a script out of SubRip entries with a single style and a
+/// `[Script Info]` that Aegisub opens without complaint.
+pub fn from_srt(entries: &[subrip::Entry], options: &FromSrtOptions) -> Script {
+    let mut script = Script::default();
+
+    let info = [
+        ("Title", options.title.clone().unwrap_or_default()),
+        ("ScriptType", "v4.00+".to_owned()),
+        ("WrapStyle", "0".to_owned()),
+        ("ScaledBorderAndShadow", "yes".to_owned()),
+        ("PlayResX", options.play_res_x.to_string()),
+        ("PlayResY", options.play_res_y.to_string()),
+    ];
+    script.info = info
+        .into_iter()
+        .filter(|(_, v)| !v.is_empty())
+        .map(|(k, v)| (k.to_owned(), v))
+        .collect();
+    script
+        .styles
+        .insert(options.style.name.clone(), options.style.clone());
+
+    script.events = entries
+        .iter()
+        .map(|e| Entry {
+            kind: Some("Dialogue".to_owned()),
+            layer: Some(0),
+            start: Some(Timestamp::from_duration(e.start).as_duration()),
+            end: Some(Timestamp::from_duration(e.end).as_duration()),
+            style: Some(options.style.name.clone()),
+            name: None,
+            margin_l: Some(0),
+            margin_r: Some(0),
+            margin_v: Some(0),
+            effect: None,
+            read_order: None,
+            text: ass_text(e, &options.style),
+        })
+        .collect();
+
+    script
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ToSrtOptions {
+    /// Styles whose events are left out, e.g. signs and songs.
+    pub exclude_styles: Vec<String>,
+    /// Leaves out events placed with `\pos` or `\move`, which are usually
+    /// typesetting rather than dialogue.
+    pub exclude_positioned: bool,
+    /// Joins events with the same start and end into one cue, one line each.
+    pub merge_simultaneous: bool,
+}
+
+fn base_style(style: Option<&Style>) -> TextStyle {
+    let mut base = TextStyle::default();
+    if let Some(style) = style {
+        base.bold = style.bold.unwrap_or(false);
+        base.italic = style.italic.unwrap_or(false);
+        base.underline = style.underline.unwrap_or(false);
+        base.strikeout = style.strikeout.unwrap_or(false);
+    }
+    base
+}
+
+fn is_positioned(sections: &[TextSection]) -> bool {
+    sections.iter().any(|s| match s {
+        TextSection::StyleOverride(overrides) => overrides
+            .iter()
+            .any(|o| matches!(o, StyleOverride::Move { .. })),
+        _ => false,
+    })
+}
+
+/// Flattens an event's text into SubRip spans: `\b`, `\i`, `\u`, `\s` and
+/// primary `\c` become formatting, `\N` a newline and `\h` a space. Drawings
+/// and every other tag are dropped. Returns the `\an` alignment too, if any.
+pub fn srt_spans(
+    entry: &Entry,
+    styles: &std::collections::HashMap<String, Style>,
+) -> (Vec<Span>, Option<u8>) {
+    let line_style = entry.style.as_deref().and_then(|s| styles.get(s));
+    let base = base_style(line_style);
+    let mut current = base.clone();
+    let mut alignment = line_style
+        .and_then(|s| s.alignment)
+        .filter(|&a| a != 2)
+        .map(|a| a as u8);
+    let mut aligned = false;
+    let mut spans: Vec<Span> = Vec::new();
+
+    for section in entry.parsed_text() {
+        match section {
+            TextSection::StyleOverride(overrides) => {
+                for o in overrides {
+                    match o {
+                        StyleOverride::Bold(weight) => {
+                            current.bold = weight == 1.0 || weight >= 600.0
+                        }
+                        StyleOverride::Italic(v) => current.italic = v,
+                        StyleOverride::Underline(v) => current.underline = v,
+                        StyleOverride::StrikeOut(v) => current.strikeout = v,
+                        StyleOverride::Color(1, c) => {
+                            let primary = line_style.and_then(|s| s.primary_color.as_ref());
+                            let is_base = primary.is_some_and(|p| {
+                                (p.red, p.green, p.blue) == (c.red, c.green, c.blue)
+                            });
+                            current.font.color = (!is_base)
+                                .then(|| format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue));
+                        }
+                        // like renderers, only the first \an of a line counts
+                        StyleOverride::NumpadLayoutAlignment(a) if !aligned => {
+                            alignment = Some(a as u8).filter(|&a| a != 2);
+                            aligned = true;
+                        }
+                        StyleOverride::Reset(name) => {
+                            current = base_style(
+                                name.as_deref().and_then(|n| styles.get(n)).or(line_style),
+                            )
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            TextSection::Text(text) => {
+                let text = unescape_text(
+                    &text
+                        .replace("\\N", "\n")
+                        .replace("\\n", " ")
+                        .replace("\\h", " "),
+                );
+                match spans.last_mut() {
+                    Some(last) if last.style == current => last.text.push_str(&text),
+                    _ => spans.push(Span {
+                        text,
+                        style: current.clone(),
+                    }),
+                }
+            }
+            TextSection::Drawing(_) => (),
+        }
+    }
+
+    (spans, alignment)
+}
+
+/// Converts a script's events to SubRip cues, sorted by start time and
+/// numbered from 1. Comments, drawings and empty lines are dropped.
+pub fn to_srt(script: &Script, options: &ToSrtOptions) -> Vec<subrip::Entry> {
+    let mut cues: Vec<(usize, subrip::Entry)> = Vec::new();
+
+    for (n, event) in script.events.iter().enumerate() {
+        if event.kind.as_deref() == Some("Comment") {
+            continue;
+        }
+        if event
+            .style
+            .as_ref()
+            .is_some_and(|s| options.exclude_styles.contains(s))
+        {
+            continue;
+        }
+        if options.exclude_positioned && is_positioned(&event.parsed_text()) {
+            continue;
+        }
+
+        let (spans, alignment) = srt_spans(event, &script.styles);
+        let text = subrip::text::write(&spans);
+        let text = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.is_empty() {
+            continue;
+        }
+
+        let start = Timestamp::from_duration(event.start.unwrap_or_default());
+        let end = Timestamp::from_duration(event.end.unwrap_or_default());
+        cues.push((
+            n,
+            subrip::Entry {
+                index: 0,
+                start: start.as_duration(),
+                end: end.as_duration(),
+                alignment,
+                coordinates: None,
+                text,
+            },
+        ));
+    }
+
+    // events with the same times end up next to each other for merging
+    cues.sort_by_key(|(n, c)| (c.start, c.end, *n));
+
+    let mut entries: Vec<subrip::Entry> = Vec::with_capacity(cues.len());
+    for (_, cue) in cues {
+        match entries.last_mut() {
+            Some(last)
+                if options.merge_simultaneous && last.start == cue.start && last.end == cue.end =>
+            {
+                last.text.push('\n');
+                last.text.push_str(&cue.text);
+                last.alignment = last.alignment.or(cue.alignment);
+            }
+            _ => entries.push(cue),
+        }
+    }
+    for (n, entry) in entries.iter_mut().enumerate() {
+        entry.index = n as u32 + 1;
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn cue(text: &str) -> subrip::Entry {
+        subrip::Entry {
+            index: 1,
+            start: Duration::from_secs(1),
+            end: Duration::from_secs(2),
+            alignment: None,
+            coordinates: None,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn braces_and_backslashes_are_escaped() {
+        let script = from_srt(
+            &[cue("use {\\b1}lit and C:\\new\n<b>\\h{}</b>")],
+            &FromSrtOptions::default(),
+        );
+        let text = &script.events[0].text;
+        assert_eq!(
+            text,
+            "use \\{\\b1\\}lit and C:\\\u{200d}new\\N{\\b1}\\\u{200d}h\\{\\}"
+        );
+        // nothing in it is read as an override block
+        let overrides = script.events[0]
+            .parsed_text()
+            .into_iter()
+            .filter(|s| matches!(s, TextSection::StyleOverride(_)))
+            .count();
+        assert_eq!(overrides, 1);
+
+        let back = to_srt(&script, &ToSrtOptions::default());
+        assert_eq!(back[0].text, "use {\\b1}lit and C:\\new\n<b>\\h{}</b>");
+    }
+
+    #[test]
+    fn round_trip() {
+        for text in ["plain", "a\\\\nb", "<i>x</i> \\{y\\}", "C:\\", "{"] {
+            let script = from_srt(&[cue(text)], &FromSrtOptions::default());
+            let back = to_srt(&script, &ToSrtOptions::default());
+            assert_eq!(back[0].text, text, "{:?}", script.events[0].text);
+        }
+    }
+
+    #[test]
+    fn simultaneous_events_merge_around_others() {
+        let timed = |text: &str, end: u64| subrip::Entry {
+            start: Duration::ZERO,
+            end: Duration::from_secs(end),
+            ..cue(text)
+        };
+        let script = from_srt(
+            &[timed("A", 2), timed("B", 3), timed("C", 2)],
+            &FromSrtOptions::default(),
+        );
+        let options = ToSrtOptions {
+            merge_simultaneous: true,
+            ..ToSrtOptions::default()
+        };
+        let back = to_srt(&script, &options);
+        assert_eq!(
+            back.iter()
+                .map(|e| (e.index, e.end.as_secs(), e.text.as_str()))
+                .collect::<Vec<_>>(),
+            [(1, 2, "A\nC"), (2, 3, "B")]
+        );
+    }
+}
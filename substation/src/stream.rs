@@ -0,0 +1,213 @@
+//! Reading and writing scripts one event at a time, for scripts too big to
+//! hold in memory, such as karaoke with millions of generated lines.
+//!
+//! Everything but the events, which is small, is still read into a
+//! [`Script`] as the header.
+
+use crate::attachments::is_section_header;
+use crate::parser::{section, subtitle};
+use crate::writer::{write_header, write_other};
+use crate::{Entry, Script, Section};
+use std::io::{self, BufRead, Write};
+
+/// An iterator over the events of a script, reading its input a line at a
+/// time. Event lines that don't parse are skipped, as [`crate::parser::script`]
+/// does.
+pub struct EventReader<R> {
+    reader: R,
+    line: String,
+    first_line: bool,
+    header: Script,
+    // the text of the section being read, parsed once it ends
+    section: Option<String>,
+    in_events: bool,
+    definition: Option<Vec<String>>,
+}
+
+impl<R: BufRead> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader {
+            reader,
+            line: String::new(),
+            first_line: true,
+            header: Script::default(),
+            section: None,
+            in_events: false,
+            definition: None,
+        }
+    }
+
+    /// Reads up to the first event and returns the script so far: its info,
+    /// styles and any sections before `[Events]`.
+    pub fn header(&mut self) -> io::Result<&Script> {
+        if self.definition.is_none() {
+            self.advance(true)?;
+        }
+        Ok(&self.header)
+    }
+
+    /// The script without its events, including any sections after them
+    /// once the reader has reached the end.
+    pub fn into_header(self) -> Script {
+        self.header
+    }
+
+    fn finish_section(&mut self) {
+        let Some(text) = self.section.take() else {
+            return;
+        };
+        if let Ok((_, parsed)) = section(&text) {
+            match parsed {
+                Section::Other { name, settings } if name == "Script Info" => {
+                    self.header.info.extend(settings)
+                }
+                Section::Styles(styles) => self.header.styles.extend(styles),
+                other => self.header.other.push(other),
+            }
+        }
+    }
+
+    // reads lines until an event, the end of the input or, with
+    // `until_events`, the `[Events]` format line
+    fn advance(&mut self, until_events: bool) -> io::Result<Option<Entry>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                self.finish_section();
+                return Ok(None);
+            }
+            if std::mem::take(&mut self.first_line) && self.line.starts_with('\u{feff}') {
+                self.line.drain(..'\u{feff}'.len_utf8());
+            }
+            let line = self.line.trim_end_matches(['\r', '\n']);
+
+            if is_section_header(line) {
+                let header = format!("{}\n", line);
+                self.finish_section();
+                self.definition = None;
+                self.in_events = header.starts_with("[Events]");
+                if !self.in_events {
+                    self.section = Some(header);
+                }
+                continue;
+            }
+
+            if self.in_events {
+                if line.trim().is_empty() || line.starts_with(';') {
+                    continue;
+                }
+                match &self.definition {
+                    None if line.starts_with("Format:") => {
+                        if let Ok((_, Section::EventsHeader(fields))) =
+                            section(&format!("[Events]\n{}", line))
+                        {
+                            self.definition = Some(fields);
+                            if until_events {
+                                return Ok(None);
+                            }
+                        }
+                    }
+                    None => (),
+                    Some(fields) => {
+                        if let Ok((_, entry)) = subtitle(line, fields) {
+                            return Ok(Some(entry));
+                        }
+                    }
+                }
+            } else if let Some(text) = &mut self.section {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<io::Result<Entry>> {
+        self.advance(false).transpose()
+    }
+}
+
+/// Writes a script one event at a time: the header when created, then each
+/// event as it comes.
+pub struct EventWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> EventWriter<W> {
+    /// Writes the info and styles of `header`, ignoring its events.
+    pub fn new(mut writer: W, header: &Script) -> io::Result<EventWriter<W>> {
+        write_header(&mut writer, header)?;
+        Ok(EventWriter { writer })
+    }
+
+    pub fn write(&mut self, event: &Entry) -> io::Result<()> {
+        writeln!(self.writer, "{}", event)
+    }
+
+    /// Writes the other sections of `header`, which go after the events, and
+    /// returns the writer. When streaming from an [`EventReader`], those are
+    /// only known once it is done, from [`EventReader::into_header`].
+    pub fn finish(mut self, header: &Script) -> io::Result<W> {
+        write_other(&mut self.writer, &header.other)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser, writer};
+
+    const SCRIPT: &str = "\u{feff}[Script Info]\r\n\
+                          Title: Streamed\r\n\
+                          PlayResX: 640\r\n\
+                          \r\n\
+                          [V4+ Styles]\r\n\
+                          Format: Name, Fontname, Fontsize, PrimaryColour, Bold\r\n\
+                          Style: Default,Arial,20,&H00FFFFFF,0\r\n\
+                          Style: Top,Arial,18.5,&H000000FF,-1\r\n\
+                          \r\n\
+                          [Events]\r\n\
+                          ; a comment\r\n\
+                          Format: Layer, Start, End, Style, Text\r\n\
+                          Dialogue: 0,0:00:01.00,0:00:02.00,Default,One, {\\i1}two\r\n\
+                          Comment: 0,0:00:02.00,0:00:03.00,Top,Hidden\r\n\
+                          not an event\r\n\
+                          \r\n\
+                          Dialogue: 1,0:00:03.00,0:00:04.50,Top,Three\r\n\
+                          \r\n\
+                          [Aegisub Project Garbage]\r\n\
+                          Active Line: 2\r\n";
+
+    #[test]
+    fn events_match_the_whole_script() {
+        let (_, parsed) = parser::script(SCRIPT).unwrap();
+        let mut reader = EventReader::new(SCRIPT.as_bytes());
+        assert_eq!(reader.header().unwrap().styles.len(), 2);
+        let events: Vec<Entry> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(format!("{:?}", events), format!("{:?}", parsed.events));
+
+        let streamed = Script {
+            events,
+            ..reader.into_header()
+        };
+        assert_eq!(writer::to_string(&streamed), writer::to_string(&parsed));
+    }
+
+    #[test]
+    fn writes_what_write_script_does() {
+        let (_, parsed) = parser::script(SCRIPT).unwrap();
+        let mut reader = EventReader::new(SCRIPT.as_bytes());
+        let mut events = EventWriter::new(Vec::new(), reader.header().unwrap()).unwrap();
+        for event in reader.by_ref() {
+            events.write(&event.unwrap()).unwrap();
+        }
+        let out = events.finish(&reader.into_header()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), writer::to_string(&parsed));
+    }
+}
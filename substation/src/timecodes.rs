@@ -0,0 +1,505 @@
+use crate::{Entry, Timestamp};
+use std::fmt;
+use std::time::Duration;
+
+/// How a time maps onto a frame, following Aegisub's conventions.
+///
+/// An `Exact` time is the instant a frame starts being displayed. `Start` and
+/// `End` times are what subtitle events use: an event starting at a frame
+/// begins halfway between the previous frame and that one, and an event
+/// ending at a frame ends halfway between that frame and the next, so the
+/// event survives rounding to centiseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeKind {
+    Exact,
+    Start,
+    End,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimecodesError {
+    InvalidFramerate(f64),
+    UnknownFormat,
+    InvalidLine { line: usize, content: String },
+    NotIncreasing { frame: usize },
+    Empty,
+}
+
+impl fmt::Display for TimecodesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimecodesError::InvalidFramerate(fps) => write!(f, "invalid framerate {}", fps),
+            TimecodesError::UnknownFormat => write!(f, "not a v1 or v2 timecode file"),
+            TimecodesError::InvalidLine { line, content } => {
+                write!(f, "invalid timecode on line {}: {:?}", line, content)
+            }
+            TimecodesError::NotIncreasing { frame } => {
+                write!(f, "frame {} does not start after the previous one", frame)
+            }
+            TimecodesError::Empty => write!(f, "timecode file contains no frames"),
+        }
+    }
+}
+
+impl std::error::Error for TimecodesError {}
+
+/// Frame timing of a video, either at a constant rate or from an mkvmerge
+/// timecode file.
+///
+/// Frames past the end of a timecode list are extrapolated at `fps`, which
+/// for v1 files is the assumed framerate and for v2 files the average.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timecodes {
+    timecodes: Vec<i64>,
+    fps: f64,
+}
+
+impl Timecodes {
+    pub fn cfr(fps: f64) -> Result<Timecodes, TimecodesError> {
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(TimecodesError::InvalidFramerate(fps));
+        }
+        Ok(Timecodes {
+            timecodes: vec![0],
+            fps,
+        })
+    }
+
+    /// Builds variable framerate timing from the start time of every frame,
+    /// in milliseconds.
+    pub fn from_timecodes(timecodes: Vec<i64>) -> Result<Timecodes, TimecodesError> {
+        if timecodes.is_empty() {
+            return Err(TimecodesError::Empty);
+        }
+        if let Some(n) = timecodes.windows(2).position(|w| w[1] <= w[0]) {
+            return Err(TimecodesError::NotIncreasing { frame: n + 1 });
+        }
+
+        let fps = if timecodes.len() > 1 {
+            let span = timecodes[timecodes.len() - 1] - timecodes[0];
+            (timecodes.len() - 1) as f64 * 1000.0 / span as f64
+        } else {
+            // a single frame says nothing about the rate, fall back to film
+            24000.0 / 1001.0
+        };
+
+        Ok(Timecodes { timecodes, fps })
+    }
+
+    /// Parses an mkvmerge v1 or v2 timecode file.
+    pub fn parse(input: &str) -> Result<Timecodes, TimecodesError> {
+        let input = input.trim_start_matches('\u{feff}');
+        let mut lines = input.lines().enumerate();
+        let header = lines
+            .find(|(_, l)| !l.trim().is_empty())
+            .map(|(_, l)| l.trim().to_ascii_lowercase());
+
+        match header.as_deref() {
+            Some("# timecode format v1") => parse_v1(lines),
+            Some("# timecode format v2") | Some("# timestamp format v2") => parse_v2(lines),
+            _ => Err(TimecodesError::UnknownFormat),
+        }
+    }
+
+    /// The framerate used for frames outside of the timecode list.
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    pub fn is_cfr(&self) -> bool {
+        self.timecodes.len() == 1
+    }
+
+    /// The frame `time` falls on, per the rules of `kind`.
+    pub fn frame_at(&self, time: Duration, kind: TimeKind) -> i64 {
+        self.frame_at_ms(time.as_millis() as i64, kind)
+    }
+
+    /// The time frame `frame` begins at for `kind`. Times before the first
+    /// frame clamp to zero.
+    pub fn time_at(&self, frame: i64, kind: TimeKind) -> Duration {
+        Duration::from_millis(self.time_at_ms(frame, kind).max(0) as u64)
+    }
+
+    pub fn frame_at_ms(&self, ms: i64, kind: TimeKind) -> i64 {
+        match kind {
+            TimeKind::Start => self.frame_at_ms(ms - 1, TimeKind::Exact) + 1,
+            TimeKind::End => self.frame_at_ms(ms - 1, TimeKind::Exact),
+            TimeKind::Exact => {
+                let first = self.timecodes[0];
+                let last = self.timecodes[self.timecodes.len() - 1];
+                if ms < first {
+                    cfr_frame_at(ms - first, self.fps)
+                } else if ms >= last {
+                    self.timecodes.len() as i64 - 1 + cfr_frame_at(ms - last, self.fps)
+                } else {
+                    self.timecodes.partition_point(|&t| t <= ms) as i64 - 1
+                }
+            }
+        }
+    }
+
+    pub fn time_at_ms(&self, frame: i64, kind: TimeKind) -> i64 {
+        match kind {
+            TimeKind::Start => {
+                let prev = self.time_at_ms(frame - 1, TimeKind::Exact);
+                let cur = self.time_at_ms(frame, TimeKind::Exact);
+                // + 1 so two frames 1ms apart still round up
+                prev + (cur - prev + 1) / 2
+            }
+            TimeKind::End => {
+                let cur = self.time_at_ms(frame, TimeKind::Exact);
+                let next = self.time_at_ms(frame + 1, TimeKind::Exact);
+                cur + (next - cur + 1) / 2
+            }
+            TimeKind::Exact => {
+                let len = self.timecodes.len() as i64;
+                if frame < 0 {
+                    self.timecodes[0] + cfr_time_at(frame, self.fps)
+                } else if frame >= len {
+                    self.timecodes[len as usize - 1] + cfr_time_at(frame - len + 1, self.fps)
+                } else {
+                    self.timecodes[frame as usize]
+                }
+            }
+        }
+    }
+
+    /// The first and last frame `entry` is visible on.
+    pub fn entry_frames(&self, entry: &Entry) -> Option<(i64, i64)> {
+        Some((
+            self.frame_at(entry.start?, TimeKind::Start),
+            self.frame_at(entry.end?, TimeKind::End),
+        ))
+    }
+
+    /// Times `entry` to be visible from frame `start` through frame `end`,
+    /// rounded to centiseconds the way Aegisub writes them.
+    pub fn set_entry_frames(&self, entry: &mut Entry, start: i64, end: i64) {
+        entry.start = Some(centiseconds(self.time_at_ms(start, TimeKind::Start)));
+        entry.end = Some(centiseconds(self.time_at_ms(end, TimeKind::End)));
+    }
+
+    /// Moves every event's start and end onto the frame boundaries they
+    /// already fall on, so no line appears or disappears a frame off once
+    /// rounded to centiseconds.
+    pub fn snap(&self, entries: &mut [Entry]) {
+        for entry in entries.iter_mut() {
+            if let Some((start, end)) = self.entry_frames(entry) {
+                self.set_entry_frames(entry, start, end);
+            }
+        }
+    }
+}
+
+fn centiseconds(ms: i64) -> Duration {
+    Timestamp::from_millis_rounded(ms).as_duration()
+}
+
+fn cfr_time_at(frame: i64, fps: f64) -> i64 {
+    (frame as f64 * 1000.0 / fps).round() as i64
+}
+
+// the last frame starting at or before `ms`, consistent with `cfr_time_at`'s
+// rounding
+fn cfr_frame_at(ms: i64, fps: f64) -> i64 {
+    let mut frame = (ms as f64 * fps / 1000.0).floor() as i64;
+    while cfr_time_at(frame + 1, fps) <= ms {
+        frame += 1;
+    }
+    while cfr_time_at(frame, fps) > ms {
+        frame -= 1;
+    }
+    frame
+}
+
+fn invalid(line: usize, content: &str) -> TimecodesError {
+    TimecodesError::InvalidLine {
+        line: line + 1,
+        content: content.to_owned(),
+    }
+}
+
+fn parse_fps(n: usize, line: &str, value: &str) -> Result<f64, TimecodesError> {
+    match value.trim().parse::<f64>() {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => Ok(fps),
+        _ => Err(invalid(n, line)),
+    }
+}
+
+// # timecode format v1
+// Assume 23.976
+// start,end,fps
+fn parse_v1<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Timecodes, TimecodesError> {
+    let mut assume = None;
+    let mut ranges: Vec<(i64, i64, f64)> = Vec::new();
+
+    for (n, line) in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if assume.is_none() {
+            let value = trimmed
+                .strip_prefix("Assume ")
+                .or_else(|| trimmed.strip_prefix("assume "))
+                .ok_or_else(|| invalid(n, line))?;
+            assume = Some(parse_fps(n, line, value)?);
+            continue;
+        }
+
+        let fields: Vec<&str> = trimmed.split(',').collect();
+        if fields.len() != 3 {
+            return Err(invalid(n, line));
+        }
+        let start = fields[0]
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| invalid(n, line))?;
+        let end = fields[1]
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| invalid(n, line))?;
+        let fps = parse_fps(n, line, fields[2])?;
+        if start < 0 || end < start {
+            return Err(invalid(n, line));
+        }
+        if ranges
+            .last()
+            .is_some_and(|&(_, prev_end, _)| start <= prev_end)
+        {
+            return Err(invalid(n, line));
+        }
+        ranges.push((start, end, fps));
+    }
+
+    let assume = assume.ok_or(TimecodesError::Empty)?;
+    let frames = ranges.last().map_or(1, |&(_, end, _)| end + 2);
+
+    let mut timecodes = Vec::with_capacity(frames as usize);
+    let mut ranges = ranges.into_iter().peekable();
+    let mut time = 0.0_f64;
+    for frame in 0..frames {
+        while ranges.peek().is_some_and(|&(_, end, _)| end < frame) {
+            ranges.next();
+        }
+        timecodes.push(time.round() as i64);
+        let fps = match ranges.peek() {
+            Some(&(start, _, fps)) if start <= frame => fps,
+            _ => assume,
+        };
+        time += 1000.0 / fps;
+    }
+
+    Ok(Timecodes {
+        timecodes,
+        fps: assume,
+    })
+}
+
+// # timecode format v2
+// one frame start time in milliseconds per line
+fn parse_v2<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Timecodes, TimecodesError> {
+    let mut timecodes = Vec::new();
+    for (n, line) in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let ms = trimmed.parse::<f64>().map_err(|_| invalid(n, line))?;
+        let ms = ms.round() as i64;
+        if timecodes.last().is_some_and(|&prev| ms <= prev) {
+            return Err(invalid(n, line));
+        }
+        timecodes.push(ms);
+    }
+    Timecodes::from_timecodes(timecodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NTSC_FILM: f64 = 24000.0 / 1001.0;
+
+    #[test]
+    fn cfr_times() {
+        let timecodes = Timecodes::cfr(NTSC_FILM).unwrap();
+        // frame, exact, start, end
+        for (frame, exact, start, end) in [
+            (0, 0, -21, 21),
+            (1, 42, 21, 63),
+            (2, 83, 63, 104),
+            (3, 125, 104, 146),
+            (24, 1001, 980, 1022),
+            (1000, 41708, 41688, 41729),
+            (-1, -42, -62, -21),
+        ] {
+            assert_eq!(
+                timecodes.time_at_ms(frame, TimeKind::Exact),
+                exact,
+                "{}",
+                frame
+            );
+            assert_eq!(
+                timecodes.time_at_ms(frame, TimeKind::Start),
+                start,
+                "{}",
+                frame
+            );
+            assert_eq!(timecodes.time_at_ms(frame, TimeKind::End), end, "{}", frame);
+        }
+        assert_eq!(timecodes.time_at(-1, TimeKind::Exact), Duration::ZERO);
+    }
+
+    #[test]
+    fn cfr_frames() {
+        let timecodes = Timecodes::cfr(NTSC_FILM).unwrap();
+        // ms, exact, start, end
+        for (ms, exact, start, end) in [
+            (0, 0, 0, -1),
+            (1, 0, 1, 0),
+            (21, 0, 1, 0),
+            (22, 0, 1, 0),
+            (41, 0, 1, 0),
+            (42, 1, 1, 0),
+            (43, 1, 2, 1),
+            (1001, 24, 24, 23),
+            (1002, 24, 25, 24),
+            (-1, -1, 0, -1),
+        ] {
+            assert_eq!(timecodes.frame_at_ms(ms, TimeKind::Exact), exact, "{}", ms);
+            assert_eq!(timecodes.frame_at_ms(ms, TimeKind::Start), start, "{}", ms);
+            assert_eq!(timecodes.frame_at_ms(ms, TimeKind::End), end, "{}", ms);
+        }
+    }
+
+    #[test]
+    fn frames_survive_rounding_to_centiseconds() {
+        for timecodes in [
+            Timecodes::cfr(NTSC_FILM).unwrap(),
+            Timecodes::cfr(30000.0 / 1001.0).unwrap(),
+            Timecodes::cfr(25.0).unwrap(),
+            Timecodes::cfr(60000.0 / 1001.0).unwrap(),
+            Timecodes::from_timecodes(vec![0, 33, 67, 100, 150, 200, 216, 233]).unwrap(),
+        ] {
+            for frame in 0..2000 {
+                for kind in [TimeKind::Exact, TimeKind::Start, TimeKind::End] {
+                    let time = timecodes.time_at_ms(frame, kind);
+                    assert_eq!(
+                        timecodes.frame_at_ms(time, kind),
+                        frame,
+                        "{} {:?}",
+                        frame,
+                        kind
+                    );
+                }
+
+                let mut entry = Entry::default();
+                timecodes.set_entry_frames(&mut entry, frame, frame + 3);
+                assert_eq!(
+                    timecodes.entry_frames(&entry),
+                    Some((frame, frame + 3)),
+                    "{} at {}",
+                    frame,
+                    timecodes.fps()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn snap() {
+        let timecodes = Timecodes::cfr(NTSC_FILM).unwrap();
+        let mut entries = [Entry {
+            start: Some(Duration::from_millis(1000)),
+            end: Some(Duration::from_millis(2000)),
+            ..Entry::default()
+        }];
+        timecodes.snap(&mut entries);
+        // frames 24 and 47, written as Aegisub would
+        assert_eq!(entries[0].start, Some(Duration::from_millis(980)));
+        assert_eq!(entries[0].end, Some(Duration::from_millis(1980)));
+        assert_eq!(timecodes.entry_frames(&entries[0]), Some((24, 47)));
+    }
+
+    #[test]
+    fn v1() {
+        let timecodes = Timecodes::parse(
+            "\u{feff}# timecode format v1\n\
+             Assume 25\n\
+             # a comment\n\
+             2,3,50\n\
+             5,5,10\n",
+        )
+        .unwrap();
+        assert!(!timecodes.is_cfr());
+        assert_eq!(timecodes.fps(), 25.0);
+        let times: Vec<i64> = (0..10)
+            .map(|f| timecodes.time_at_ms(f, TimeKind::Exact))
+            .collect();
+        assert_eq!(times, [0, 40, 80, 100, 120, 160, 260, 300, 340, 380]);
+    }
+
+    #[test]
+    fn v2() {
+        let timecodes =
+            Timecodes::parse("# timestamp format v2\r\n0\r\n40\r\n80.4\r\n\r\n120.6\r\n").unwrap();
+        assert!(!timecodes.is_cfr());
+        assert_eq!(timecodes.fps(), 3000.0 / 121.0);
+        for (frame, exact) in [(0, 0), (2, 80), (3, 121), (-1, -40), (4, 161)] {
+            assert_eq!(
+                timecodes.time_at_ms(frame, TimeKind::Exact),
+                exact,
+                "{}",
+                frame
+            );
+        }
+        for (ms, frame) in [(0, 0), (39, 0), (40, 1), (120, 2), (121, 3), (161, 4)] {
+            assert_eq!(timecodes.frame_at_ms(ms, TimeKind::Exact), frame, "{}", ms);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        use TimecodesError::*;
+        let line = |line, content: &str| InvalidLine {
+            line,
+            content: content.to_owned(),
+        };
+        for (input, error) in [
+            ("", UnknownFormat),
+            ("0\n40\n", UnknownFormat),
+            ("# timecode format v1\n", Empty),
+            ("# timecode format v1\nAssume x\n", line(2, "Assume x")),
+            ("# timecode format v1\n0,1,25\n", line(2, "0,1,25")),
+            (
+                "# timecode format v1\nAssume 25\n0,5,30\n5,6,30\n",
+                line(4, "5,6,30"),
+            ),
+            (
+                "# timecode format v1\nAssume 25\n3,2,30\n",
+                line(3, "3,2,30"),
+            ),
+            ("# timecode format v1\nAssume 25\n0,2,0\n", line(3, "0,2,0")),
+            ("# timecode format v2\n", Empty),
+            ("# timecode format v2\n0\n40\n40\n", line(4, "40")),
+            ("# timecode format v2\n0\nabc\n", line(3, "abc")),
+        ] {
+            assert_eq!(Timecodes::parse(input), Err(error), "{:?}", input);
+        }
+
+        assert_eq!(Timecodes::cfr(0.0), Err(InvalidFramerate(0.0)));
+        assert_eq!(Timecodes::cfr(-1.0), Err(InvalidFramerate(-1.0)));
+        assert!(Timecodes::cfr(f64::NAN).is_err());
+        assert_eq!(Timecodes::from_timecodes(Vec::new()), Err(Empty));
+        assert_eq!(
+            Timecodes::from_timecodes(vec![0, 10, 10]),
+            Err(NotIncreasing { frame: 2 })
+        );
+    }
+}
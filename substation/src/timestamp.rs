@@ -0,0 +1,241 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A point in an ASS script, in the centiseconds the format stores.
+///
+/// Arithmetic may go negative; formatting clamps to zero like renderers do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub const ZERO: Timestamp = Timestamp(0);
+
+    pub fn from_centis(centis: i64) -> Timestamp {
+        Timestamp(centis)
+    }
+
+    pub fn from_hms(hours: i64, minutes: i64, seconds: i64, centis: i64) -> Timestamp {
+        Timestamp(((hours * 60 + minutes) * 60 + seconds) * 100 + centis)
+    }
+
+    /// [`Timestamp::from_hms`], or `None` if the time doesn't fit.
+    pub fn checked_from_hms(
+        hours: i64,
+        minutes: i64,
+        seconds: i64,
+        centis: i64,
+    ) -> Option<Timestamp> {
+        hours
+            .checked_mul(60)?
+            .checked_add(minutes)?
+            .checked_mul(60)?
+            .checked_add(seconds)?
+            .checked_mul(100)?
+            .checked_add(centis)
+            .map(Timestamp)
+    }
+
+    /// Rounds a millisecond time to the nearest centisecond, halves rounding
+    /// up, so `1.005s` becomes `1.01s` every time.
+    pub fn from_millis_rounded(millis: i64) -> Timestamp {
+        Timestamp((millis + 5).div_euclid(10))
+    }
+
+    pub fn from_duration(duration: Duration) -> Timestamp {
+        Timestamp::from_millis_rounded(duration.as_millis() as i64)
+    }
+
+    pub fn centis(self) -> i64 {
+        self.0
+    }
+
+    pub fn as_millis(self) -> i64 {
+        self.0 * 10
+    }
+
+    /// The timestamp as a `Duration`, clamped to zero.
+    pub fn as_duration(self) -> Duration {
+        Duration::from_millis(self.as_millis().max(0) as u64)
+    }
+
+    pub fn scale(self, factor: f64) -> Timestamp {
+        Timestamp((self.0 as f64 * factor).round() as i64)
+    }
+}
+
+impl From<Duration> for Timestamp {
+    fn from(duration: Duration) -> Timestamp {
+        Timestamp::from_duration(duration)
+    }
+}
+
+impl From<Timestamp> for Duration {
+    fn from(timestamp: Timestamp) -> Duration {
+        timestamp.as_duration()
+    }
+}
+
+/// Exact, since every centisecond is a whole number of milliseconds.
+impl From<Timestamp> for subrip::Timestamp {
+    fn from(timestamp: Timestamp) -> subrip::Timestamp {
+        subrip::Timestamp::from_millis(timestamp.as_millis())
+    }
+}
+
+/// Rounds to the nearest centisecond, see [`Timestamp::from_millis_rounded`].
+impl From<subrip::Timestamp> for Timestamp {
+    fn from(timestamp: subrip::Timestamp) -> Timestamp {
+        Timestamp::from_millis_rounded(timestamp.millis())
+    }
+}
+
+impl Add for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, other: Timestamp) -> Timestamp {
+        Timestamp(self.0 + other.0)
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, other: Timestamp) -> Timestamp {
+        Timestamp(self.0 - other.0)
+    }
+}
+
+impl AddAssign for Timestamp {
+    fn add_assign(&mut self, other: Timestamp) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Timestamp {
+    fn sub_assign(&mut self, other: Timestamp) {
+        self.0 -= other.0;
+    }
+}
+
+// H:MM:SS.cc, with as many hour digits as needed
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cs = self.0.max(0);
+        write!(
+            f,
+            "{}:{:02}:{:02}.{:02}",
+            cs / 360_000,
+            cs / 6_000 % 60,
+            cs / 100 % 60,
+            cs % 100
+        )
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = nom::Err<nom::error::Error<String>>;
+
+    fn from_str(s: &str) -> Result<Timestamp, Self::Err> {
+        nom::combinator::all_consuming(crate::parser::timestamp)(s.trim())
+            .map(|(_, t)| t)
+            .map_err(|e| e.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        for (text, centis, shown) in [
+            ("0:00:00.00", 0, "0:00:00.00"),
+            ("1:02:03.45", 372_345, "1:02:03.45"),
+            (" 0:00:01.50 ", 150, "0:00:01.50"),
+            ("0:00:01:50", 150, "0:00:01.50"),
+            ("00:01:02.03", 6_203, "0:01:02.03"),
+            ("123:00:00.00", 44_280_000, "123:00:00.00"),
+            ("0:00:59.99", 5_999, "0:00:59.99"),
+        ] {
+            let timestamp: Timestamp = text.parse().unwrap();
+            assert_eq!(timestamp.centis(), centis, "{:?}", text);
+            assert_eq!(timestamp.to_string(), shown, "{:?}", text);
+            assert_eq!(shown.parse::<Timestamp>(), Ok(timestamp), "{:?}", shown);
+        }
+        for text in [
+            "",
+            "1:02",
+            "1:02:03",
+            "1:02:03,45",
+            "a:00:00.00",
+            "0:00:00.00x",
+            "99999999999999999:00:00.00",
+            "0:00:00.99999999999999999999",
+        ] {
+            assert!(text.parse::<Timestamp>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn rounding() {
+        for (millis, centis) in [
+            (0, 0),
+            (4, 0),
+            (5, 1),
+            (1004, 100),
+            (1005, 101),
+            (1009, 101),
+            (-4, 0),
+            (-5, 0),
+            (-6, -1),
+            (-15, -1),
+        ] {
+            assert_eq!(
+                Timestamp::from_millis_rounded(millis).centis(),
+                centis,
+                "{}",
+                millis
+            );
+        }
+        assert_eq!(
+            Timestamp::from_duration(Duration::from_micros(1_004_999)).centis(),
+            100
+        );
+        assert_eq!(Timestamp::from_centis(3).scale(0.5).centis(), 2);
+        assert_eq!(Timestamp::from_centis(100).scale(1.001).centis(), 100);
+    }
+
+    #[test]
+    fn negatives() {
+        let early = Timestamp::from_centis(50) - Timestamp::from_centis(150);
+        assert_eq!(early.centis(), -100);
+        assert_eq!(early.as_millis(), -1000);
+        assert_eq!(early.as_duration(), Duration::ZERO);
+        assert_eq!(early.to_string(), "0:00:00.00");
+        assert!(early < Timestamp::ZERO);
+
+        let mut t = early;
+        t += Timestamp::from_hms(0, 0, 2, 0);
+        assert_eq!(t, Timestamp::from_centis(100));
+        t -= Timestamp::from_centis(25);
+        assert_eq!(t + Timestamp::from_centis(25), Timestamp::from_centis(100));
+        assert_eq!(Timestamp::from_hms(0, 0, -1, 0).centis(), -100);
+        assert_eq!(Timestamp::checked_from_hms(i64::MAX, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn subrip_conversions() {
+        for (millis, centis) in [(1000, 100), (1004, 100), (1005, 101), (-20, -2)] {
+            let ass = Timestamp::from(subrip::Timestamp::from_millis(millis));
+            assert_eq!(ass.centis(), centis, "{}", millis);
+        }
+        let srt = subrip::Timestamp::from(Timestamp::from_centis(101));
+        assert_eq!(srt.millis(), 1010);
+        assert_eq!(
+            Duration::from(Timestamp::from_centis(101)),
+            Duration::from_millis(1010)
+        );
+    }
+}
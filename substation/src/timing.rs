@@ -0,0 +1,293 @@
+use crate::Entry;
+use parsing_utils::timing::{scale_duration, shift_duration, TimeRange};
+
+/// Restricts a timing operation to some of a script's events.
+///
+/// An event is selected when its start time falls inside `range` (if any)
+/// and its style is one of `styles` (if any).
+#[derive(Default, Clone, Debug)]
+pub struct Selection {
+    pub range: Option<TimeRange>,
+    pub styles: Option<Vec<String>>,
+}
+
+impl Selection {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        let in_range = match (&self.range, entry.start) {
+            (Some(range), Some(start)) => range.contains(start),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        let in_styles = match (&self.styles, &entry.style) {
+            (Some(styles), Some(style)) => styles.iter().any(|s| s == style),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        in_range && in_styles
+    }
+}
+
+/// Moves the selected events by `offset_ms` milliseconds. Times that would
+/// become negative are clamped to zero.
+///
+/// In-line times (`\t`, `\move`, `\fad`, `\k`...) are relative to the start
+/// of the event, so they are left untouched.
+pub fn shift(entries: &mut [Entry], offset_ms: i64, selection: &Selection) {
+    for entry in entries.iter_mut().filter(|e| selection.matches(e)) {
+        shift_entry(entry, offset_ms);
+    }
+}
+
+pub(crate) fn shift_entry(entry: &mut Entry, offset_ms: i64) {
+    entry.start = entry.start.map(|t| shift_duration(t, offset_ms));
+    entry.end = entry.end.map(|t| shift_duration(t, offset_ms));
+}
+
+/// Multiplies the times of the selected events by `factor`, including the
+/// in-line times of their override tags so animations keep their pace
+/// relative to the event.
+///
+/// Use [`parsing_utils::timing::framerate_ratio`] to get the factor for a
+/// framerate conversion.
+pub fn scale(entries: &mut [Entry], factor: f64, selection: &Selection) {
+    for entry in entries.iter_mut().filter(|e| selection.matches(e)) {
+        scale_entry(entry, factor);
+    }
+}
+
+pub(crate) fn scale_entry(entry: &mut Entry, factor: f64) {
+    entry.start = entry.start.map(|t| scale_duration(t, factor));
+    entry.end = entry.end.map(|t| scale_duration(t, factor));
+    entry.text = scale_override_times(&entry.text, factor);
+}
+
+/// Rewrites the times inside the override blocks of an event's text,
+/// leaving everything else byte-for-byte intact.
+pub fn scale_override_times(text: &str, factor: f64) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let block = &rest[open..];
+        let close = block.find('}').map(|i| i + 1).unwrap_or(block.len());
+        out.push_str(&scale_block(&block[..close], factor));
+        rest = &block[close..];
+    }
+    out.push_str(rest);
+
+    out
+}
+
+fn scale_block(block: &str, factor: f64) -> String {
+    let mut out = String::with_capacity(block.len());
+    let mut rest = block;
+
+    while let Some(slash) = rest.find('\\') {
+        out.push_str(&rest[..=slash]);
+        rest = &rest[slash + 1..];
+
+        if let Some(args) = rest.strip_prefix("t(") {
+            out.push_str("t(");
+            // only the leading numeric arguments are times, the rest are tags
+            let len = args.find(['\\', ')']).unwrap_or(args.len());
+            let mut numbers: Vec<&str> = args[..len].split(',').collect();
+            let tail = if numbers.len() > 1 {
+                numbers.pop()
+            } else {
+                None
+            };
+            let times = if numbers.len() >= 2 { 2 } else { 0 };
+            out.push_str(&scale_args(&numbers, 0..times, factor));
+            if let Some(tail) = tail {
+                out.push(',');
+                out.push_str(tail);
+            }
+            rest = &args[len..];
+        } else if let Some(args) = rest.strip_prefix("move(") {
+            out.push_str("move(");
+            rest = scale_function(args, 4..6, factor, &mut out);
+        } else if let Some(args) = rest.strip_prefix("fade(") {
+            out.push_str("fade(");
+            rest = scale_function(args, 3..7, factor, &mut out);
+        } else if let Some(args) = rest.strip_prefix("fad(") {
+            out.push_str("fad(");
+            rest = scale_function(args, 0..2, factor, &mut out);
+        } else if let Some(kind) = ["kf", "ko", "k", "K"].iter().find(|k| rest.starts_with(*k)) {
+            out.push_str(kind);
+            let args = &rest[kind.len()..];
+            let len = args
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(args.len());
+            out.push_str(&scale_args(&[&args[..len]], 0..1, factor));
+            rest = &args[len..];
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
+fn scale_function<'a>(
+    args: &'a str,
+    times: std::ops::Range<usize>,
+    factor: f64,
+    out: &mut String,
+) -> &'a str {
+    let len = args.find(')').unwrap_or(args.len());
+    let values: Vec<&str> = args[..len].split(',').collect();
+    out.push_str(&scale_args(&values, times, factor));
+    &args[len..]
+}
+
+fn scale_args(args: &[&str], times: std::ops::Range<usize>, factor: f64) -> String {
+    args.iter()
+        .enumerate()
+        .map(|(n, arg)| match arg.trim().parse::<f64>() {
+            Ok(v) if times.contains(&n) => format!("{}", (v * factor).round() as i64),
+            _ => arg.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn event(style: &str, start: u64, end: u64, text: &str) -> Entry {
+        Entry {
+            style: Some(style.to_owned()),
+            start: Some(Duration::from_millis(start)),
+            end: Some(Duration::from_millis(end)),
+            text: text.to_owned(),
+            ..Entry::default()
+        }
+    }
+
+    fn times(entries: &[Entry]) -> Vec<(u64, u64)> {
+        entries
+            .iter()
+            .map(|e| {
+                (
+                    e.start.unwrap().as_millis() as u64,
+                    e.end.unwrap().as_millis() as u64,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn override_times() {
+        for (text, scaled) in [
+            ("{\\t(100,200,\\fs20)}a", "{\\t(200,400,\\fs20)}a"),
+            ("{\\t(100,200,0.5,\\fs20)}a", "{\\t(200,400,0.5,\\fs20)}a"),
+            ("{\\t(0.5,\\fs20)}a", "{\\t(0.5,\\fs20)}a"),
+            ("{\\t(\\fs20)}a", "{\\t(\\fs20)}a"),
+            ("{\\move(1,2,3,4,100,300)}a", "{\\move(1,2,3,4,200,600)}a"),
+            ("{\\move(1,2,3,4)}a", "{\\move(1,2,3,4)}a"),
+            ("{\\fad(150,250)}a", "{\\fad(300,500)}a"),
+            (
+                "{\\fade(255,0,255,0,100,200,300)}a",
+                "{\\fade(255,0,255,0,200,400,600)}a",
+            ),
+            (
+                "{\\k10}a{\\K20}b{\\kf30}c{\\ko40}d",
+                "{\\k20}a{\\K40}b{\\kf60}c{\\ko80}d",
+            ),
+            (
+                "{\\fs20\\b1}a {not a tag 100}",
+                "{\\fs20\\b1}a {not a tag 100}",
+            ),
+            ("\\k10 outside a block", "\\k10 outside a block"),
+            ("{\\k10", "{\\k20"),
+        ] {
+            assert_eq!(scale_override_times(text, 2.0), scaled, "{:?}", text);
+        }
+        assert_eq!(scale_override_times("{\\k3}", 0.5), "{\\k2}");
+    }
+
+    #[test]
+    fn shift_leaves_override_times_alone() {
+        let text = "{\\t(100,200,\\fs20)\\move(1,2,3,4,100,300)\\fad(150,250)\\k10}a";
+        let mut entries = [event("Default", 1000, 2000, text)];
+        shift(&mut entries, 500, &Selection::default());
+        assert_eq!(times(&entries), [(1500, 2500)]);
+        assert_eq!(entries[0].text, text);
+
+        shift(&mut entries, -2000, &Selection::default());
+        assert_eq!(times(&entries), [(0, 500)]);
+    }
+
+    #[test]
+    fn scale_moves_override_times() {
+        let mut entries = [event(
+            "Default",
+            1000,
+            2000,
+            "{\\t(100,200,\\fs20)\\fad(150,250)\\k10}a",
+        )];
+        scale(&mut entries, 2.0, &Selection::default());
+        assert_eq!(times(&entries), [(2000, 4000)]);
+        assert_eq!(entries[0].text, "{\\t(200,400,\\fs20)\\fad(300,500)\\k20}a");
+    }
+
+    #[test]
+    fn selections() {
+        let original = [
+            event("Default", 0, 1000, "a"),
+            event("Signs", 1000, 2000, "b"),
+            event("Default", 2000, 3000, "c"),
+            Entry {
+                start: Some(Duration::from_millis(1500)),
+                end: Some(Duration::from_millis(1600)),
+                ..Entry::default()
+            },
+        ];
+        let range = Some(TimeRange::new(
+            Duration::from_millis(1000),
+            Duration::from_millis(2500),
+        ));
+        let default = Some(vec!["Default".to_owned()]);
+        for (selection, expected) in [
+            (
+                Selection::default(),
+                [(100, 1100), (1100, 2100), (2100, 3100), (1600, 1700)],
+            ),
+            (
+                Selection {
+                    range,
+                    styles: None,
+                },
+                [(0, 1000), (1100, 2100), (2100, 3100), (1600, 1700)],
+            ),
+            (
+                Selection {
+                    range: None,
+                    styles: default.clone(),
+                },
+                [(100, 1100), (1000, 2000), (2100, 3100), (1500, 1600)],
+            ),
+            (
+                Selection {
+                    range,
+                    styles: default,
+                },
+                [(0, 1000), (1000, 2000), (2100, 3100), (1500, 1600)],
+            ),
+        ] {
+            let mut entries = original.clone();
+            shift(&mut entries, 100, &selection);
+            assert_eq!(times(&entries), expected, "{:?}", selection);
+        }
+
+        let untimed = Entry::default();
+        assert!(Selection::default().matches(&untimed));
+        assert!(!Selection {
+            range,
+            styles: None
+        }
+        .matches(&untimed));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::attachments::{encode, file_key};
+use crate::{Color, Entry, Script, Section, Style, Timestamp};
+use std::fmt;
+use std::io::{self, Write};
+
+pub const STYLE_FORMAT: &str = "Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, \
+    OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, \
+    Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+
+pub const EVENT_FORMAT: &str =
+    "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+// the order Aegisub writes these in, anything else follows alphabetically
+const INFO_ORDER: [&str; 8] = [
+    "Title",
+    "ScriptType",
+    "WrapStyle",
+    "ScaledBorderAndShadow",
+    "YCbCr Matrix",
+    "PlayResX",
+    "PlayResY",
+    "LayoutResX",
+];
+
+/// `&HAABBGGRR`, with a missing alpha written as opaque.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "&H{:02X}{:02X}{:02X}{:02X}",
+            self.alpha.unwrap_or(0),
+            self.blue,
+            self.green,
+            self.red
+        )
+    }
+}
+
+fn flag(value: Option<bool>) -> &'static str {
+    if value.unwrap_or(false) {
+        "-1"
+    } else {
+        "0"
+    }
+}
+
+fn color(value: &Option<Color>, default: Color) -> Color {
+    value.clone().unwrap_or(default)
+}
+
+const fn rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
+    Color {
+        alpha: Some(alpha),
+        red,
+        green,
+        blue,
+    }
+}
+
+/// A `Style:` line in the order of [`STYLE_FORMAT`]. Missing values get the
+/// defaults Aegisub uses for new styles.
+impl<S: fmt::Display> fmt::Display for Style<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Style: {},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.name,
+            self.fontname,
+            self.fontsize,
+            color(&self.primary_color, rgba(255, 255, 255, 0)),
+            color(&self.secondary_color, rgba(255, 0, 0, 0)),
+            color(&self.outline_color, rgba(0, 0, 0, 0)),
+            color(&self.back_color, rgba(0, 0, 0, 0)),
+            flag(self.bold),
+            flag(self.italic),
+            flag(self.underline),
+            flag(self.strikeout),
+            self.scale_x.unwrap_or(100),
+            self.scale_y.unwrap_or(100),
+            self.spacing.unwrap_or(0),
+            self.angle.unwrap_or(0.0),
+            self.border_style.unwrap_or(1),
+            self.outline_size.unwrap_or(2),
+            self.shadow.unwrap_or(2),
+            self.alignment.unwrap_or(2),
+            self.margin_l.unwrap_or(10),
+            self.margin_r.unwrap_or(10),
+            self.margin_v.unwrap_or(10),
+            self.encoding.unwrap_or(1),
+        )
+    }
+}
+
+fn field<'a, S: AsRef<str>>(value: &'a Option<S>, default: &'a str) -> &'a str {
+    value.as_ref().map_or(default, |v| v.as_ref())
+}
+
+fn time(value: Option<std::time::Duration>) -> Timestamp {
+    value.map(Timestamp::from_duration).unwrap_or_default()
+}
+
+/// An event line in the order of [`EVENT_FORMAT`], as a `Dialogue:` unless
+/// `kind` says otherwise.
+impl<S: AsRef<str>> fmt::Display for Entry<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {},{},{},{},{},{},{},{},{},{}",
+            field(&self.kind, "Dialogue"),
+            self.layer.unwrap_or(0),
+            time(self.start),
+            time(self.end),
+            field(&self.style, "Default"),
+            field(&self.name, ""),
+            self.margin_l.unwrap_or(0),
+            self.margin_r.unwrap_or(0),
+            self.margin_v.unwrap_or(0),
+            field(&self.effect, ""),
+            self.text.as_ref()
+        )
+    }
+}
+
+fn write_settings<W: Write>(
+    writer: &mut W,
+    name: &str,
+    settings: &std::collections::HashMap<String, String>,
+    order: &[&str],
+) -> io::Result<()> {
+    writeln!(writer, "[{}]", name)?;
+    let mut keys: Vec<&String> = settings.keys().collect();
+    keys.sort_by_key(|k| {
+        (
+            order.iter().position(|o| o == k).unwrap_or(order.len()),
+            k.as_str(),
+        )
+    });
+    for key in keys {
+        writeln!(writer, "{}: {}", key, settings[key])?;
+    }
+    Ok(())
+}
+
+/// Writes everything before the events: `[Script Info]`, `[V4+ Styles]`
+/// and the `[Events]` format line. A `ScriptType` says `v4.00+`, as SSA
+/// scripts are written as ASS.
+pub(crate) fn write_header<W: Write>(writer: &mut W, script: &Script) -> io::Result<()> {
+    let mut info = script.info.clone();
+    if let Some(script_type) = info.get_mut("ScriptType") {
+        *script_type = "v4.00+".to_owned();
+    }
+    write_settings(writer, "Script Info", &info, &INFO_ORDER)?;
+
+    writeln!(writer)?;
+    writeln!(writer, "[V4+ Styles]")?;
+    writeln!(writer, "Format: {}", STYLE_FORMAT)?;
+    let mut styles: Vec<&Style> = script.styles.values().collect();
+    styles.sort_by_key(|s| (s.name != "Default", s.name.as_str()));
+    for style in styles {
+        writeln!(writer, "{}", style)?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "[Events]")?;
+    writeln!(writer, "Format: {}", EVENT_FORMAT)
+}
+
+/// Writes the sections that come after the events.
+pub(crate) fn write_other<W: Write>(writer: &mut W, other: &[Section]) -> io::Result<()> {
+    for section in other {
+        match section {
+            Section::Other { name, settings } => {
+                writeln!(writer)?;
+                write_settings(writer, name, settings, &[])?;
+            }
+            Section::Attachments { name, files } => {
+                writeln!(writer)?;
+                writeln!(writer, "[{}]", name)?;
+                let key = file_key(name).unwrap_or("filename");
+                for file in files {
+                    writeln!(writer, "{}: {}", key, file.name)?;
+                    writeln!(writer, "{}", encode(&file.data))?;
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Writes a script section by section: `[Script Info]`, `[V4+ Styles]`,
+/// `[Events]`, then whatever other sections were kept. Styles are written
+/// with `Default` first and the rest by name.
+pub fn write_script<W: Write>(mut writer: W, script: &Script) -> io::Result<()> {
+    write_header(&mut writer, script)?;
+    for event in &script.events {
+        writeln!(writer, "{}", event)?;
+    }
+    write_other(&mut writer, &script.other)
+}
+
+pub fn to_string(script: &Script) -> String {
+    let mut out = Vec::new();
+    write_script(&mut out, script).expect("writing to a Vec can't fail");
+    String::from_utf8(out).expect("scripts are valid UTF-8")
+}
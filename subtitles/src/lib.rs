@@ -0,0 +1,61 @@
+//! Every format in the workspace behind one [`SubtitleFormat`] trait, for
+//! reading and converting files without knowing their format up front.
+
+use parsing_utils::encoding::{decode, encode};
+use subformats::format::{Lrc, MicroDvd, Mpl2, Sami, Sbv};
+use subrip::format::SubRip;
+use substation::format::SubStation;
+use ttml::format::Ttml;
+use webvtt::format::WebVttFormat;
+
+pub use parsing_utils::encoding::{Detection, Encoding};
+pub use parsing_utils::format::{detect, Cue, Format, FormatError, SubtitleFormat};
+
+/// The format's reader and writer with default options. SSA shares the
+/// ASS one, which reads both but only writes ASS.
+pub fn format(format: Format) -> Box<dyn SubtitleFormat> {
+    match format {
+        Format::Ass | Format::Ssa => Box::new(SubStation::default()),
+        Format::SubRip => Box::new(SubRip::default()),
+        Format::WebVtt => Box::new(WebVttFormat),
+        Format::Ttml => Box::new(Ttml),
+        Format::MicroDvd => Box::new(MicroDvd::default()),
+        Format::Mpl2 => Box::new(Mpl2::default()),
+        Format::Sbv => Box::new(Sbv::default()),
+        Format::Sami => Box::new(Sami::default()),
+        Format::Lrc => Box::new(Lrc::default()),
+    }
+}
+
+/// Detects the format of `input` and reads it.
+pub fn parse_any(input: &str) -> Result<(Format, Vec<Cue>), FormatError> {
+    let detected = detect(input.as_bytes()).ok_or_else(|| FormatError {
+        line: None,
+        message: "unrecognized subtitle format".to_owned(),
+    })?;
+    Ok((detected, format(detected).parse(input)?))
+}
+
+/// Reads `input`, detecting its format, and writes it as `to`.
+pub fn convert(input: &str, to: Format) -> Result<String, FormatError> {
+    let (_, cues) = parse_any(input)?;
+    Ok(format(to).write(&cues))
+}
+
+/// Decodes a file of unknown encoding, detects its format and reads it.
+/// ASS and SSA scripts fall back on the charset of their styles when the
+/// encoding can only be guessed.
+pub fn parse_bytes(input: &[u8]) -> Result<(Format, Detection, Vec<Cue>), FormatError> {
+    let decoded = match detect(input) {
+        Some(Format::Ass | Format::Ssa) => substation::encoding::decode_script(input),
+        _ => decode(input),
+    };
+    let (detected, cues) = parse_any(&decoded.text)?;
+    Ok((detected, decoded.detection, cues))
+}
+
+/// Writes the cues as `to` in `encoding`, with a byte order mark if `bom`
+/// is set and the encoding is UTF-8 or UTF-16.
+pub fn write_bytes(cues: &[Cue], to: Format, encoding: &'static Encoding, bom: bool) -> Vec<u8> {
+    encode(&format(to).write(cues), encoding, bom).0
+}
@@ -0,0 +1,396 @@
+//! Conversion to and from ASS scripts.
+//!
+//! Styles become `<style>`s with font, size, colors, weight, slant and
+//! underline; alignment, margins and `\pos` become `<region>`s; `\b`, `\i`,
+//! `\u` and primary `\c` become span attributes. Other override tags are
+//! dropped.
+
+use crate::srt::{alignment_text_align, region_alignment, rgba};
+use crate::{
+    DisplayAlign, Document, Paragraph, Region, Rgba, Span, Style, StyleAttributes, TextAlign,
+};
+use std::fmt::Write as _;
+use substation::srt::FromSrtOptions;
+use substation::{alignment_cell, escape_text, Color, Entry, Script, StyleOverride, TextSection};
+
+// the writer doesn't set ttp:cellResolution, so the default 32 by 15 applies
+const CELL_ROWS: f64 = 15.0;
+
+/// A valid `xml:id` for a style name.
+pub fn style_id(name: &str) -> String {
+    let id: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') {
+        format!("s{}", id)
+    } else {
+        id
+    }
+}
+
+fn ttml_color(color: &Color) -> Rgba {
+    // ASS alpha is transparency
+    Rgba {
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+        alpha: 255 - color.alpha.unwrap_or(0),
+    }
+}
+
+fn ass_color(color: Rgba) -> Color {
+    Color {
+        alpha: Some(255 - color.alpha),
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+    }
+}
+
+fn style_attributes(style: &substation::Style, height: f64) -> StyleAttributes {
+    StyleAttributes {
+        font_family: Some(style.fontname.clone()).filter(|f| !f.is_empty()),
+        font_size: Some((style.fontsize / height * CELL_ROWS * 100.0).round() / 100.0)
+            .filter(|&s| s > 0.0),
+        color: style.primary_color.as_ref().map(ttml_color),
+        background_color: match style.border_style {
+            Some(3) => style.back_color.as_ref().map(ttml_color),
+            _ => None,
+        },
+        bold: style.bold,
+        italic: style.italic,
+        underline: style.underline,
+        text_align: Some(alignment_text_align(style.alignment.unwrap_or(2) as u8)),
+    }
+}
+
+fn position(sections: &[TextSection]) -> Option<(f64, f64)> {
+    sections.iter().find_map(|s| match s {
+        TextSection::StyleOverride(overrides) => overrides.iter().find_map(|o| match o {
+            StyleOverride::Move {
+                start_x, start_y, ..
+            } => Some((*start_x, *start_y)),
+            _ => None,
+        }),
+        _ => None,
+    })
+}
+
+fn percent(value: f64, of: f64) -> f64 {
+    ((value / of * 100.0).clamp(0.0, 100.0) * 100.0).round() / 100.0
+}
+
+/// The region an event is shown in, as an ASS renderer would place it: the
+/// area inside the margins, or for `\pos` the largest box the point anchors
+/// with the alignment. The id is left empty.
+pub fn placement(
+    entry: &Entry,
+    style: Option<&substation::Style>,
+    alignment: u8,
+    pos: Option<(f64, f64)>,
+    (width, height): (f64, f64),
+) -> Region {
+    let (column, row) = alignment_cell(alignment);
+    let display_align = match row {
+        0 => DisplayAlign::After,
+        1 => DisplayAlign::Center,
+        _ => DisplayAlign::Before,
+    };
+
+    let (left, right, top, bottom) = match pos {
+        Some((x, y)) => {
+            let (left, right) = match column {
+                0 => (x, width),
+                1 => (x - x.min(width - x), x + x.min(width - x)),
+                _ => (0.0, x),
+            };
+            let (top, bottom) = match row {
+                0 => (0.0, y),
+                1 => (y - y.min(height - y), y + y.min(height - y)),
+                _ => (y, height),
+            };
+            (left, right, top, bottom)
+        }
+        None => {
+            let (margin_l, margin_r, margin_v) = entry.margins(style);
+            (margin_l, width - margin_r, margin_v, height - margin_v)
+        }
+    };
+
+    let (x, y) = (percent(left, width), percent(top, height));
+    Region {
+        id: String::new(),
+        origin: (x, y),
+        extent: (
+            (percent(right, width) - x).max(0.0),
+            (percent(bottom, height) - y).max(0.0),
+        ),
+        display_align,
+    }
+}
+
+/// Exports a script's dialogue as an IMSC1 document. Comments and events
+/// without text are skipped, and events placed the same way share a region.
+pub fn from_ass(script: &Script) -> Document {
+    let resolution = script.play_res();
+
+    let mut names: Vec<&String> = script.styles.keys().collect();
+    names.sort_by_key(|n| (n.as_str() != "Default", n.as_str()));
+
+    let mut document = Document {
+        lang: script.info.get("Language").cloned(),
+        styles: names
+            .iter()
+            .map(|name| Style {
+                id: style_id(name),
+                attributes: style_attributes(&script.styles[*name], resolution.1),
+            })
+            .collect(),
+        ..Document::default()
+    };
+
+    for entry in &script.events {
+        if entry.kind.as_deref() == Some("Comment") {
+            continue;
+        }
+        let style = entry.style.as_deref().and_then(|s| script.styles.get(s));
+        let (srt_spans, alignment) = substation::srt::srt_spans(entry, &script.styles);
+        let spans: Vec<Span> = srt_spans
+            .iter()
+            .map(|s| Span {
+                text: s.text.clone(),
+                attributes: StyleAttributes {
+                    bold: Some(s.style.bold).filter(|&b| Some(b) != style.and_then(|s| s.bold)),
+                    italic: Some(s.style.italic)
+                        .filter(|&i| Some(i) != style.and_then(|s| s.italic)),
+                    underline: Some(s.style.underline)
+                        .filter(|&u| Some(u) != style.and_then(|s| s.underline)),
+                    color: s.style.font.rgb().map(rgba),
+                    ..StyleAttributes::default()
+                },
+            })
+            .collect();
+        if spans.iter().all(|s| s.text.trim().is_empty()) {
+            continue;
+        }
+
+        let alignment = alignment.unwrap_or(2);
+        let mut region = placement(
+            entry,
+            style,
+            alignment,
+            position(&entry.parsed_text()),
+            resolution,
+        );
+        let existing = document.regions.iter().find(|r| {
+            r.origin == region.origin
+                && r.extent == region.extent
+                && r.display_align == region.display_align
+        });
+        region.id = match existing {
+            Some(r) => r.id.clone(),
+            None => {
+                region.id = format!("r{}", document.regions.len() + 1);
+                document.regions.push(region.clone());
+                region.id
+            }
+        };
+
+        let text_align = alignment_text_align(alignment);
+        let style_align = style.map(|s| alignment_text_align(s.alignment.unwrap_or(2) as u8));
+        document.paragraphs.push(Paragraph {
+            begin: entry.start.unwrap_or_default(),
+            end: entry.end.unwrap_or_default(),
+            style: entry.style.as_deref().map(style_id),
+            region: Some(region.id),
+            attributes: StyleAttributes {
+                text_align: Some(text_align).filter(|&a| Some(a) != style_align),
+                ..StyleAttributes::default()
+            },
+            spans,
+        });
+    }
+
+    document.paragraphs.sort_by_key(|p| p.begin);
+    document
+}
+
+fn ass_style(style: &Style, (_, height): (f64, f64)) -> substation::Style {
+    let mut ass = FromSrtOptions::default().style;
+    let attributes = &style.attributes;
+    ass.name = style.id.clone();
+    if let Some(family) = &attributes.font_family {
+        ass.fontname = family.clone();
+    }
+    if let Some(size) = attributes.font_size {
+        ass.fontsize = (size / CELL_ROWS * height).round();
+    }
+    if let Some(color) = attributes.color {
+        ass.primary_color = Some(ass_color(color));
+    }
+    if let Some(color) = attributes.background_color {
+        ass.back_color = Some(ass_color(color));
+        ass.border_style = Some(3);
+    }
+    ass.bold = attributes.bold.or(ass.bold);
+    ass.italic = attributes.italic.or(ass.italic);
+    ass.underline = attributes.underline.or(ass.underline);
+    ass.alignment = Some(match attributes.text_align {
+        Some(TextAlign::Left) | Some(TextAlign::Start) => 1,
+        Some(TextAlign::Right) | Some(TextAlign::End) => 3,
+        _ => 2,
+    });
+    ass
+}
+
+// override tags taking the text from `from` to `to`
+fn overrides(from: &StyleAttributes, to: &StyleAttributes) -> String {
+    let mut tags = String::new();
+    let mut toggle = |tag: &str, from: Option<bool>, to: Option<bool>| {
+        if from.unwrap_or(false) != to.unwrap_or(false) {
+            let _ = write!(tags, "\\{}{}", tag, to.unwrap_or(false) as u8);
+        }
+    };
+    toggle("b", from.bold, to.bold);
+    toggle("i", from.italic, to.italic);
+    toggle("u", from.underline, to.underline);
+    if let (Some(color), true) = (to.color, from.color != to.color) {
+        let _ = write!(
+            tags,
+            "\\c&H{:02X}{:02X}{:02X}&",
+            color.blue, color.green, color.red
+        );
+    }
+    tags
+}
+
+/// Imports a document as a script at 1920x1080. Each style becomes an ASS
+/// style, regions become margins and `\an`, and span attributes override
+/// tags.
+pub fn to_ass(document: &Document) -> Script {
+    let resolution = (1920.0, 1080.0);
+    let mut script = Script {
+        info: [
+            ("ScriptType", "v4.00+"),
+            ("WrapStyle", "0"),
+            ("ScaledBorderAndShadow", "yes"),
+            ("PlayResX", "1920"),
+            ("PlayResY", "1080"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect(),
+        ..Script::default()
+    };
+    if let Some(lang) = &document.lang {
+        script.info.insert("Language".to_owned(), lang.clone());
+    }
+
+    for style in &document.styles {
+        script
+            .styles
+            .insert(style.id.clone(), ass_style(style, resolution));
+    }
+
+    for paragraph in &document.paragraphs {
+        if paragraph.plain_text().trim().is_empty() {
+            continue;
+        }
+        let style_name = paragraph
+            .style
+            .as_deref()
+            .and_then(|ids| {
+                ids.split_whitespace()
+                    .rev()
+                    .find(|id| script.styles.contains_key(*id))
+            })
+            .unwrap_or("Default")
+            .to_owned();
+        if !script.styles.contains_key(&style_name) {
+            script
+                .styles
+                .insert(style_name.clone(), FromSrtOptions::default().style);
+        }
+        let style = &script.styles[&style_name];
+        let style_attributes = document
+            .style(&style_name)
+            .map(|s| s.attributes.clone())
+            .unwrap_or_default();
+
+        let region = paragraph
+            .region
+            .as_deref()
+            .and_then(|id| document.region(id));
+        let text_align = paragraph
+            .attributes
+            .text_align
+            .or(style_attributes.text_align);
+        let alignment = region_alignment(region, text_align);
+
+        let mut text = String::new();
+        if Some(alignment as usize) != style.alignment {
+            let _ = write!(text, "{{\\an{}}}", alignment);
+        }
+        let mut current = style_attributes.clone();
+        for span in &paragraph.spans {
+            let target = span.attributes.inherit(&style_attributes);
+            let tags = overrides(&current, &target);
+            if !tags.is_empty() {
+                if text.ends_with('}') {
+                    text.pop();
+                    text.push_str(&tags);
+                    text.push('}');
+                } else {
+                    let _ = write!(text, "{{{}}}", tags);
+                }
+            }
+            let lines: Vec<String> = span.text.split('\n').map(escape_text).collect();
+            text.push_str(&lines.join("\\N"));
+            current = target;
+        }
+
+        // margins put the text where the region is
+        let (width, height) = resolution;
+        let (margin_l, margin_r, margin_v) = match region {
+            Some(r) => {
+                let margin_v = match r.display_align {
+                    DisplayAlign::Before => r.origin.1,
+                    DisplayAlign::Center => 0.0,
+                    DisplayAlign::After => 100.0 - r.origin.1 - r.extent.1,
+                };
+                (
+                    r.origin.0 / 100.0 * width,
+                    (100.0 - r.origin.0 - r.extent.0) / 100.0 * width,
+                    margin_v / 100.0 * height,
+                )
+            }
+            None => (0.0, 0.0, 0.0),
+        };
+        let margin = |m: f64| Some(m.max(0.0).round() as usize);
+
+        script.events.push(Entry {
+            kind: Some("Dialogue".to_owned()),
+            layer: Some(0),
+            start: Some(substation::Timestamp::from_duration(paragraph.begin).as_duration()),
+            end: Some(substation::Timestamp::from_duration(paragraph.end).as_duration()),
+            style: Some(style_name),
+            name: None,
+            margin_l: margin(margin_l),
+            margin_r: margin(margin_r),
+            margin_v: margin(margin_v),
+            effect: None,
+            read_order: None,
+            text,
+        });
+    }
+
+    script
+}
@@ -0,0 +1,33 @@
+//! TTML through the workspace's common [`SubtitleFormat`] trait, converting
+//! by way of SubRip entries with [`crate::srt`].
+
+use crate::srt::{from_srt, to_srt};
+use crate::{parse, writer, Error};
+use parsing_utils::format::{Cue, Format, FormatError, SubtitleFormat};
+
+impl From<Error> for FormatError {
+    fn from(e: Error) -> FormatError {
+        FormatError {
+            line: None,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Reads any TTML and writes IMSC1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ttml;
+
+impl SubtitleFormat for Ttml {
+    fn format(&self) -> Format {
+        Format::Ttml
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(subrip::format::cues(&to_srt(&parse(input)?)))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        writer::to_string(&from_srt(&subrip::format::entries(cues)))
+    }
+}
@@ -0,0 +1,637 @@
+//! TTML documents in the IMSC1 Text Profile.
+//!
+//! [`Document`] is a flattened view of a TTML file: named styles, regions
+//! and timed paragraphs of styled spans. [`parse`] reads any TTML with media
+//! timing into it, [`writer`] writes IMSC1, and [`srt`] and [`ass`] convert
+//! to and from the other formats of the workspace.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, TryFromFloatSecsError};
+
+pub mod ass;
+pub mod format;
+pub mod srt;
+pub mod writer;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgba {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    Start,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayAlign {
+    Before,
+    Center,
+    After,
+}
+
+/// The `tts:` attributes this crate understands.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StyleAttributes {
+    pub font_family: Option<String>,
+    /// In `c` units, a fraction of the cell height.
+    pub font_size: Option<f64>,
+    pub color: Option<Rgba>,
+    pub background_color: Option<Rgba>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underline: Option<bool>,
+    pub text_align: Option<TextAlign>,
+}
+
+impl StyleAttributes {
+    /// These attributes with anything unset taken from `parent`.
+    pub fn inherit(&self, parent: &StyleAttributes) -> StyleAttributes {
+        StyleAttributes {
+            font_family: self.font_family.clone().or(parent.font_family.clone()),
+            font_size: self.font_size.or(parent.font_size),
+            color: self.color.or(parent.color),
+            background_color: self.background_color.or(parent.background_color),
+            bold: self.bold.or(parent.bold),
+            italic: self.italic.or(parent.italic),
+            underline: self.underline.or(parent.underline),
+            text_align: self.text_align.or(parent.text_align),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == StyleAttributes::default()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Style {
+    pub id: String,
+    pub attributes: StyleAttributes,
+}
+
+/// A rectangle of the video, in percent of its width and height.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub id: String,
+    pub origin: (f64, f64),
+    pub extent: (f64, f64),
+    pub display_align: DisplayAlign,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    /// Line breaks are kept as `\n`.
+    pub text: String,
+    /// When parsed, resolved through referenced styles and every ancestor.
+    pub attributes: StyleAttributes,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Paragraph {
+    pub begin: Duration,
+    pub end: Duration,
+    pub style: Option<String>,
+    pub region: Option<String>,
+    /// Only the attributes set on the `<p>` itself.
+    pub attributes: StyleAttributes,
+    pub spans: Vec<Span>,
+}
+
+impl Paragraph {
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    pub lang: Option<String>,
+    pub styles: Vec<Style>,
+    pub regions: Vec<Region>,
+    pub paragraphs: Vec<Paragraph>,
+}
+
+impl Document {
+    pub fn style(&self, id: &str) -> Option<&Style> {
+        self.styles.iter().find(|s| s.id == id)
+    }
+
+    pub fn region(&self, id: &str) -> Option<&Region> {
+        self.regions.iter().find(|r| r.id == id)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Xml(String),
+    NotTtml,
+    InvalidTime(String),
+    UnsupportedTimeBase(String),
+    /// A `ttp:frameRate` or `ttp:tickRate` that isn't a positive number.
+    InvalidRate(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Xml(e) => write!(f, "invalid XML: {}", e),
+            Error::NotTtml => write!(f, "root element is not <tt>"),
+            Error::InvalidTime(t) => write!(f, "invalid time expression {:?}", t),
+            Error::UnsupportedTimeBase(t) => write!(f, "unsupported time base {:?}", t),
+            Error::InvalidRate(r) => write!(f, "invalid frame or tick rate {:?}", r),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<quick_xml::Error> for Error {
+    fn from(e: quick_xml::Error) -> Error {
+        Error::Xml(e.to_string())
+    }
+}
+
+/// The frame and tick rates time expressions are counted in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeParameters {
+    pub frame_rate: f64,
+    pub tick_rate: f64,
+}
+
+impl Default for TimeParameters {
+    fn default() -> TimeParameters {
+        TimeParameters {
+            frame_rate: 30.0,
+            tick_rate: 1.0,
+        }
+    }
+}
+
+// frames and ticks only count time at a finite, positive rate
+fn valid_rate(rate: &f64) -> bool {
+    rate.is_finite() && *rate > 0.0
+}
+
+// negative times are clamped to zero, ones too large for a Duration are
+// an error
+fn seconds(value: f64) -> Result<Duration, TryFromFloatSecsError> {
+    Duration::try_from_secs_f64(value.max(0.0))
+}
+
+/// Parses a clock time (`01:02:03.456`, `01:02:03:12` with frames) or an
+/// offset time (`12.5s`, `500ms`, `2m`, `1h`, `25f`, `9000t`).
+pub fn time(value: &str, params: &TimeParameters) -> Result<Duration, Error> {
+    let invalid = || Error::InvalidTime(value.to_owned());
+    let value = value.trim();
+
+    if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() != 3 && parts.len() != 4 {
+            return Err(invalid());
+        }
+        let hours: f64 = parts[0].parse().map_err(|_| invalid())?;
+        let minutes: f64 = parts[1].parse().map_err(|_| invalid())?;
+        let secs: f64 = parts[2].parse().map_err(|_| invalid())?;
+        let frames: f64 = match parts.get(3) {
+            // sub-frames after a dot are ignored
+            Some(f) => f
+                .split('.')
+                .next()
+                .unwrap_or("0")
+                .parse()
+                .map_err(|_| invalid())?,
+            None => 0.0,
+        };
+        return seconds(hours * 3600.0 + minutes * 60.0 + secs + frames / params.frame_rate)
+            .map_err(|_| invalid());
+    }
+
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .ok_or_else(invalid)?;
+    let (number, metric) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let secs = match metric {
+        "h" => number * 3600.0,
+        "m" => number * 60.0,
+        "s" => number,
+        "ms" => number / 1000.0,
+        "f" => number / params.frame_rate,
+        "t" => number / params.tick_rate,
+        _ => return Err(invalid()),
+    };
+    seconds(secs).map_err(|_| invalid())
+}
+
+/// `#RRGGBB`, `#RRGGBBAA`, `rgb(r,g,b)`, `rgba(r,g,b,a)` or a named color.
+pub fn color(value: &str) -> Option<Rgba> {
+    let value = value.trim();
+    let rgba = |red, green, blue, alpha| {
+        Some(Rgba {
+            red,
+            green,
+            blue,
+            alpha,
+        })
+    };
+    if let Some(hex) = value.strip_prefix('#') {
+        let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return match hex.len() {
+            6 => rgba(byte(0)?, byte(2)?, byte(4)?, 255),
+            8 => rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?),
+            _ => None,
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+    {
+        let args: Vec<u8> = args
+            .trim_end_matches(')')
+            .split(',')
+            .map(|a| a.trim().parse::<u8>().ok())
+            .collect::<Option<_>>()?;
+        return match args[..] {
+            [r, g, b] => rgba(r, g, b, 255),
+            [r, g, b, a] => rgba(r, g, b, a),
+            _ => None,
+        };
+    }
+    match value {
+        "transparent" => rgba(0, 0, 0, 0),
+        "black" => rgba(0, 0, 0, 255),
+        "silver" => rgba(192, 192, 192, 255),
+        "gray" => rgba(128, 128, 128, 255),
+        "white" => rgba(255, 255, 255, 255),
+        "maroon" => rgba(128, 0, 0, 255),
+        "red" => rgba(255, 0, 0, 255),
+        "purple" => rgba(128, 0, 128, 255),
+        "fuchsia" | "magenta" => rgba(255, 0, 255, 255),
+        "green" => rgba(0, 128, 0, 255),
+        "lime" => rgba(0, 255, 0, 255),
+        "olive" => rgba(128, 128, 0, 255),
+        "yellow" => rgba(255, 255, 0, 255),
+        "navy" => rgba(0, 0, 128, 255),
+        "blue" => rgba(0, 0, 255, 255),
+        "teal" => rgba(0, 128, 128, 255),
+        "aqua" | "cyan" => rgba(0, 255, 255, 255),
+        _ => None,
+    }
+}
+
+fn pair(value: &str, unit: &str) -> Option<(f64, f64)> {
+    let mut values = value
+        .split_whitespace()
+        .map(|v| v.strip_suffix(unit)?.parse::<f64>().ok());
+    Some((values.next()??, values.next()??))
+}
+
+struct Element {
+    name: String,
+    attributes: HashMap<String, String>,
+}
+
+fn element(e: &BytesStart) -> Result<Element, Error> {
+    let mut attributes = HashMap::new();
+    for attr in e.attributes() {
+        let attr = attr.map_err(|e| Error::Xml(e.to_string()))?;
+        let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+        let value = attr.unescape_value()?.into_owned();
+        attributes.insert(key, value);
+    }
+    Ok(Element {
+        name: String::from_utf8_lossy(e.local_name().as_ref()).into_owned(),
+        attributes,
+    })
+}
+
+fn style_attributes(attributes: &HashMap<String, String>, cell_rows: f64) -> StyleAttributes {
+    let get = |k: &str| attributes.get(k).map(|v| v.trim());
+    StyleAttributes {
+        font_family: get("fontFamily").map(|v| v.trim_matches(['"', '\'']).to_owned()),
+        font_size: get("fontSize").and_then(|v| {
+            let v = v.split_whitespace().last()?;
+            if let Some(c) = v.strip_suffix('c') {
+                c.parse().ok()
+            } else if let Some(p) = v.strip_suffix('%') {
+                p.parse::<f64>().ok().map(|p| p / 100.0)
+            } else if let Some(px) = v.strip_suffix("px") {
+                // without a root extent, assume 1080 lines
+                px.parse::<f64>().ok().map(|px| px / 1080.0 * cell_rows)
+            } else {
+                None
+            }
+        }),
+        color: get("color").and_then(color),
+        background_color: get("backgroundColor").and_then(color),
+        bold: get("fontWeight").map(|v| v == "bold"),
+        italic: get("fontStyle").map(|v| v == "italic" || v == "oblique"),
+        underline: get("textDecoration").map(|v| v.split_whitespace().any(|d| d == "underline")),
+        text_align: get("textAlign").and_then(|v| match v {
+            "left" => Some(TextAlign::Left),
+            "center" => Some(TextAlign::Center),
+            "right" => Some(TextAlign::Right),
+            "start" => Some(TextAlign::Start),
+            "end" => Some(TextAlign::End),
+            _ => None,
+        }),
+    }
+}
+
+// what a timed element's children are relative to
+#[derive(Clone)]
+struct Scope {
+    begin: Duration,
+    end: Option<Duration>,
+    attributes: StyleAttributes,
+    region: Option<String>,
+}
+
+fn timing(
+    element: &Element,
+    parent: &Scope,
+    params: &TimeParameters,
+) -> Result<(Duration, Option<Duration>), Error> {
+    // `value` after `from`, as long as the sum fits in a Duration
+    let after = |from: Duration, value: &String| {
+        from.checked_add(time(value, params)?)
+            .ok_or_else(|| Error::InvalidTime(value.clone()))
+    };
+    let begin = match element.attributes.get("begin") {
+        Some(b) => after(parent.begin, b)?,
+        None => parent.begin,
+    };
+    let end = match (element.attributes.get("end"), element.attributes.get("dur")) {
+        (Some(e), _) => Some(after(parent.begin, e)?),
+        (None, Some(d)) => Some(after(begin, d)?),
+        (None, None) => parent.end,
+    };
+    let end = match (end, parent.end) {
+        (Some(e), Some(p)) => Some(e.min(p)),
+        (e, p) => e.or(p),
+    };
+    Ok((begin, end))
+}
+
+// default xml:space handling: runs of whitespace become a single space, and
+// spaces next to line breaks or the paragraph edges go
+fn collapse(spans: &mut Vec<Span>) {
+    let mut last_space = true;
+    for span in spans.iter_mut() {
+        let mut text = String::with_capacity(span.text.len());
+        for c in span.text.chars() {
+            if c == '\n' {
+                if text.ends_with(' ') {
+                    text.pop();
+                }
+                text.push('\n');
+                last_space = true;
+            } else if c == ' ' {
+                if !last_space {
+                    text.push(' ');
+                }
+                last_space = true;
+            } else {
+                text.push(c);
+                last_space = false;
+            }
+        }
+        span.text = text;
+    }
+    if let Some(last) = spans.iter_mut().rev().find(|s| !s.text.is_empty()) {
+        let trimmed = last.text.trim_end_matches(' ').len();
+        last.text.truncate(trimmed);
+    }
+    spans.retain(|s| !s.text.is_empty());
+}
+
+// line breaks in the source are only whitespace, `<br/>` makes real ones
+fn xml_space(text: &str) -> String {
+    text.replace(['\n', '\r', '\t'], " ")
+}
+
+fn push_text(spans: &mut Vec<Span>, text: &str, attributes: &StyleAttributes) {
+    match spans.last_mut() {
+        Some(last) if last.attributes == *attributes => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.to_owned(),
+            attributes: attributes.clone(),
+        }),
+    }
+}
+
+/// Parses a TTML document. Only media timing is supported; `<set>`,
+/// animation and metadata are ignored.
+pub fn parse(input: &str) -> Result<Document, Error> {
+    let mut reader = Reader::from_str(input);
+    let mut document = Document::default();
+    let mut params = TimeParameters::default();
+    let mut cell_rows = 15.0;
+
+    let mut seen_root = false;
+    let mut path: Vec<String> = Vec::new();
+    let mut scopes: Vec<Scope> = vec![Scope {
+        begin: Duration::ZERO,
+        end: None,
+        attributes: StyleAttributes::default(),
+        region: None,
+    }];
+    let mut paragraph: Option<Paragraph> = None;
+    let mut region: Option<Region> = None;
+
+    loop {
+        let event = reader.read_event()?;
+        let (start, empty) = match &event {
+            Event::Start(e) => (Some(e), false),
+            Event::Empty(e) => (Some(e), true),
+            _ => (None, false),
+        };
+
+        if let Some(e) = start {
+            let el = element(e)?;
+            if !seen_root {
+                if el.name != "tt" {
+                    return Err(Error::NotTtml);
+                }
+                seen_root = true;
+                if let Some(base) = el.attributes.get("timeBase") {
+                    if base != "media" {
+                        return Err(Error::UnsupportedTimeBase(base.clone()));
+                    }
+                }
+                let rate = |value: &String| value.trim().parse::<f64>().ok().filter(valid_rate);
+                if let Some(value) = el.attributes.get("frameRate") {
+                    params.frame_rate =
+                        rate(value).ok_or_else(|| Error::InvalidRate(value.clone()))?;
+                    if let Some(multiplier) = el.attributes.get("frameRateMultiplier") {
+                        if let Some((num, den)) = multiplier.split_once(' ') {
+                            if let (Ok(num), Ok(den)) = (num.parse::<f64>(), den.parse::<f64>()) {
+                                params.frame_rate = Some(params.frame_rate * num / den)
+                                    .filter(valid_rate)
+                                    .ok_or_else(|| Error::InvalidRate(multiplier.clone()))?;
+                            }
+                        }
+                    }
+                }
+                if let Some(value) = el.attributes.get("tickRate") {
+                    params.tick_rate =
+                        rate(value).ok_or_else(|| Error::InvalidRate(value.clone()))?;
+                }
+                if let Some((_, rows)) = el
+                    .attributes
+                    .get("cellResolution")
+                    .and_then(|r| r.split_once(' '))
+                {
+                    cell_rows = rows.parse().unwrap_or(cell_rows);
+                }
+                document.lang = el.attributes.get("lang").cloned();
+            }
+
+            let parent = scopes.last().cloned().expect("root scope");
+            let referenced = |attributes: &HashMap<String, String>, document: &Document| {
+                let mut inherited = StyleAttributes::default();
+                if let Some(ids) = attributes.get("style") {
+                    for id in ids.split_whitespace() {
+                        if let Some(style) = document.style(id) {
+                            inherited = style.attributes.inherit(&inherited);
+                        }
+                    }
+                }
+                style_attributes(attributes, cell_rows).inherit(&inherited)
+            };
+
+            match el.name.as_str() {
+                "style" if path.iter().any(|p| p == "styling") => {
+                    let attributes = referenced(&el.attributes, &document);
+                    document.styles.push(Style {
+                        id: el.attributes.get("id").cloned().unwrap_or_default(),
+                        attributes,
+                    });
+                }
+                "region" if path.iter().any(|p| p == "layout") => {
+                    region = Some(Region {
+                        id: el.attributes.get("id").cloned().unwrap_or_default(),
+                        origin: el
+                            .attributes
+                            .get("origin")
+                            .and_then(|v| pair(v, "%"))
+                            .unwrap_or((0.0, 0.0)),
+                        extent: el
+                            .attributes
+                            .get("extent")
+                            .and_then(|v| pair(v, "%"))
+                            .unwrap_or((100.0, 100.0)),
+                        display_align: match el.attributes.get("displayAlign").map(|s| s.as_str()) {
+                            Some("center") => DisplayAlign::Center,
+                            Some("after") => DisplayAlign::After,
+                            _ => DisplayAlign::Before,
+                        },
+                    });
+                }
+                "body" | "div" | "p" | "span" => {
+                    let (begin, end) = timing(&el, &parent, &params)?;
+                    let attributes =
+                        referenced(&el.attributes, &document).inherit(&parent.attributes);
+                    let region_id = el
+                        .attributes
+                        .get("region")
+                        .cloned()
+                        .or(parent.region.clone());
+                    if el.name == "p" {
+                        paragraph = Some(Paragraph {
+                            begin,
+                            end: end.unwrap_or(begin),
+                            style: el.attributes.get("style").cloned(),
+                            region: region_id.clone(),
+                            attributes: style_attributes(&el.attributes, cell_rows),
+                            spans: Vec::new(),
+                        });
+                    }
+                    if !empty {
+                        scopes.push(Scope {
+                            begin,
+                            end,
+                            attributes,
+                            region: region_id,
+                        });
+                    }
+                }
+                "br" => {
+                    if let Some(p) = paragraph.as_mut() {
+                        push_text(&mut p.spans, "\n", &parent.attributes);
+                    }
+                }
+                _ => (),
+            }
+
+            if !empty {
+                path.push(el.name);
+            } else if el.name == "region" {
+                document.regions.extend(region.take());
+            } else if el.name == "p" {
+                document.paragraphs.extend(paragraph.take());
+            }
+            continue;
+        }
+
+        match event {
+            Event::Text(text) => {
+                if let Some(p) = paragraph.as_mut() {
+                    let scope = scopes.last().expect("root scope");
+                    push_text(
+                        &mut p.spans,
+                        &xml_space(&text.unescape()?),
+                        &scope.attributes,
+                    );
+                }
+            }
+            Event::CData(data) => {
+                if let Some(p) = paragraph.as_mut() {
+                    let scope = scopes.last().expect("root scope");
+                    push_text(
+                        &mut p.spans,
+                        &xml_space(&String::from_utf8_lossy(&data)),
+                        &scope.attributes,
+                    );
+                }
+            }
+            Event::End(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                path.pop();
+                match name.as_str() {
+                    "body" | "div" | "span" => {
+                        scopes.pop();
+                    }
+                    "p" => {
+                        scopes.pop();
+                        if let Some(mut p) = paragraph.take() {
+                            collapse(&mut p.spans);
+                            document.paragraphs.push(p);
+                        }
+                    }
+                    "region" => document.regions.extend(region.take()),
+                    _ => (),
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    if !seen_root {
+        return Err(Error::NotTtml);
+    }
+    document.paragraphs.sort_by_key(|p| p.begin);
+    Ok(document)
+}
@@ -0,0 +1,211 @@
+//! Conversion to and from SubRip entries.
+
+use crate::{
+    DisplayAlign, Document, Paragraph, Region, Rgba, Span, Style, StyleAttributes, TextAlign,
+};
+use subrip::text::{Font, TextStyle};
+
+const WHITE: Rgba = Rgba {
+    red: 255,
+    green: 255,
+    blue: 255,
+    alpha: 255,
+};
+
+/// The region an `{\anN}` row is shown in: the title-safe area, with text
+/// pushed to its top, middle or bottom.
+pub fn alignment_region(alignment: u8) -> Region {
+    let (id, display_align) = match alignment {
+        7..=9 => ("top", DisplayAlign::Before),
+        4..=6 => ("middle", DisplayAlign::Center),
+        _ => ("bottom", DisplayAlign::After),
+    };
+    Region {
+        id: id.to_owned(),
+        origin: (10.0, 10.0),
+        extent: (80.0, 80.0),
+        display_align,
+    }
+}
+
+/// The `tts:textAlign` an `{\anN}` column stands for.
+pub fn alignment_text_align(alignment: u8) -> TextAlign {
+    match alignment {
+        1 | 4 | 7 => TextAlign::Left,
+        3 | 6 | 9 => TextAlign::Right,
+        _ => TextAlign::Center,
+    }
+}
+
+/// The numpad position of text shown in `region` with `text_align`, judging
+/// the row by where in the video the region puts it.
+pub fn region_alignment(region: Option<&Region>, text_align: Option<TextAlign>) -> u8 {
+    let row = match region {
+        Some(r) => {
+            let y = match r.display_align {
+                DisplayAlign::Before => r.origin.1,
+                DisplayAlign::Center => r.origin.1 + r.extent.1 / 2.0,
+                DisplayAlign::After => r.origin.1 + r.extent.1,
+            };
+            if y < 100.0 / 3.0 {
+                7
+            } else if y < 200.0 / 3.0 {
+                4
+            } else {
+                1
+            }
+        }
+        None => 1,
+    };
+    let column = match text_align {
+        Some(TextAlign::Left) | Some(TextAlign::Start) => 0,
+        Some(TextAlign::Right) | Some(TextAlign::End) => 2,
+        _ => 1,
+    };
+    row + column
+}
+
+pub(crate) fn rgba((red, green, blue): (u8, u8, u8)) -> Rgba {
+    Rgba {
+        red,
+        green,
+        blue,
+        alpha: 255,
+    }
+}
+
+fn span_attributes(style: &TextStyle) -> StyleAttributes {
+    StyleAttributes {
+        bold: style.bold.then_some(true),
+        italic: style.italic.then_some(true),
+        underline: style.underline.then_some(true),
+        color: style.font.rgb().map(rgba),
+        ..StyleAttributes::default()
+    }
+}
+
+/// A paragraph for a cue. The alignment goes to a region and a text
+/// alignment; bold, italic, underline and font colors to its spans.
+pub fn paragraph_from_srt(entry: &subrip::Entry) -> Paragraph {
+    let alignment = entry.alignment.unwrap_or(2);
+    Paragraph {
+        begin: entry.start,
+        end: entry.end,
+        style: Some("default".to_owned()),
+        region: Some(alignment_region(alignment).id),
+        attributes: StyleAttributes {
+            text_align: Some(alignment_text_align(alignment)).filter(|_| alignment % 3 != 2),
+            ..StyleAttributes::default()
+        },
+        spans: entry
+            .parsed_text()
+            .iter()
+            .map(|s| Span {
+                text: s.text.replace("\r\n", "\n"),
+                attributes: span_attributes(&s.style),
+            })
+            .collect(),
+    }
+}
+
+/// An IMSC1 document with one paragraph per cue, white on the default
+/// background.
+pub fn from_srt(entries: &[subrip::Entry]) -> Document {
+    let mut document = Document {
+        lang: Some("en".to_owned()),
+        styles: vec![Style {
+            id: "default".to_owned(),
+            attributes: StyleAttributes {
+                font_family: Some("proportionalSansSerif".to_owned()),
+                color: Some(WHITE),
+                text_align: Some(TextAlign::Center),
+                ..StyleAttributes::default()
+            },
+        }],
+        regions: Vec::new(),
+        paragraphs: entries.iter().map(paragraph_from_srt).collect(),
+    };
+
+    for paragraph in &document.paragraphs {
+        let id = paragraph.region.as_deref().unwrap_or("bottom");
+        if document.region(id).is_none() {
+            let alignment = match id {
+                "top" => 8,
+                "middle" => 5,
+                _ => 2,
+            };
+            document.regions.push(alignment_region(alignment));
+        }
+    }
+    document.regions.sort_by_key(|r| r.display_align as u8);
+
+    document
+}
+
+fn text_style(attributes: &StyleAttributes) -> TextStyle {
+    TextStyle {
+        bold: attributes.bold.unwrap_or(false),
+        italic: attributes.italic.unwrap_or(false),
+        underline: attributes.underline.unwrap_or(false),
+        strikeout: false,
+        font: Font {
+            // white is what players use anyway
+            color: attributes
+                .color
+                .filter(|c| (c.red, c.green, c.blue) != (255, 255, 255))
+                .map(|c| format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue)),
+            ..Font::default()
+        },
+    }
+}
+
+/// A cue for a paragraph, with an `{\anN}` tag unless it sits at the bottom
+/// center.
+pub fn paragraph_to_srt(document: &Document, paragraph: &Paragraph) -> subrip::Entry {
+    let region = paragraph
+        .region
+        .as_deref()
+        .and_then(|id| document.region(id));
+    let mut text_align = paragraph.attributes.text_align;
+    if let Some(ids) = &paragraph.style {
+        // later styles win
+        for id in ids.split_whitespace().rev() {
+            text_align = text_align.or(document.style(id).and_then(|s| s.attributes.text_align));
+        }
+    }
+    let alignment = region_alignment(region, text_align);
+
+    let spans: Vec<subrip::text::Span> = paragraph
+        .spans
+        .iter()
+        .map(|s| subrip::text::Span {
+            text: s.text.clone(),
+            style: text_style(&s.attributes),
+        })
+        .collect();
+
+    subrip::Entry {
+        index: 0,
+        start: paragraph.begin,
+        end: paragraph.end,
+        alignment: Some(alignment).filter(|&a| a != 2),
+        coordinates: None,
+        text: subrip::text::write(&spans),
+    }
+}
+
+/// Converts every paragraph with text to a cue, numbered from 1 in the order
+/// they start.
+pub fn to_srt(document: &Document) -> Vec<subrip::Entry> {
+    let mut entries: Vec<subrip::Entry> = document
+        .paragraphs
+        .iter()
+        .filter(|p| !p.plain_text().trim().is_empty())
+        .map(|p| paragraph_to_srt(document, p))
+        .collect();
+    entries.sort_by_key(|e| e.start);
+    for (n, entry) in entries.iter_mut().enumerate() {
+        entry.index = n as u32 + 1;
+    }
+    entries
+}
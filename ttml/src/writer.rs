@@ -0,0 +1,179 @@
+use crate::{DisplayAlign, Document, Rgba, StyleAttributes, TextAlign};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::time::Duration;
+
+pub const IMSC1_TEXT_PROFILE: &str = "http://www.w3.org/ns/ttml/profile/imsc1/text";
+
+/// `HH:MM:SS.mmm`, a clock time in the media time base.
+pub fn format_time(time: Duration) -> String {
+    let ms = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn format_color(c: Rgba) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", c.red, c.green, c.blue, c.alpha)
+}
+
+fn percent(value: f64) -> String {
+    format!("{}%", (value * 100.0).round() / 100.0)
+}
+
+fn attributes(attrs: &StyleAttributes) -> String {
+    let mut out = String::new();
+    if let Some(family) = &attrs.font_family {
+        let _ = write!(out, " tts:fontFamily=\"{}\"", escape(family));
+    }
+    if let Some(size) = attrs.font_size {
+        let _ = write!(out, " tts:fontSize=\"{}c\"", (size * 100.0).round() / 100.0);
+    }
+    if let Some(color) = attrs.color {
+        let _ = write!(out, " tts:color=\"{}\"", format_color(color));
+    }
+    if let Some(color) = attrs.background_color {
+        let _ = write!(out, " tts:backgroundColor=\"{}\"", format_color(color));
+    }
+    if let Some(bold) = attrs.bold {
+        let _ = write!(
+            out,
+            " tts:fontWeight=\"{}\"",
+            if bold { "bold" } else { "normal" }
+        );
+    }
+    if let Some(italic) = attrs.italic {
+        let _ = write!(
+            out,
+            " tts:fontStyle=\"{}\"",
+            if italic { "italic" } else { "normal" }
+        );
+    }
+    if let Some(underline) = attrs.underline {
+        let _ = write!(
+            out,
+            " tts:textDecoration=\"{}\"",
+            if underline {
+                "underline"
+            } else {
+                "noUnderline"
+            }
+        );
+    }
+    if let Some(align) = attrs.text_align {
+        let align = match align {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+            TextAlign::Start => "start",
+            TextAlign::End => "end",
+        };
+        let _ = write!(out, " tts:textAlign=\"{}\"", align);
+    }
+    out
+}
+
+/// Writes a document as IMSC1 Text Profile TTML, with clock times in the
+/// media time base and regions in percent.
+pub fn to_string(document: &Document) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<tt xmlns=\"http://www.w3.org/ns/ttml\" \
+         xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+         xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" \
+         ttp:profile=\"{}\" ttp:timeBase=\"media\" xml:lang=\"{}\">",
+        IMSC1_TEXT_PROFILE,
+        escape(document.lang.as_deref().unwrap_or(""))
+    );
+
+    out.push_str("  <head>\n");
+    if !document.styles.is_empty() {
+        out.push_str("    <styling>\n");
+        for style in &document.styles {
+            let _ = writeln!(
+                out,
+                "      <style xml:id=\"{}\"{}/>",
+                escape(&style.id),
+                attributes(&style.attributes)
+            );
+        }
+        out.push_str("    </styling>\n");
+    }
+    if !document.regions.is_empty() {
+        out.push_str("    <layout>\n");
+        for region in &document.regions {
+            let align = match region.display_align {
+                DisplayAlign::Before => "before",
+                DisplayAlign::Center => "center",
+                DisplayAlign::After => "after",
+            };
+            let _ = writeln!(
+                out,
+                "      <region xml:id=\"{}\" tts:origin=\"{} {}\" tts:extent=\"{} {}\" tts:displayAlign=\"{}\"/>",
+                escape(&region.id),
+                percent(region.origin.0),
+                percent(region.origin.1),
+                percent(region.extent.0),
+                percent(region.extent.1),
+                align
+            );
+        }
+        out.push_str("    </layout>\n");
+    }
+    out.push_str("  </head>\n");
+
+    out.push_str("  <body>\n    <div>\n");
+    for p in &document.paragraphs {
+        let _ = write!(
+            out,
+            "      <p begin=\"{}\" end=\"{}\"",
+            format_time(p.begin),
+            format_time(p.end)
+        );
+        if let Some(style) = &p.style {
+            let _ = write!(out, " style=\"{}\"", escape(style));
+        }
+        if let Some(region) = &p.region {
+            let _ = write!(out, " region=\"{}\"", escape(region));
+        }
+        out.push_str(&attributes(&p.attributes));
+        out.push('>');
+        for span in &p.spans {
+            let lines: Vec<String> = span.text.split('\n').map(escape).collect();
+            let text = lines.join("<br/>");
+            if span.attributes.is_empty() {
+                out.push_str(&text);
+            } else {
+                let _ = write!(out, "<span{}>{}</span>", attributes(&span.attributes), text);
+            }
+        }
+        out.push_str("</p>\n");
+    }
+    out.push_str("    </div>\n  </body>\n</tt>\n");
+
+    out
+}
+
+pub fn write_document<W: Write>(mut writer: W, document: &Document) -> io::Result<()> {
+    writer.write_all(to_string(document).as_bytes())
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+use substation::{Entry, Script, Style};
+use ttml::DisplayAlign;
+
+fn script(style_alignment: usize, text: &str) -> Script {
+    let mut script = Script::default();
+    let style = Style {
+        name: "Default".to_owned(),
+        fontname: "Arial".to_owned(),
+        fontsize: 20.0,
+        alignment: Some(style_alignment),
+        margin_v: Some(18),
+        ..Style::default()
+    };
+    script.styles.insert(style.name.clone(), style);
+    script.events.push(Entry {
+        start: Some(Duration::from_secs(1)),
+        end: Some(Duration::from_secs(2)),
+        style: Some("Default".to_owned()),
+        text: text.to_owned(),
+        ..Entry::default()
+    });
+    script
+}
+
+#[test]
+fn invalid_alignment_is_bottom_center() {
+    let expected = ttml::ass::from_ass(&script(2, "bottom")).regions;
+    assert_eq!(expected[0].display_align, DisplayAlign::After);
+    for (style, text) in [(2, "{\\an0}zero"), (0, "zero")] {
+        let document = ttml::ass::from_ass(&script(style, text));
+        assert_eq!(document.regions, expected, "{:?}", text);
+    }
+}
+
+#[test]
+fn event_margins_override_the_style() {
+    let entry = Entry {
+        margin_v: Some(36),
+        ..Entry::default()
+    };
+    let style = Style {
+        margin_l: Some(10),
+        margin_r: Some(20),
+        margin_v: Some(18),
+        ..Style::default()
+    };
+    let region = ttml::ass::placement(&entry, Some(&style), 8, None, (400.0, 360.0));
+    assert_eq!(region.origin, (2.5, 10.0));
+    assert_eq!(region.extent, (92.5, 80.0));
+    assert_eq!(region.display_align, DisplayAlign::Before);
+}
@@ -0,0 +1,251 @@
+use std::time::Duration;
+use ttml::{DisplayAlign, Error, Rgba, TextAlign};
+
+fn sample(name: &str) -> String {
+    let path = format!("{}/tests/samples/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn parses_imsc1_sample() {
+    let doc = ttml::parse(&sample("imsc1_basic.ttml")).unwrap();
+
+    assert_eq!(doc.lang.as_deref(), Some("en"));
+    assert_eq!(doc.styles.len(), 2);
+    let yellow = doc.style("yellow").unwrap();
+    assert_eq!(
+        yellow.attributes.font_family.as_deref(),
+        Some("proportionalSansSerif")
+    );
+    assert_eq!(
+        yellow.attributes.color,
+        Some(Rgba {
+            red: 255,
+            green: 255,
+            blue: 0,
+            alpha: 255
+        })
+    );
+    assert_eq!(
+        doc.region("top").unwrap().display_align,
+        DisplayAlign::Before
+    );
+    assert_eq!(doc.region("bottom").unwrap().extent, (80.0, 80.0));
+
+    let p = &doc.paragraphs;
+    assert_eq!(p.len(), 3);
+    assert_eq!((p[0].begin, p[0].end), (ms(1_000), ms(3_500)));
+    assert_eq!(p[0].plain_text(), "Hello, world!");
+    assert_eq!(p[0].region.as_deref(), Some("bottom"));
+    assert_eq!(p[0].spans[1].text, "world");
+    assert_eq!(p[0].spans[1].attributes.italic, Some(true));
+
+    assert_eq!(p[1].plain_text(), "First line\nsecond line");
+    assert_eq!(p[1].spans[0].attributes.color, yellow.attributes.color);
+
+    assert_eq!(p[2].plain_text(), "Fish & chips");
+    assert_eq!(p[2].region.as_deref(), Some("top"));
+    assert_eq!(p[2].attributes.text_align, Some(TextAlign::Left));
+    assert_eq!(p[2].spans[1].attributes.bold, Some(true));
+}
+
+#[test]
+fn parses_tick_and_offset_times() {
+    let doc = ttml::parse(&sample("tick_rate.ttml")).unwrap();
+    let times: Vec<(Duration, Duration)> =
+        doc.paragraphs.iter().map(|p| (p.begin, p.end)).collect();
+
+    // the div starts at 10s and its paragraphs are relative to it
+    assert_eq!(
+        times,
+        [
+            (ms(11_000), ms(13_000)),
+            (ms(13_500), ms(15_000)),
+            (ms(70_000), ms(130_000))
+        ]
+    );
+}
+
+#[test]
+fn parses_frame_times() {
+    let doc = ttml::parse(&sample("frames.ttml")).unwrap();
+    let p = &doc.paragraphs;
+
+    // frames count at 29.97 a second, whole seconds stay whole
+    assert_eq!(p[0].begin.as_millis(), 1_500);
+    assert_eq!(p[0].end.as_millis(), 2_000);
+    assert_eq!(p[1].begin.as_millis(), 3_003);
+    assert_eq!(p[1].plain_text(), "rouge et souligné");
+    assert_eq!(p[1].spans[0].attributes.color.unwrap().alpha, 0x80);
+    assert_eq!(p[1].spans[2].attributes.underline, Some(true));
+}
+
+#[test]
+fn rejects_other_documents() {
+    assert_eq!(ttml::parse("<html><body/></html>"), Err(Error::NotTtml));
+    assert!(matches!(
+        ttml::parse(
+            "<tt xmlns=\"http://www.w3.org/ns/ttml\"><body><p begin=\"soon\">x</p></body></tt>"
+        ),
+        Err(Error::InvalidTime(_))
+    ));
+}
+
+#[test]
+fn rejects_times_too_large_for_a_duration() {
+    for body in [
+        "<p begin=\"100000000000000000000000s\">x</p>",
+        "<p begin=\"0s\" dur=\"1000000000000000000000h\">x</p>",
+        "<p begin=\"99999999999999999999:00:00\">x</p>",
+        "<div begin=\"18446744073709551615s\"><p begin=\"18446744073709551615s\">x</p></div>",
+    ] {
+        let input = format!(
+            "<tt xmlns=\"http://www.w3.org/ns/ttml\"><body>{}</body></tt>",
+            body
+        );
+        assert!(
+            matches!(ttml::parse(&input), Err(Error::InvalidTime(_))),
+            "{}",
+            body
+        );
+    }
+}
+
+#[test]
+fn rejects_rates_that_are_not_positive() {
+    for attributes in [
+        "ttp:frameRate=\"0\"",
+        "ttp:frameRate=\"-25\"",
+        "ttp:frameRate=\"fast\"",
+        "ttp:frameRate=\"30\" ttp:frameRateMultiplier=\"0 1\"",
+        "ttp:frameRate=\"30\" ttp:frameRateMultiplier=\"1 0\"",
+        "ttp:tickRate=\"0\"",
+        "ttp:tickRate=\"NaN\"",
+    ] {
+        let input = format!(
+            "<tt xmlns=\"http://www.w3.org/ns/ttml\" \
+             xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" {}>\
+             <body><p begin=\"25f\" end=\"10t\">x</p></body></tt>",
+            attributes
+        );
+        assert!(
+            matches!(ttml::parse(&input), Err(Error::InvalidRate(_))),
+            "{}",
+            attributes
+        );
+    }
+}
+
+#[test]
+fn written_documents_read_back() {
+    for name in ["imsc1_basic.ttml", "tick_rate.ttml", "frames.ttml"] {
+        let doc = ttml::parse(&sample(name)).unwrap();
+        let written = ttml::writer::to_string(&doc);
+        assert!(written.contains("ttp:profile=\"http://www.w3.org/ns/ttml/profile/imsc1/text\""));
+
+        let again = ttml::parse(&written).unwrap();
+        assert_eq!(again.styles, doc.styles, "{}", name);
+        assert_eq!(again.regions, doc.regions, "{}", name);
+        assert_eq!(again.paragraphs.len(), doc.paragraphs.len(), "{}", name);
+        for (a, b) in again.paragraphs.iter().zip(&doc.paragraphs) {
+            assert_eq!(a.plain_text(), b.plain_text(), "{}", name);
+            assert_eq!(a.begin.as_millis(), b.begin.as_millis(), "{}", name);
+            assert_eq!(a.end.as_millis(), b.end.as_millis(), "{}", name);
+            let attributes = |p: &ttml::Paragraph| -> Vec<_> {
+                p.spans.iter().map(|s| s.attributes.clone()).collect()
+            };
+            assert_eq!(attributes(a), attributes(b), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn converts_to_subrip() {
+    let doc = ttml::parse(&sample("imsc1_basic.ttml")).unwrap();
+    let entries = ttml::srt::to_srt(&doc);
+
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].index, 1);
+    assert_eq!(entries[0].text, "Hello, <i>world</i>!");
+    assert_eq!(entries[0].alignment, None);
+    assert_eq!(
+        entries[1].text,
+        "<font color=\"#ffff00\">First line\nsecond line</font>"
+    );
+    assert_eq!(entries[2].text, "Fish & <b>chips</b>");
+    assert_eq!(entries[2].alignment, Some(7));
+}
+
+#[test]
+fn subrip_round_trip() {
+    let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hi</i> there\r\nsecond line\r\n\r\n\
+               2\r\n00:00:03,000 --> 00:00:04,000\r\n{\\an8}<font color=\"#ff0000\">Up</font> top\r\n";
+    let (_, entries) = subrip::entries(srt).unwrap();
+
+    let doc = ttml::srt::from_srt(&entries);
+    let written = ttml::writer::to_string(&doc);
+    let back = ttml::srt::to_srt(&ttml::parse(&written).unwrap());
+
+    assert_eq!(back.len(), 2);
+    assert_eq!((back[0].start, back[0].end), (ms(1_000), ms(2_500)));
+    assert_eq!(back[0].text, "<i>Hi</i> there\nsecond line");
+    assert_eq!(back[1].alignment, Some(8));
+    assert_eq!(back[1].text, "<font color=\"#ff0000\">Up</font> top");
+}
+
+#[test]
+fn ass_round_trip() {
+    let script = "[Script Info]\nPlayResX: 1920\nPlayResY: 1080\n\n\
+        [V4+ Styles]\n\
+        Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+        Style: Default,Arial,72,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,96,96,54,1\n\
+        Style: Sign,Georgia,54,&H0000FFFF,&H000000FF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,2,2,8,10,10,10,1\n\n\
+        [Events]\n\
+        Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+        Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,Plain and {\\i1}slanted{\\i0}\\Nnext line\n\
+        Comment: 0,0:00:02.00,0:00:03.00,Default,,0,0,0,,not shown\n\
+        Dialogue: 0,0:00:04.00,0:00:05.00,Sign,,0,0,0,,{\\pos(960,100)}A sign\n\
+        Dialogue: 0,0:00:06.00,0:00:07.00,Default,,0,0,0,,{\\c&H0000FF&}Red\n";
+    let (_, script) = substation::parser::script(script).unwrap();
+
+    let doc = ttml::ass::from_ass(&script);
+    assert_eq!(doc.styles.len(), 2);
+    assert_eq!(doc.styles[0].id, "Default");
+    // 72px of 1080 is one of 15 cells
+    assert_eq!(doc.styles[0].attributes.font_size, Some(1.0));
+    assert_eq!(doc.styles[1].attributes.bold, Some(true));
+    assert_eq!(doc.paragraphs.len(), 3);
+    assert_eq!(doc.regions.len(), 2);
+
+    let bottom = doc
+        .region(doc.paragraphs[0].region.as_deref().unwrap())
+        .unwrap();
+    assert_eq!(bottom.origin, (5.0, 5.0));
+    assert_eq!(bottom.extent, (90.0, 90.0));
+    assert_eq!(bottom.display_align, DisplayAlign::After);
+    let sign = doc
+        .region(doc.paragraphs[1].region.as_deref().unwrap())
+        .unwrap();
+    assert_eq!(sign.display_align, DisplayAlign::Before);
+    assert_eq!(sign.origin.1, 9.26);
+
+    let parsed = ttml::parse(&ttml::writer::to_string(&doc)).unwrap();
+    let back = ttml::ass::to_ass(&parsed);
+    let texts: Vec<&str> = back.events.iter().map(|e| e.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "Plain and {\\i1}slanted{\\i0}\\Nnext line",
+            "{\\an8}A sign",
+            "{\\c&H0000FF&}Red"
+        ]
+    );
+    assert_eq!(back.events[0].margin_l, Some(96));
+    assert_eq!(back.events[0].margin_v, Some(54));
+    assert_eq!(back.styles["Sign"].fontsize, 54.0);
+    assert_eq!(back.styles["Sign"].bold, Some(true));
+}
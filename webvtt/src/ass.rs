@@ -0,0 +1,346 @@
+//! Export of ASS scripts for players without an ASS renderer.
+//!
+//! Every ASS style becomes a cue class styled in a `STYLE` block, placement
+//! from alignment, margins and `\pos` becomes cue settings, and bold,
+//! italic, underline and the basic colors are kept inline. Everything else is
+//! dropped and listed in the returned [`Loss`]es.
+
+use crate::srt::{merge, span_node};
+use crate::text::{Node, SpanKind};
+use crate::{Align, Block, Cue, CueSettings, LineAlign, LineValue, PositionAlign, WebVtt};
+use std::collections::BTreeSet;
+use substation::{alignment_cell, Color, Entry, Script, Style, StyleOverride, TextSection};
+
+/// Something an event used that WebVTT can't show.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loss {
+    /// Index of the event in `Script::events`.
+    pub event: usize,
+    pub feature: String,
+}
+
+/// The CSS class used for cues of an ASS style.
+pub fn class_name(style: &str) -> String {
+    let class: String = style
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if class.is_empty() || class.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        format!("s{}", class)
+    } else {
+        class
+    }
+}
+
+fn css_color(color: &Color) -> String {
+    // ASS alpha is transparency, CSS alpha opacity
+    let alpha = 1.0 - color.alpha.unwrap_or(0) as f64 / 255.0;
+    format!(
+        "rgba({}, {}, {}, {})",
+        color.red,
+        color.green,
+        color.blue,
+        (alpha * 1000.0).round() / 1000.0
+    )
+}
+
+/// A `::cue(.class)` rule for each style, with its font, colors and outline.
+/// Bold and italic are left to the cue text, where overrides can undo them.
+pub fn style_css(styles: &[&Style]) -> String {
+    let mut css = String::new();
+    for style in styles {
+        let mut rules: Vec<String> = Vec::new();
+        if !style.fontname.is_empty() {
+            rules.push(format!(
+                "font-family: \"{}\"",
+                style.fontname.replace('"', "")
+            ));
+        }
+        if let Some(color) = &style.primary_color {
+            rules.push(format!("color: {}", css_color(color)));
+        }
+        if style.border_style == Some(3) {
+            if let Some(color) = style.back_color.as_ref().or(style.outline_color.as_ref()) {
+                rules.push(format!("background-color: {}", css_color(color)));
+            }
+        } else {
+            rules.push("background-color: transparent".to_owned());
+            if let (Some(size), Some(color)) = (style.outline_size, &style.outline_color) {
+                if size > 0 {
+                    rules.push(format!("text-shadow: 0 0 {}px {}", size, css_color(color)));
+                }
+            }
+        }
+        css.push_str(&format!(
+            "::cue(.{}) {{\n  {};\n}}\n",
+            class_name(&style.name),
+            rules.join(";\n  ")
+        ));
+    }
+    css.trim_end().to_owned()
+}
+
+/// The script resolution, see [`Script::play_res`].
+pub fn play_res(script: &Script) -> (f64, f64) {
+    script.play_res()
+}
+
+fn feature(o: &StyleOverride) -> Option<String> {
+    use StyleOverride::*;
+    let name = match o {
+        // drawings themselves are reported instead
+        DrawingMode(_) => return None,
+        Bold(_) | Italic(_) | Underline(_) | NumpadLayoutAlignment(_) | Reset(_) => return None,
+        Color(1, _) => return None,
+        Alignment(_) => "\\a",
+        StrikeOut(_) => "\\s",
+        Border(_) => "\\bord",
+        Shadow(_) => "\\shad",
+        BlurEdges(_) => "\\be",
+        FontName(_) => "\\fn",
+        FontSize(_) => "\\fs",
+        ScaleX(_) | ScaleY(_) => "\\fsc",
+        LetterSpacing(_) => "\\fsp",
+        RotationX(_) | RotationY(_) | RotationZ(_) => "\\fr",
+        Charset(_) => "\\fe",
+        Color(..) => "\\c",
+        Alpha(..) => "\\alpha",
+        KaraokeDuration(_) => "\\k",
+        WrappingStyle(_) => "\\q",
+        BaselineOffset(_) => "\\pbo",
+        Transition { .. } => "\\t",
+        Move {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            ..
+        } if start_x == end_x && start_y == end_y => return None,
+        Move { .. } => "\\move",
+        Origin { .. } => "\\org",
+        Fade { .. } => "\\fade",
+        FadeInAndOut { .. } => "\\fad",
+        Clip { .. } | ClipToDrawing(..) | EmptyClip => "\\clip",
+        Other(tag) => {
+            let tag = tag.trim_start_matches('\\');
+            let len = tag
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(tag.len());
+            return Some(format!("\\{}", &tag[..len]));
+        }
+    };
+    Some(name.to_owned())
+}
+
+fn position(sections: &[TextSection]) -> Option<(f64, f64)> {
+    sections.iter().find_map(|s| match s {
+        TextSection::StyleOverride(overrides) => overrides.iter().find_map(|o| match o {
+            StyleOverride::Move {
+                start_x, start_y, ..
+            } => Some((*start_x, *start_y)),
+            _ => None,
+        }),
+        _ => None,
+    })
+}
+
+fn percent(value: f64, of: f64) -> f64 {
+    (value / of * 100.0).clamp(0.0, 100.0)
+}
+
+/// Cue settings placing an event like an ASS renderer would, as far as
+/// WebVTT's line and position model allows.
+pub fn placement(
+    entry: &Entry,
+    style: Option<&Style>,
+    alignment: u8,
+    pos: Option<(f64, f64)>,
+    (width, height): (f64, f64),
+) -> CueSettings {
+    let mut settings = CueSettings::default();
+    let (column, row) = alignment_cell(alignment);
+
+    settings.align = Some(match column {
+        0 => Align::Left,
+        1 => Align::Center,
+        _ => Align::Right,
+    });
+    let position_align = match column {
+        0 => PositionAlign::LineLeft,
+        1 => PositionAlign::Center,
+        _ => PositionAlign::LineRight,
+    };
+    let line_align = match row {
+        0 => LineAlign::End,
+        1 => LineAlign::Center,
+        _ => LineAlign::Start,
+    };
+
+    let (x, y) = match pos {
+        Some(pos) => pos,
+        None => {
+            let (margin_l, margin_r, margin_v) = entry.margins(style);
+            let x = match column {
+                0 => margin_l,
+                1 => (margin_l + width - margin_r) / 2.0,
+                _ => width - margin_r,
+            };
+            let y = match row {
+                0 => height - margin_v,
+                1 => height / 2.0,
+                _ => margin_v,
+            };
+            (x, y)
+        }
+    };
+
+    let bottom_center = (column, row) == (1, 0);
+    if pos.is_some() || !bottom_center {
+        settings.line = Some((LineValue::Percent(percent(y, height)), Some(line_align)));
+        settings.position = Some((percent(x, width), Some(position_align)));
+    }
+    if bottom_center && pos.is_none() {
+        settings.align = None;
+    }
+    settings
+}
+
+/// Exports a script's dialogue as WebVTT. Comments are skipped and so are
+/// events left without any text.
+pub fn from_ass(script: &Script) -> (WebVtt, Vec<Loss>) {
+    let mut losses = Vec::new();
+    let resolution = play_res(script);
+
+    let mut styles: Vec<&Style> = script.styles.values().collect();
+    styles.sort_by_key(|s| (s.name != "Default", s.name.as_str()));
+
+    let mut vtt = WebVtt::default();
+    if !styles.is_empty() {
+        vtt.blocks.push(Block::Style(style_css(&styles)));
+    }
+
+    let mut cues: Vec<Cue> = Vec::new();
+    for (n, entry) in script.events.iter().enumerate() {
+        if entry.kind.as_deref() == Some("Comment") {
+            continue;
+        }
+        let sections = entry.parsed_text();
+        let mut lost = BTreeSet::new();
+        for section in &sections {
+            match section {
+                TextSection::StyleOverride(overrides) => {
+                    lost.extend(overrides.iter().filter_map(feature));
+                }
+                TextSection::Drawing(_) => {
+                    lost.insert("drawing".to_owned());
+                }
+                TextSection::Text(_) => (),
+            }
+        }
+
+        let (spans, alignment) = substation::srt::srt_spans(entry, &script.styles);
+        let mut nodes = Vec::new();
+        for span in &spans {
+            if span.style.strikeout {
+                lost.insert("\\s".to_owned());
+            }
+            let node = span_node(span);
+            if span.style.font.color.is_some() && !contains_class(&node) {
+                lost.insert("\\c".to_owned());
+            }
+            nodes.push(node);
+        }
+        let nodes = merge(nodes);
+        let text = crate::text::write(&nodes);
+        if crate::text::plain_text(&nodes).trim().is_empty() {
+            continue;
+        }
+
+        let style = entry.style.as_deref().and_then(|s| script.styles.get(s));
+        let class = class_name(entry.style.as_deref().unwrap_or("Default"));
+        let settings = placement(
+            entry,
+            style,
+            alignment.unwrap_or(2),
+            position(&sections),
+            resolution,
+        );
+
+        losses.extend(lost.into_iter().map(|feature| Loss { event: n, feature }));
+        cues.push(Cue {
+            identifier: None,
+            start: entry.start.unwrap_or_default(),
+            end: entry.end.unwrap_or_default(),
+            settings,
+            text: format!("<c.{}>{}</c>", class, text),
+        });
+    }
+
+    cues.sort_by_key(|c| c.start);
+    vtt.blocks.extend(cues.into_iter().map(Block::Cue));
+
+    (vtt, losses)
+}
+
+fn contains_class(node: &Node) -> bool {
+    match node {
+        Node::Span {
+            kind: SpanKind::Class,
+            ..
+        } => true,
+        Node::Span { children, .. } => children.iter().any(contains_class),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(style_alignment: usize, text: &str) -> Script {
+        let mut script = Script::default();
+        let style = Style {
+            name: "Default".to_owned(),
+            fontname: "Arial".to_owned(),
+            fontsize: 20.0,
+            alignment: Some(style_alignment),
+            ..Style::default()
+        };
+        script.styles.insert(style.name.clone(), style);
+        script.events.push(Entry {
+            start: Some(std::time::Duration::from_secs(1)),
+            end: Some(std::time::Duration::from_secs(2)),
+            style: Some("Default".to_owned()),
+            text: text.to_owned(),
+            ..Entry::default()
+        });
+        script
+    }
+
+    #[test]
+    fn invalid_alignment_is_bottom_center() {
+        for (style, text) in [(2, "{\\an0}zero"), (0, "zero"), (12, "twelve")] {
+            let (vtt, _) = from_ass(&script(style, text));
+            let cues: Vec<&Cue> = vtt.cues().collect();
+            assert_eq!(cues.len(), 1);
+            assert_eq!(cues[0].settings, CueSettings::default(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn top_left() {
+        let settings = placement(&Entry::default(), None, 7, None, (384.0, 288.0));
+        assert_eq!(settings.align, Some(Align::Left));
+        assert_eq!(
+            settings.position,
+            Some((0.0, Some(PositionAlign::LineLeft)))
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! WebVTT through the workspace's common [`SubtitleFormat`] trait,
+//! converting by way of SubRip entries with [`crate::srt`].
+
+use crate::srt::{from_srt, to_srt};
+use crate::{parse, ParseError};
+use parsing_utils::format::{Cue, Format, FormatError, SubtitleFormat};
+
+impl From<ParseError> for FormatError {
+    fn from(e: ParseError) -> FormatError {
+        FormatError {
+            line: Some(e.line),
+            message: e.message,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebVttFormat;
+
+impl SubtitleFormat for WebVttFormat {
+    fn format(&self) -> Format {
+        Format::WebVtt
+    }
+
+    fn parse(&self, input: &str) -> Result<Vec<Cue>, FormatError> {
+        Ok(subrip::format::cues(&to_srt(&parse(input)?)))
+    }
+
+    fn write(&self, cues: &[Cue]) -> String {
+        from_srt(&subrip::format::entries(cues)).to_string()
+    }
+}
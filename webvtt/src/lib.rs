@@ -0,0 +1,495 @@
+use nom::{
+    bytes::complete::tag,
+    character::complete::{char, digit1},
+    combinator::{map_res, opt, verify},
+    error::ErrorKind,
+    sequence::{preceded, terminated, tuple},
+    IResult,
+};
+use parsing_utils::*;
+use std::fmt;
+use std::time::Duration;
+
+pub mod ass;
+pub mod format;
+pub mod srt;
+pub mod text;
+pub mod writer;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WebVtt {
+    /// Whatever follows `WEBVTT` on the first line, and any header lines
+    /// after it.
+    pub header: String,
+    pub blocks: Vec<Block>,
+}
+
+impl WebVtt {
+    pub fn cues(&self) -> impl Iterator<Item = &Cue> {
+        self.blocks.iter().filter_map(|b| match b {
+            Block::Cue(c) => Some(c),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Cue(Cue),
+    /// The CSS inside a `STYLE` block.
+    Style(String),
+    Region(Region),
+    /// The text of a `NOTE` comment.
+    Note(String),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cue {
+    pub identifier: Option<String>,
+    pub start: Duration,
+    pub end: Duration,
+    pub settings: CueSettings,
+    pub text: String,
+}
+
+impl Cue {
+    pub fn parsed_text(&self) -> Vec<text::Node> {
+        text::parse(&self.text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vertical {
+    RightToLeft,
+    LeftToRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineValue {
+    Percent(f64),
+    Number(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineAlign {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionAlign {
+    LineLeft,
+    Center,
+    LineRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CueSettings {
+    pub vertical: Option<Vertical>,
+    pub line: Option<(LineValue, Option<LineAlign>)>,
+    /// A percentage of the video width.
+    pub position: Option<(f64, Option<PositionAlign>)>,
+    /// A percentage of the video width.
+    pub size: Option<f64>,
+    pub align: Option<Align>,
+    pub region: Option<String>,
+}
+
+impl CueSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == CueSettings::default()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Region {
+    pub id: String,
+    pub width: Option<f64>,
+    pub lines: Option<u32>,
+    pub region_anchor: Option<(f64, f64)>,
+    pub viewport_anchor: Option<(f64, f64)>,
+    pub scroll_up: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line parsing failed on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn number(input: &str) -> IResult<&str, u64> {
+    map_res(digit1, |d: &str| d.parse::<u64>())(input)
+}
+
+// (hours:)?minutes:seconds.milliseconds, minutes and seconds below 60
+pub fn timestamp(input: &str) -> IResult<&str, Duration> {
+    let (input, (first, second, third)) = tuple((
+        terminated(number, char(':')),
+        number,
+        opt(preceded(char(':'), number)),
+    ))(input)?;
+    let (input, millis) = preceded(char('.'), verify(digit1, |d: &str| d.len() == 3))(input)?;
+    let (hours, mins, secs) = match third {
+        Some(secs) => (first, second, secs),
+        None => (0, first, second),
+    };
+    let millis = millis.parse::<u64>().unwrap_or(0);
+    let error = |kind| nom::Err::Error(nom::error::Error::new(input, kind));
+    if mins >= 60 || secs >= 60 {
+        return Err(error(ErrorKind::Verify));
+    }
+    let total = hours
+        .checked_mul(3_600_000)
+        .and_then(|h| h.checked_add(mins * 60_000 + secs * 1_000 + millis))
+        .ok_or_else(|| error(ErrorKind::TooLarge))?;
+    Ok((input, Duration::from_millis(total)))
+}
+
+fn percent(value: &str) -> Option<f64> {
+    value
+        .strip_suffix('%')?
+        .parse::<f64>()
+        .ok()
+        .filter(|v| (0.0..=100.0).contains(v))
+}
+
+fn anchor(value: &str) -> Option<(f64, f64)> {
+    let (x, y) = value.split_once(',')?;
+    Some((percent(x)?, percent(y)?))
+}
+
+/// Reads `name:value` cue settings, ignoring unknown or malformed ones as the
+/// spec asks.
+pub fn cue_settings(input: &str) -> CueSettings {
+    let mut settings = CueSettings::default();
+    for setting in input.split_whitespace() {
+        let Some((name, value)) = setting.split_once(':') else {
+            continue;
+        };
+        match name {
+            "vertical" => {
+                settings.vertical = match value {
+                    "rl" => Some(Vertical::RightToLeft),
+                    "lr" => Some(Vertical::LeftToRight),
+                    _ => settings.vertical,
+                }
+            }
+            "line" => {
+                let (value, align) = match value.split_once(',') {
+                    Some((v, a)) => (v, Some(a)),
+                    None => (value, None),
+                };
+                let align = match align {
+                    Some("start") => Some(LineAlign::Start),
+                    Some("center") => Some(LineAlign::Center),
+                    Some("end") => Some(LineAlign::End),
+                    Some(_) => continue,
+                    None => None,
+                };
+                let value = if value.ends_with('%') {
+                    percent(value).map(LineValue::Percent)
+                } else {
+                    value.parse::<f64>().ok().map(LineValue::Number)
+                };
+                if let Some(value) = value {
+                    settings.line = Some((value, align));
+                }
+            }
+            "position" => {
+                let (value, align) = match value.split_once(',') {
+                    Some((v, a)) => (v, Some(a)),
+                    None => (value, None),
+                };
+                let align = match align {
+                    Some("line-left") => Some(PositionAlign::LineLeft),
+                    Some("center") => Some(PositionAlign::Center),
+                    Some("line-right") => Some(PositionAlign::LineRight),
+                    Some("auto") | None => None,
+                    Some(_) => continue,
+                };
+                if let Some(value) = percent(value) {
+                    settings.position = Some((value, align));
+                }
+            }
+            "size" => settings.size = percent(value).or(settings.size),
+            "align" => {
+                settings.align = match value {
+                    "start" => Some(Align::Start),
+                    "center" | "middle" => Some(Align::Center),
+                    "end" => Some(Align::End),
+                    "left" => Some(Align::Left),
+                    "right" => Some(Align::Right),
+                    _ => settings.align,
+                }
+            }
+            "region" => settings.region = Some(value.to_owned()),
+            _ => (),
+        }
+    }
+    settings
+}
+
+fn region(input: &str) -> Region {
+    let mut region = Region::default();
+    for setting in input.split_whitespace() {
+        let Some((name, value)) = setting.split_once(':') else {
+            continue;
+        };
+        match name {
+            "id" => region.id = value.to_owned(),
+            "width" => region.width = percent(value),
+            "lines" => region.lines = value.parse::<u32>().ok(),
+            "regionanchor" => region.region_anchor = anchor(value),
+            "viewportanchor" => region.viewport_anchor = anchor(value),
+            "scroll" => region.scroll_up = value == "up",
+            _ => (),
+        }
+    }
+    region
+}
+
+// start --> end settings
+fn timing(input: &str) -> IResult<&str, (Duration, Duration)> {
+    let (input, (start, _, end)) = tuple((timestamp, ws(tag("-->")), timestamp))(input)?;
+    Ok((input, (start, end)))
+}
+
+fn is_keyword(line: &str, keyword: &str) -> bool {
+    line.strip_prefix(keyword)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Parses a WebVTT file. Cues with a malformed timing line are an error; other
+/// unrecognized blocks are skipped like a browser would.
+pub fn parse(input: &str) -> Result<WebVtt, ParseError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let lines: Vec<&str> = input
+        .split('\n')
+        .map(|l| l.trim_end_matches('\r'))
+        .collect();
+
+    let Some(signature) = lines.first().and_then(|l| l.strip_prefix("WEBVTT")) else {
+        return Err(ParseError {
+            line: 1,
+            message: "missing WEBVTT signature".to_owned(),
+        });
+    };
+    if !(signature.is_empty() || signature.starts_with([' ', '\t'])) {
+        return Err(ParseError {
+            line: 1,
+            message: "missing WEBVTT signature".to_owned(),
+        });
+    }
+
+    let mut vtt = WebVtt::default();
+    let mut header = vec![signature.trim_start()];
+    let mut n = 1;
+    while n < lines.len() && !lines[n].is_empty() {
+        header.push(lines[n]);
+        n += 1;
+    }
+    vtt.header = header
+        .into_iter()
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut seen_cue = false;
+    while n < lines.len() {
+        if lines[n].is_empty() {
+            n += 1;
+            continue;
+        }
+        let start = n;
+        while n < lines.len() && !lines[n].is_empty() {
+            n += 1;
+        }
+        let block = &lines[start..n];
+        let first = block[0];
+
+        if is_keyword(first, "NOTE") {
+            let mut note = vec![first["NOTE".len()..].trim_start()];
+            note.extend(&block[1..]);
+            vtt.blocks.push(Block::Note(
+                note.into_iter()
+                    .filter(|l| !l.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ));
+            continue;
+        }
+        let has_timing = block.iter().take(2).any(|l| l.contains("-->"));
+        if !seen_cue && !has_timing && is_keyword(first, "STYLE") {
+            vtt.blocks.push(Block::Style(block[1..].join("\n")));
+            continue;
+        }
+        if !seen_cue && !has_timing && is_keyword(first, "REGION") {
+            vtt.blocks
+                .push(Block::Region(region(&block[1..].join(" "))));
+            continue;
+        }
+
+        let (identifier, timing_line) = if first.contains("-->") {
+            (None, 0)
+        } else if block.len() > 1 && block[1].contains("-->") {
+            (Some(first.to_owned()), 1)
+        } else {
+            // neither a cue nor anything else we know, browsers drop these
+            continue;
+        };
+
+        let line = block[timing_line];
+        let ((start_time, end_time), settings) = match timing(line) {
+            Ok((rest, times)) => (times, cue_settings(rest)),
+            Err(_) => {
+                return Err(ParseError {
+                    line: start + timing_line + 1,
+                    message: format!("invalid cue timing {:?}", line),
+                })
+            }
+        };
+
+        seen_cue = true;
+        vtt.blocks.push(Block::Cue(Cue {
+            identifier,
+            start: start_time,
+            end: end_time,
+            settings,
+            text: block[timing_line + 1..].join("\n"),
+        }));
+    }
+
+    Ok(vtt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        for (input, expected) in [
+            ("00:01.500", Some(1500)),
+            ("01:02:03.004", Some(3_723_004)),
+            ("100:00:00.000", Some(360_000_000)),
+            ("00:60.000", None),
+            ("60:00.000", None),
+            ("00:60:00.000", None),
+            ("00:00.50", None),
+            ("9999999999999999:00:00.000", None),
+        ] {
+            assert_eq!(
+                timestamp(input).ok().map(|(_, t)| t.as_millis() as u64),
+                expected,
+                "{:?}",
+                input
+            );
+        }
+        let err = parse("WEBVTT\n\n9999999999999999:00:00.000 --> 00:00:01.000\nx\n").unwrap_err();
+        assert_eq!(err.line, 3);
+    }
+
+    #[test]
+    fn settings() {
+        let settings = cue_settings(
+            "vertical:rl line:-2,end position:10%,line-left size:50% align:middle region:r1",
+        );
+        assert_eq!(
+            settings,
+            CueSettings {
+                vertical: Some(Vertical::RightToLeft),
+                line: Some((LineValue::Number(-2.0), Some(LineAlign::End))),
+                position: Some((10.0, Some(PositionAlign::LineLeft))),
+                size: Some(50.0),
+                align: Some(Align::Center),
+                region: Some("r1".to_owned()),
+            }
+        );
+        // malformed settings are ignored
+        for input in [
+            "vertical:up",
+            "line:50%,top",
+            "position:120%",
+            "size:abc",
+            "align:top",
+            "nonsense",
+            "line",
+        ] {
+            assert!(cue_settings(input).is_empty(), "{:?}", input);
+        }
+        assert_eq!(
+            cue_settings("line:25% position:auto").line,
+            Some((LineValue::Percent(25.0), None))
+        );
+    }
+
+    #[test]
+    fn blocks_and_identifiers() {
+        let vtt = parse(
+            "\u{feff}WEBVTT - Title\r\nKind: captions\r\n\r\n\
+             STYLE\n::cue { color: red }\n\n\
+             REGION\nid:r1 width:40% lines:3\nregionanchor:0%,100% viewportanchor:10%,90% scroll:up\n\n\
+             NOTE one line\n\n\
+             NOTE\nmore\nlines\n\n\
+             intro\n00:00:01.000 --> 00:00:02.000 region:r1\nHello\nthere\n\n\
+             00:03.000 --> 00:04.000\nBye\n\n\
+             STYLE\nafter a cue this is not a style block\n",
+        )
+        .unwrap();
+        assert_eq!(vtt.header, "- Title\nKind: captions");
+        assert_eq!(
+            vtt.blocks[..4],
+            [
+                Block::Style("::cue { color: red }".to_owned()),
+                Block::Region(Region {
+                    id: "r1".to_owned(),
+                    width: Some(40.0),
+                    lines: Some(3),
+                    region_anchor: Some((0.0, 100.0)),
+                    viewport_anchor: Some((10.0, 90.0)),
+                    scroll_up: true,
+                }),
+                Block::Note("one line".to_owned()),
+                Block::Note("more\nlines".to_owned()),
+            ]
+        );
+        let cues: Vec<&Cue> = vtt.cues().collect();
+        assert_eq!(vtt.blocks.len(), 6);
+        assert_eq!(cues[0].identifier.as_deref(), Some("intro"));
+        assert_eq!(cues[0].settings.region.as_deref(), Some("r1"));
+        assert_eq!(cues[0].text, "Hello\nthere");
+        assert_eq!(cues[1].identifier, None);
+        assert_eq!(cues[1].start, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn invalid_files() {
+        for (input, line) in [
+            ("", 1),
+            ("WEBVTTX\n", 1),
+            ("WEBVTT\n\n00:00:01.000 -->\nx\n", 3),
+            ("WEBVTT\n\nid\n00:00:01.000 --> 00:00:02\nx\n", 4),
+        ] {
+            assert_eq!(parse(input).unwrap_err().line, line, "{:?}", input);
+        }
+    }
+}
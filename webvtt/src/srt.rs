@@ -0,0 +1,197 @@
+//! Conversion to and from SubRip entries.
+
+use crate::text::{Node, SpanKind};
+use crate::{Align, Block, Cue, CueSettings, LineValue, WebVtt};
+use subrip::text::{Span, TextStyle};
+
+// the color classes every WebVTT renderer knows
+const COLORS: [(&str, (u8, u8, u8)); 8] = [
+    ("white", (255, 255, 255)),
+    ("lime", (0, 255, 0)),
+    ("cyan", (0, 255, 255)),
+    ("red", (255, 0, 0)),
+    ("yellow", (255, 255, 0)),
+    ("magenta", (255, 0, 255)),
+    ("blue", (0, 0, 255)),
+    ("black", (0, 0, 0)),
+];
+
+/// Cue settings placing a line like an `{\anN}` tag would.
+pub fn alignment_settings(alignment: u8) -> CueSettings {
+    let mut settings = CueSettings::default();
+    match alignment {
+        7..=9 => settings.line = Some((LineValue::Percent(0.0), None)),
+        4..=6 => settings.line = Some((LineValue::Percent(50.0), None)),
+        _ => (),
+    }
+    match alignment {
+        1 | 4 | 7 => settings.align = Some(Align::Left),
+        3 | 6 | 9 => settings.align = Some(Align::Right),
+        _ => (),
+    }
+    settings
+}
+
+/// The `{\anN}` position closest to a cue's settings, if it isn't the default
+/// bottom center.
+pub fn settings_alignment(settings: &CueSettings) -> Option<u8> {
+    let row = match settings.line {
+        Some((LineValue::Percent(p), _)) if p < 33.4 => 7,
+        Some((LineValue::Percent(p), _)) if p < 66.7 => 4,
+        Some((LineValue::Number(n), _)) if n >= 0.0 => 7,
+        _ => 1,
+    };
+    let column = match settings.align {
+        Some(Align::Left) | Some(Align::Start) => 0,
+        Some(Align::Right) | Some(Align::End) => 2,
+        _ => 1,
+    };
+    Some(row + column).filter(|&a| a != 2)
+}
+
+fn wrap(kind: SpanKind, classes: Vec<String>, children: Vec<Node>) -> Node {
+    Node::Span {
+        kind,
+        classes,
+        annotation: None,
+        children,
+    }
+}
+
+pub(crate) fn span_node(span: &Span) -> Node {
+    let mut node = Node::Text(span.text.clone());
+    let style = &span.style;
+    if let Some(class) = style
+        .font
+        .rgb()
+        .and_then(|rgb| COLORS.iter().find(|(_, c)| *c == rgb))
+    {
+        node = wrap(SpanKind::Class, vec![class.0.to_owned()], vec![node]);
+    }
+    if style.underline {
+        node = wrap(SpanKind::Underline, Vec::new(), vec![node]);
+    }
+    if style.italic {
+        node = wrap(SpanKind::Italic, Vec::new(), vec![node]);
+    }
+    if style.bold {
+        node = wrap(SpanKind::Bold, Vec::new(), vec![node]);
+    }
+    node
+}
+
+// joins neighbouring spans of the same kind, so `<b>a </b><b><u>b</u></b>`
+// becomes `<b>a <u>b</u></b>`
+pub(crate) fn merge(nodes: Vec<Node>) -> Vec<Node> {
+    let mut out: Vec<Node> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match (out.last_mut(), node) {
+            (
+                Some(Node::Span {
+                    kind,
+                    classes,
+                    children,
+                    ..
+                }),
+                Node::Span {
+                    kind: next_kind,
+                    classes: next_classes,
+                    children: next_children,
+                    ..
+                },
+            ) if *kind == next_kind && *classes == next_classes => {
+                children.extend(next_children);
+                *children = merge(std::mem::take(children));
+            }
+            (_, node) => out.push(node),
+        }
+    }
+    out
+}
+
+/// Converts a SubRip entry to a cue. Bold, italic, underline and the basic
+/// colors carry over; strikeout, fonts and sizes have no WebVTT equivalent.
+pub fn cue_from_srt(entry: &subrip::Entry) -> Cue {
+    let nodes = merge(entry.parsed_text().iter().map(span_node).collect());
+    Cue {
+        identifier: None,
+        start: entry.start,
+        end: entry.end,
+        settings: entry.alignment.map(alignment_settings).unwrap_or_default(),
+        text: crate::text::write(&nodes),
+    }
+}
+
+pub fn from_srt(entries: &[subrip::Entry]) -> WebVtt {
+    WebVtt {
+        header: String::new(),
+        blocks: entries
+            .iter()
+            .map(|e| Block::Cue(cue_from_srt(e)))
+            .collect(),
+    }
+}
+
+fn spans(nodes: &[Node], style: &TextStyle, out: &mut Vec<Span>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => match out.last_mut() {
+                Some(last) if last.style == *style => last.text.push_str(text),
+                _ => out.push(Span {
+                    text: text.clone(),
+                    style: style.clone(),
+                }),
+            },
+            Node::Timestamp(_) => (),
+            // ruby annotations don't fit on a SubRip line
+            Node::Span {
+                kind: SpanKind::RubyText,
+                ..
+            } => (),
+            Node::Span {
+                kind,
+                classes,
+                children,
+                ..
+            } => {
+                let mut inner = style.clone();
+                match kind {
+                    SpanKind::Bold => inner.bold = true,
+                    SpanKind::Italic => inner.italic = true,
+                    SpanKind::Underline => inner.underline = true,
+                    _ => (),
+                }
+                if let Some(color) = classes
+                    .iter()
+                    .find(|c| COLORS.iter().any(|(name, _)| name == c))
+                {
+                    let (_, (r, g, b)) = COLORS.iter().find(|(name, _)| name == color).unwrap();
+                    inner.font.color = Some(format!("#{:02x}{:02x}{:02x}", r, g, b));
+                }
+                spans(children, &inner, out);
+            }
+        }
+    }
+}
+
+/// Converts a cue to a SubRip entry numbered `index`, keeping bold, italic,
+/// underline, color classes and a rough `{\anN}` position.
+pub fn cue_to_srt(cue: &Cue, index: u32) -> subrip::Entry {
+    let mut out = Vec::new();
+    spans(&cue.parsed_text(), &TextStyle::default(), &mut out);
+    subrip::Entry {
+        index,
+        start: cue.start,
+        end: cue.end,
+        alignment: settings_alignment(&cue.settings),
+        coordinates: None,
+        text: subrip::text::write(&out),
+    }
+}
+
+pub fn to_srt(vtt: &WebVtt) -> Vec<subrip::Entry> {
+    vtt.cues()
+        .enumerate()
+        .map(|(n, cue)| cue_to_srt(cue, n as u32 + 1))
+        .collect()
+}
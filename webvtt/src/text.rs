@@ -0,0 +1,340 @@
+//! Cue text: class, italic, bold, underline, ruby, voice and language spans,
+//! in-cue timestamps and character references.
+
+use parsing_utils::entity;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+    Class,
+    Italic,
+    Bold,
+    Underline,
+    Ruby,
+    RubyText,
+    Voice,
+    Lang,
+}
+
+impl SpanKind {
+    fn from_tag(name: &str) -> Option<SpanKind> {
+        Some(match name {
+            "c" => SpanKind::Class,
+            "i" => SpanKind::Italic,
+            "b" => SpanKind::Bold,
+            "u" => SpanKind::Underline,
+            "ruby" => SpanKind::Ruby,
+            "rt" => SpanKind::RubyText,
+            "v" => SpanKind::Voice,
+            "lang" => SpanKind::Lang,
+            _ => return None,
+        })
+    }
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            SpanKind::Class => "c",
+            SpanKind::Italic => "i",
+            SpanKind::Bold => "b",
+            SpanKind::Underline => "u",
+            SpanKind::Ruby => "ruby",
+            SpanKind::RubyText => "rt",
+            SpanKind::Voice => "v",
+            SpanKind::Lang => "lang",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Text(String),
+    /// `<00:00:01.000>`, when the following text becomes current.
+    Timestamp(Duration),
+    Span {
+        kind: SpanKind,
+        classes: Vec<String>,
+        /// The voice name of `<v>` or the language of `<lang>`.
+        annotation: Option<String>,
+        children: Vec<Node>,
+    },
+}
+
+struct Open {
+    kind: SpanKind,
+    classes: Vec<String>,
+    annotation: Option<String>,
+    children: Vec<Node>,
+}
+
+fn push_text(nodes: &mut Vec<Node>, text: &mut String) {
+    if text.is_empty() {
+        return;
+    }
+    match nodes.last_mut() {
+        Some(Node::Text(last)) => last.push_str(text),
+        _ => nodes.push(Node::Text(text.clone())),
+    }
+    text.clear();
+}
+
+fn close(stack: &mut Vec<Open>, root: &mut Vec<Node>) {
+    let open = stack.pop().expect("closing with an empty stack");
+    let node = Node::Span {
+        kind: open.kind,
+        classes: open.classes,
+        annotation: open.annotation,
+        children: open.children,
+    };
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => root.push(node),
+    }
+}
+
+/// Parses cue text into a tree of spans. End tags close the innermost span
+/// of their kind (and anything opened inside it); unknown tags are dropped
+/// and unclosed spans end with the cue.
+pub fn parse(input: &str) -> Vec<Node> {
+    let mut root: Vec<Node> = Vec::new();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut text = String::new();
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '&' => {
+                if let Some((c, len)) = entity::decode(rest) {
+                    text.push(c);
+                    rest = &rest[len..];
+                } else {
+                    text.push('&');
+                    rest = &rest[1..];
+                }
+            }
+            '<' => {
+                let len = rest.find('>').map(|n| n + 1).unwrap_or(rest.len());
+                let tag = rest[1..len].trim_end_matches('>');
+                rest = &rest[len..];
+
+                let nodes = match stack.last_mut() {
+                    Some(open) => &mut open.children,
+                    None => &mut root,
+                };
+                push_text(nodes, &mut text);
+
+                if let Some(name) = tag.strip_prefix('/') {
+                    let kind = SpanKind::from_tag(name.trim());
+                    if let Some(n) = stack.iter().rposition(|o| Some(&o.kind) == kind.as_ref()) {
+                        while stack.len() > n {
+                            close(&mut stack, &mut root);
+                        }
+                    }
+                } else if tag.starts_with(|c: char| c.is_ascii_digit()) {
+                    if let Ok(("", time)) = crate::timestamp(tag) {
+                        nodes.push(Node::Timestamp(time));
+                    }
+                } else {
+                    let (name, annotation) = match tag.split_once([' ', '\t']) {
+                        Some((name, annotation)) => (name, Some(annotation.trim().to_owned())),
+                        None => (tag, None),
+                    };
+                    let mut classes = name.split('.');
+                    let Some(kind) = classes.next().and_then(SpanKind::from_tag) else {
+                        continue;
+                    };
+                    // a new <rt> or <ruby> closes an open <rt>
+                    if matches!(kind, SpanKind::RubyText | SpanKind::Ruby)
+                        && stack.last().is_some_and(|o| o.kind == SpanKind::RubyText)
+                    {
+                        close(&mut stack, &mut root);
+                    }
+                    let annotation = match kind {
+                        SpanKind::Voice | SpanKind::Lang => annotation.filter(|a| !a.is_empty()),
+                        _ => None,
+                    };
+                    stack.push(Open {
+                        kind,
+                        classes: classes
+                            .filter(|c| !c.is_empty())
+                            .map(|c| c.to_owned())
+                            .collect(),
+                        annotation,
+                        children: Vec::new(),
+                    });
+                }
+            }
+            c => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    let nodes = match stack.last_mut() {
+        Some(open) => &mut open.children,
+        None => &mut root,
+    };
+    push_text(nodes, &mut text);
+    while !stack.is_empty() {
+        close(&mut stack, &mut root);
+    }
+
+    root
+}
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\u{200e}' => out.push_str("&lrm;"),
+            '\u{200f}' => out.push_str("&rlm;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn write_nodes(nodes: &[Node], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => escape(text, out),
+            Node::Timestamp(time) => {
+                out.push('<');
+                out.push_str(&crate::writer::format_timestamp(*time));
+                out.push('>');
+            }
+            Node::Span {
+                kind,
+                classes,
+                annotation,
+                children,
+            } => {
+                out.push('<');
+                out.push_str(kind.tag());
+                for class in classes {
+                    out.push('.');
+                    out.push_str(class);
+                }
+                if let Some(annotation) = annotation {
+                    out.push(' ');
+                    escape(annotation, out);
+                }
+                out.push('>');
+                write_nodes(children, out);
+                out.push_str("</");
+                out.push_str(kind.tag());
+                out.push('>');
+            }
+        }
+    }
+}
+
+pub fn write(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    write_nodes(nodes, &mut out);
+    out
+}
+
+/// The text of the nodes without any markup.
+pub fn plain_text(nodes: &[Node]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Span { children, .. } => out.push_str(&plain_text(children)),
+            Node::Timestamp(_) => (),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_ascii_after_ampersand() {
+        let nodes = parse("a &éééééé b &amp;");
+        assert_eq!(nodes, vec![Node::Text("a &éééééé b &".to_owned())]);
+    }
+
+    #[test]
+    fn non_ascii_after_ampersand_in_a_file() {
+        use crate::format::WebVttFormat;
+        use parsing_utils::format::SubtitleFormat;
+
+        let input = "WEBVTT\n\n00:00:01.000 --> 00:00:02.000\na &éééééé b\n";
+        let cues = WebVttFormat.parse(input).unwrap();
+        assert_eq!(cues[0].text, "a &éééééé b");
+    }
+
+    #[test]
+    fn nested_spans() {
+        let nodes = parse(
+            "<v.loud Bob><i>a<b>b</i>c</v><c.x.y>d&lt;</c><00:00:01.500>e<ruby>f<rt>g<rt>h</ruby>",
+        );
+        let span = |kind, classes: &[&str], annotation: Option<&str>, children| Node::Span {
+            kind,
+            classes: classes.iter().map(|c| c.to_string()).collect(),
+            annotation: annotation.map(|a| a.to_owned()),
+            children,
+        };
+        let text = |t: &str| Node::Text(t.to_owned());
+        assert_eq!(
+            nodes,
+            [
+                span(
+                    SpanKind::Voice,
+                    &["loud"],
+                    Some("Bob"),
+                    vec![
+                        span(
+                            SpanKind::Italic,
+                            &[],
+                            None,
+                            vec![text("a"), span(SpanKind::Bold, &[], None, vec![text("b")])]
+                        ),
+                        text("c"),
+                    ]
+                ),
+                span(SpanKind::Class, &["x", "y"], None, vec![text("d<")]),
+                Node::Timestamp(Duration::from_millis(1500)),
+                text("e"),
+                span(
+                    SpanKind::Ruby,
+                    &[],
+                    None,
+                    vec![
+                        text("f"),
+                        span(SpanKind::RubyText, &[], None, vec![text("g")]),
+                        span(SpanKind::RubyText, &[], None, vec![text("h")]),
+                    ]
+                ),
+            ]
+        );
+        assert_eq!(plain_text(&nodes), "abcd<efgh");
+        assert_eq!(
+            write(&nodes),
+            "<v.loud Bob><i>a<b>b</b></i>c</v><c.x.y>d&lt;</c><00:00:01.500>e\
+             <ruby>f<rt>g</rt><rt>h</rt></ruby>"
+        );
+    }
+
+    #[test]
+    fn unknown_and_unclosed_tags() {
+        assert_eq!(
+            parse("<font>a</font><i>b"),
+            [
+                Node::Text("a".to_owned()),
+                Node::Span {
+                    kind: SpanKind::Italic,
+                    classes: Vec::new(),
+                    annotation: None,
+                    children: vec![Node::Text("b".to_owned())],
+                },
+            ]
+        );
+        assert_eq!(parse("a</b>c<00:99.000>"), [Node::Text("ac".to_owned())]);
+    }
+}
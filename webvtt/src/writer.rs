@@ -0,0 +1,208 @@
+use crate::{
+    Align, Block, Cue, CueSettings, LineAlign, LineValue, PositionAlign, Region, Vertical, WebVtt,
+};
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// `HH:MM:SS.mmm`, with more hour digits if needed.
+pub fn format_timestamp(time: Duration) -> String {
+    let ms = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    )
+}
+
+fn percent(value: f64) -> String {
+    format!("{}%", (value * 1000.0).round() / 1000.0)
+}
+
+impl fmt::Display for CueSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut settings: Vec<String> = Vec::new();
+        if let Some(region) = &self.region {
+            settings.push(format!("region:{}", region));
+        }
+        if let Some(vertical) = self.vertical {
+            settings.push(
+                match vertical {
+                    Vertical::RightToLeft => "vertical:rl",
+                    Vertical::LeftToRight => "vertical:lr",
+                }
+                .to_owned(),
+            );
+        }
+        if let Some((value, align)) = self.line {
+            let mut line = match value {
+                LineValue::Percent(p) => format!("line:{}", percent(p)),
+                LineValue::Number(n) => format!("line:{}", n),
+            };
+            if let Some(align) = align {
+                line.push_str(match align {
+                    LineAlign::Start => ",start",
+                    LineAlign::Center => ",center",
+                    LineAlign::End => ",end",
+                });
+            }
+            settings.push(line);
+        }
+        if let Some((value, align)) = self.position {
+            let mut position = format!("position:{}", percent(value));
+            if let Some(align) = align {
+                position.push_str(match align {
+                    PositionAlign::LineLeft => ",line-left",
+                    PositionAlign::Center => ",center",
+                    PositionAlign::LineRight => ",line-right",
+                });
+            }
+            settings.push(position);
+        }
+        if let Some(size) = self.size {
+            settings.push(format!("size:{}", percent(size)));
+        }
+        if let Some(align) = self.align {
+            settings.push(
+                match align {
+                    Align::Start => "align:start",
+                    Align::Center => "align:center",
+                    Align::End => "align:end",
+                    Align::Left => "align:left",
+                    Align::Right => "align:right",
+                }
+                .to_owned(),
+            );
+        }
+        f.write_str(&settings.join(" "))
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "REGION")?;
+        writeln!(f, "id:{}", self.id)?;
+        if let Some(width) = self.width {
+            writeln!(f, "width:{}", percent(width))?;
+        }
+        if let Some(lines) = self.lines {
+            writeln!(f, "lines:{}", lines)?;
+        }
+        if let Some((x, y)) = self.region_anchor {
+            writeln!(f, "regionanchor:{},{}", percent(x), percent(y))?;
+        }
+        if let Some((x, y)) = self.viewport_anchor {
+            writeln!(f, "viewportanchor:{},{}", percent(x), percent(y))?;
+        }
+        if self.scroll_up {
+            writeln!(f, "scroll:up")?;
+        }
+        Ok(())
+    }
+}
+
+/// A cue block, ending in a newline. Blank lines in the text, which would end
+/// the cue early, are dropped.
+impl fmt::Display for Cue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(identifier) = &self.identifier {
+            writeln!(f, "{}", identifier)?;
+        }
+        write!(
+            f,
+            "{} --> {}",
+            format_timestamp(self.start),
+            format_timestamp(self.end)
+        )?;
+        if !self.settings.is_empty() {
+            write!(f, " {}", self.settings)?;
+        }
+        writeln!(f)?;
+        for line in self.text.lines().filter(|l| !l.is_empty()) {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Block::Cue(cue) => cue.fmt(f),
+            Block::Style(css) => writeln!(f, "STYLE\n{}", css),
+            Block::Region(region) => region.fmt(f),
+            Block::Note(note) if note.contains('\n') => writeln!(f, "NOTE\n{}", note),
+            Block::Note(note) => writeln!(f, "NOTE {}", note),
+        }
+    }
+}
+
+impl fmt::Display for WebVtt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut header = self.header.lines();
+        match header.next() {
+            Some(first) if !first.is_empty() => writeln!(f, "WEBVTT {}", first)?,
+            _ => writeln!(f, "WEBVTT")?,
+        }
+        for line in header {
+            writeln!(f, "{}", line)?;
+        }
+        for block in &self.blocks {
+            writeln!(f)?;
+            block.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn write_vtt<W: Write>(mut writer: W, vtt: &WebVtt) -> io::Result<()> {
+    write!(writer, "{}", vtt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        for (millis, expected) in [
+            (0, "00:00:00.000"),
+            (3_723_004, "01:02:03.004"),
+            (360_000_000, "100:00:00.000"),
+        ] {
+            assert_eq!(format_timestamp(Duration::from_millis(millis)), expected);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "WEBVTT - Title\nKind: captions\n\n\
+                     STYLE\n::cue(.x) { color: red }\n\n\
+                     REGION\nid:r1\nwidth:40%\nlines:3\nregionanchor:0%,100%\nviewportanchor:10%,90%\nscroll:up\n\n\
+                     NOTE one line\n\n\
+                     NOTE\ntwo\nlines\n\n\
+                     intro\n00:00:01.000 --> 00:00:02.500 region:r1 line:-2,end position:10%,line-left size:50% align:start\n<c.x>Hello</c>\nthere\n\n\
+                     00:00:03.000 --> 01:00:04.000\nBye &amp; <b>bye</b>\n";
+        let vtt = crate::parse(input).unwrap();
+        let written = vtt.to_string();
+        assert_eq!(written, input);
+        assert_eq!(crate::parse(&written).unwrap(), vtt);
+
+        let mut out = Vec::new();
+        write_vtt(&mut out, &vtt).unwrap();
+        assert_eq!(out, input.as_bytes());
+    }
+
+    #[test]
+    fn blank_lines_in_text_are_dropped() {
+        let cue = Cue {
+            start: Duration::from_secs(1),
+            end: Duration::from_secs(2),
+            text: "a\n\nb".to_owned(),
+            ..Cue::default()
+        };
+        assert_eq!(cue.to_string(), "00:00:01.000 --> 00:00:02.000\na\nb\n");
+    }
+}